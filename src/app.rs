@@ -1,51 +1,225 @@
-use crate::constants::constants::DEFAULT_PIXELS_PER_POINT;
+use crate::config::{Config, ConfigError, ConfigWatcher};
+use crate::constants::constants::DEFAULT_SPLIT_LIMIT;
+use crate::export::{self, ExportFormat};
+use crate::history::{Change, History, REDO_SHORTCUT, UNDO_SHORTCUT};
+use crate::keymap::{Action, Keymap};
+use crate::message::CopyTarget;
+use crate::migrate::LegacyAppState;
 use crate::panels;
-use crate::scene::{Mode, Scene, TextSlot};
-use egui::{Key, widgets};
+use crate::panels::central::SlotContext;
+use crate::panels::dice::DiceState;
+use crate::panels::keymap::KeymapState;
+use crate::panels::mode_editor::ModeEditorState;
+use crate::panels::palette::{PaletteAction, PaletteState};
+use crate::panels::player_view::{PlayerViewState, Revealed};
+use crate::panels::queue::CopyQueue;
+use crate::panels::roster::RosterState;
+use crate::panels::search::{SEARCH_SHORTCUT, SearchAction, SearchState};
+use crate::panels::side::{SceneAction, SceneListState};
+use crate::panels::variables::VariablesState;
+use crate::parser::dice::DiceRng;
+use crate::parser::markdown::{self, ImportWarning};
+use crate::placeholder::Bindings;
+use crate::scenario::Scenario;
+use crate::scene::{self, Id, Scene};
+use crate::search::{self, Hit, Location, Replacement};
+use crate::system::{GameSystem, SystemSwitchPlan};
+use crate::widgets::icon::IconCache;
+use egui::Key;
 use egui_notify::Toasts;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 /*
 TODO:アイコン/名前表示
-TODO:デフォルトの形をどうするか？▶設定ファイルと整合
-TODO:ファイルIO
 TODO:パーサー
 TODO:それのやり取りするInterface(Trate)
-TODO:設定ファイル追加
-TODO:ショートカットキー(一部追加済)
 */
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct MemoApp {
-    #[serde(default)]
-    scenes: Vec<Scene>,
-    modes: Vec<Mode>,
+    scenario: Scenario,
+    /// 選択中のシーン（並べ替えや削除の後も同じシーンを指す）
+    selected_scene: Id,
+    #[serde(skip)]
     selected_scene_index: usize,
     app_mode: AppMode,
-    player: Vec<Player_default>,
+    #[serde(default)]
+    copy: CopySettings,
     #[serde(skip)]
     toasts: Toasts,
     #[serde(skip)]
     modal: Modal,
     #[serde(skip)]
     create_index: usize,
+    #[serde(skip)]
+    file: FileState,
+    #[serde(skip)]
+    icons: IconCache,
+    #[serde(skip)]
+    history: History,
+    #[serde(skip)]
+    rng: DiceRng,
+    #[serde(skip)]
+    config: Config,
+    #[serde(skip)]
+    config_watcher: ConfigWatcher,
+    #[serde(skip)]
+    keymap: Keymap,
 }
 
-#[derive(serde::Deserialize, serde::Serialize)]
-pub struct Player_default {
-    name: String,
-    icon_path: Option<PathBuf>,
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq, Eq)]
+pub struct Player {
+    /// スロットの話者として参照されるID
+    #[serde(default = "Id::new")]
+    pub id: Id,
+    pub name: String,
+    pub icon_path: Option<PathBuf>,
+    /// コピー時に`{プレイヤー名}`を置き換えるキャラクター名（空ならプレイヤー名のまま）
+    #[serde(default)]
+    pub character: String,
 }
 
-#[derive(serde::Deserialize, serde::Serialize, PartialEq)]
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq)]
 pub enum AppMode {
     Edit,
     Copy,
 }
 
+/// コピーモードでのコピーの設定
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct CopySettings {
+    /// 技能判定で判定を選んだらテキストをコピーする
+    pub after_check: bool,
+    /// 長いテキストを分割してコピーする
+    pub split: bool,
+    /// 分割するときの1回の最大文字数
+    pub split_limit: usize,
+    /// 貼り付け先に合わせた書式
+    pub target: CopyTarget,
+}
+
+impl Default for CopySettings {
+    fn default() -> Self {
+        Self {
+            after_check: false,
+            split: false,
+            split_limit: DEFAULT_SPLIT_LIMIT,
+            target: CopyTarget::default(),
+        }
+    }
+}
+
+impl CopySettings {
+    /// 分割する場合の1回の最大文字数
+    pub fn split_limit(&self) -> Option<usize> {
+        self.split.then_some(self.split_limit)
+    }
+}
+
+/// メニューバーから発行されるコマンド
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MenuCommand {
+    New,
+    Open,
+    Save,
+    SaveAs,
+    ImportMarkdown,
+    Export(ExportFormat),
+    Quit,
+    SwitchSystem(GameSystem),
+    OpenModeEditor,
+    OpenRoster,
+    OpenVariables,
+    OpenDice,
+    OpenChatPalette,
+    OpenPlayerView,
+    ClearPlayerView,
+    OpenKeymap,
+    CopyConfigPath,
+    Undo,
+    Redo,
+    OpenSearch,
+}
+
+/// 未保存の変更を破棄する前に確認が必要な操作
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PendingAction {
+    New,
+    Open,
+    Quit,
+}
+
+/// 開いているシナリオファイルの状態
+#[derive(Default)]
+pub struct FileState {
+    /// 保存先のパス（未保存の新規シナリオはNone）
+    pub path: Option<PathBuf>,
+    /// 最後に保存/読み込みした時点の編集履歴のリビジョン
+    pub saved_revision: u64,
+    /// 未保存の変更があるか
+    pub dirty: bool,
+    /// 未保存確認モーダルで保留中の操作
+    pub pending: Option<PendingAction>,
+    /// 確認済みでウィンドウを閉じてよいか
+    pub close_confirmed: bool,
+    /// 最後に設定したウィンドウタイトル
+    pub window_title: String,
+}
+
+impl FileState {
+    pub fn new(history: &History) -> Self {
+        Self {
+            saved_revision: history.revision(),
+            ..Default::default()
+        }
+    }
+
+    /// タイトルバーに表示するファイル名
+    pub fn display_name(&self) -> String {
+        self.path
+            .as_deref()
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("無題"))
+    }
+}
+
 #[derive(Default)]
 pub struct Modal {
     pub editing_scene_name_modal_open: bool,
     pub editing_scene_name_buffer: String,
     pub editing_scene_delete_modal_open: bool,
+    /// 削除確認中のシーン（空なら選択中のシーン）
+    pub scene_delete: Vec<Id>,
+    /// シーン一覧の複数選択
+    pub scene_list: SceneListState,
+    /// 削除確認中のスロット（シーンID, スロットID）
+    pub slot_delete: Option<(Id, Id)>,
+    /// 次のフレームでスクロールして表示するスロット
+    pub scroll_to_slot: Option<Id>,
+    /// 直前のインポートで出た警告（空でなければ結果ウィンドウを表示）
+    pub import_warnings: Vec<ImportWarning>,
+    /// ゲームシステム切り替えのプレビュー中の計画
+    pub system_switch_plan: Option<SystemSwitchPlan>,
+    /// モード/判定編集ウィンドウ
+    pub mode_editor: ModeEditorState,
+    /// プレイヤー一覧の編集ウィンドウ
+    pub roster: RosterState,
+    /// 検索ウィンドウ
+    pub search: SearchState,
+    /// 変数一覧の編集ウィンドウ
+    pub variables: VariablesState,
+    /// ダイスロールウィンドウ
+    pub dice: DiceState,
+    /// コピーモードでスロットを順にコピーするキュー
+    pub copy_queue: CopyQueue,
+    /// ccfoliaのチャットパレットウィンドウ
+    pub palette: PaletteState,
+    /// プレイヤー用ウィンドウ
+    pub player_view: PlayerViewState,
+    /// ショートカットキー設定ウィンドウ
+    pub keymap: KeymapState,
+    /// 最後にフォーカスしたスロット（ショートカットキーの対象）
+    pub focused_slot: Option<Id>,
 }
 
 impl Modal {
@@ -54,195 +228,334 @@ impl Modal {
             editing_scene_name_modal_open: false,
             editing_scene_name_buffer: String::new(),
             editing_scene_delete_modal_open: false,
+            scene_delete: Vec::new(),
+            scene_list: SceneListState::default(),
+            slot_delete: None,
+            scroll_to_slot: None,
+            import_warnings: Vec::new(),
+            system_switch_plan: None,
+            mode_editor: ModeEditorState::default(),
+            roster: RosterState::default(),
+            search: SearchState::default(),
+            variables: VariablesState::default(),
+            dice: DiceState::default(),
+            copy_queue: CopyQueue::default(),
+            palette: PaletteState::default(),
+            player_view: PlayerViewState::default(),
+            keymap: KeymapState::default(),
+            focused_slot: None,
         }
     }
 }
 
-impl Default for Player_default {
+impl Default for Player {
     fn default() -> Self {
         Self {
+            id: Id::new(),
             name: String::new(),
             icon_path: None,
+            character: String::new(),
         }
     }
 }
 
-#[allow(clippy::too_many_lines)]
 impl Default for MemoApp {
     fn default() -> Self {
-        Self {
-            scenes: vec![
-                Scene {
-                    title: String::from("シーン1"),
-                    mode_index: 1,
-                    contents: vec![
-                        vec![TextSlot {
-                            texts: vec![String::from("地の文1")],
-                            selected_judge_index: 0,
-                            label: None,
-                            icon_path: None,
-                        }],
-                        vec![TextSlot {
-                            texts: vec![
-                                String::from("大成功1"),
-                                String::from("成功1"),
-                                String::from("失敗1"),
-                                String::from("ファンブル1"),
-                            ],
-                            selected_judge_index: 0,
-                            label: None,
-                            icon_path: None,
-                        }],
-                        vec![TextSlot {
-                            texts: vec![
-                                String::from("大成功1"),
-                                String::from("成功1"),
-                                String::from("失敗1"),
-                                String::from("ファンブル1"),
-                            ],
-                            selected_judge_index: 0,
-                            label: None,
-                            icon_path: None,
-                        }],
-                    ],
-                    layout_cache: None,
-                },
-                Scene {
-                    title: String::from("シーン2"),
-                    mode_index: 1,
-                    contents: vec![
-                        vec![TextSlot {
-                            texts: vec![String::from("地の文2")],
-                            selected_judge_index: 0,
-                            label: None,
-                            icon_path: None,
-                        }],
-                        vec![TextSlot {
-                            texts: vec![
-                                String::from("大成功2"),
-                                String::from("成功2"),
-                                String::from("失敗2"),
-                                String::from("ファンブル2"),
-                            ],
-                            selected_judge_index: 0,
-                            label: None,
-                            icon_path: None,
-                        }],
-                        vec![TextSlot {
-                            texts: vec![
-                                String::from("大成功1"),
-                                String::from("成功1"),
-                                String::from("失敗1"),
-                                String::from("ファンブル1"),
-                            ],
-                            selected_judge_index: 0,
-                            label: None,
-                            icon_path: None,
-                        }],
-                    ],
-                    layout_cache: None,
-                },
-                Scene {
-                    title: String::from("シーン3"),
-                    mode_index: 1,
-                    contents: vec![
-                        vec![TextSlot {
-                            texts: vec![String::from("地の文3")],
-                            selected_judge_index: 0,
-                            label: None,
-                            icon_path: None,
-                        }],
-                        vec![TextSlot {
-                            texts: vec![
-                                String::from("大成功3"),
-                                String::from("成功3"),
-                                String::from("失敗3"),
-                                String::from("ファンブル3"),
-                            ],
-                            selected_judge_index: 0,
-                            label: None,
-                            icon_path: None,
-                        }],
-                        vec![TextSlot {
-                            texts: vec![
-                                String::from("大成功1"),
-                                String::from("成功1"),
-                                String::from("失敗1"),
-                                String::from("ファンブル1"),
-                            ],
-                            selected_judge_index: 0,
-                            label: None,
-                            icon_path: None,
-                        }],
-                    ],
-                    layout_cache: None,
-                },
-            ],
-            modes: vec![
-                Mode {
-                    name: String::from("地の文"),
-                    judges: vec![String::from("本文")],
-                    default_text_num: 1,
-                },
-                Mode {
-                    name: String::from("プレイヤー"),
-                    judges: vec![
-                        String::from("大成功"),
-                        String::from("成功"),
-                        String::from("失敗"),
-                        String::from("ファンブル"),
-                    ],
-                    default_text_num: 4,
-                },
-                Mode {
-                    name: String::from("探索"),
-                    judges: vec![
-                        String::from("大成功"),
-                        String::from("成功"),
-                        String::from("失敗"),
-                        String::from("ファンブル"),
-                    ],
-                    default_text_num: 4,
-                },
-            ],
+        let config_watcher = ConfigWatcher::new();
+        let (config, config_error) = match config_watcher.load() {
+            Ok(config) => (config, None),
+            Err(err) => (Config::default(), Some(err)),
+        };
+        // 設定ファイルでモード/プレイヤー一覧を決めていなければサンプルのシナリオ
+        let scenario = if config.has_scenario_defaults() {
+            Scenario::blank(GameSystem::Generic, &config)
+        } else {
+            Scenario::default()
+        };
+        let history = History::new();
+        let file = FileState::new(&history);
+        let mut app = Self {
+            create_index: scenario.scenes.len() + 1,
+            scenario,
+            selected_scene: Id::default(),
             selected_scene_index: 0,
-            create_index: 1,
             app_mode: AppMode::Edit,
+            copy: config.copy.unwrap_or_default(),
             toasts: Toasts::default(),
-            player: vec![
-                Player_default {
-                    name: String::from("地の文"),
-                    icon_path: None,
-                },
-                Player_default {
-                    name: String::from("探偵"),
-                    icon_path: None,
-                },
-                Player_default {
-                    name: String::from("助手"),
-                    icon_path: None,
-                },
-                Player_default {
-                    name: String::from("医者"),
-                    icon_path: None,
-                },
-                Player_default {
-                    name: String::from("怪盗"),
-                    icon_path: None,
-                },
-            ],
             modal: Modal::new(),
+            file,
+            icons: IconCache::default(),
+            history,
+            rng: DiceRng::default(),
+            config,
+            config_watcher,
+            keymap: Keymap::default(),
+        };
+        if let Some(err) = config_error {
+            app.apply_config(Err(err));
         }
+        app.load_keymap();
+        app
     }
 }
 
 #[warn(dead_code)]
 impl MemoApp {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        if let Some(storage) = cc.storage {
-            eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
+        let Some(storage) = cc.storage else {
+            return Self::default();
+        };
+
+        if let Some(app) = eframe::get_value::<Self>(storage, eframe::APP_KEY) {
+            let mut app = app.restored();
+            app.config_watcher = ConfigWatcher::new();
+            let config = app.config_watcher.load();
+            app.apply_config(config);
+            app.load_keymap();
+            return app;
+        }
+        if let Some(legacy) = eframe::get_value::<LegacyAppState>(storage, eframe::APP_KEY) {
+            // 位置で参照していた古い保存データはIDで参照する形式に変換する
+            // （設定ファイルとキーマップはdefault()で読み込み済み）
+            let (scenario, selected_scene, dropped) = legacy.into_scenario();
+            let mut app = Self {
+                scenario,
+                selected_scene,
+                ..Self::default()
+            }
+            .restored();
+            app.warn_dropped_texts(dropped);
+            return app;
+        }
+        Self::default()
+    }
+
+    /// 古い形式からの変換で破棄したテキストがあれば知らせる
+    fn warn_dropped_texts(&mut self, dropped: usize) {
+        if dropped > 0 {
+            self.toasts
+                .warning(format!(
+                    "古い形式のシナリオを変換しました。対応するモード/判定がない{dropped}件のテキストは破棄しました"
+                ))
+                .duration(Some(std::time::Duration::from_secs(10)));
+        }
+    }
+
+    /// 保存データから復元したシナリオに合わせて、保存しない状態を作り直す
+    fn restored(mut self) -> Self {
+        self.scenario.normalize();
+        self.history = History::new();
+        self.file = FileState::new(&self.history);
+        self.create_index = self.scenario.scenes.len() + 1;
+        self
+    }
+
+    /// キーマップファイルを読み込む（ファイルがなければ既定のキー、不正な行の操作も既定のキー）
+    fn load_keymap(&mut self) {
+        let Some(path) = Keymap::path().filter(|path| path.exists()) else {
+            return;
+        };
+        match Keymap::load(&path) {
+            Ok((keymap, problems)) => {
+                self.keymap = keymap;
+                if !problems.is_empty() {
+                    self.toasts
+                        .warning(format!(
+                            "キーマップファイルの次の行は既定のキーのままにしました:\n{}",
+                            problems.join("\n")
+                        ))
+                        .duration(Some(std::time::Duration::from_secs(10)));
+                }
+            }
+            Err(err) => {
+                self.toasts
+                    .error(format!("キーマップファイル: {err}"))
+                    .duration(Some(std::time::Duration::from_secs(10)));
+            }
+        }
+    }
+
+    /// キーマップファイルに保存する
+    fn save_keymap(&mut self) {
+        let Some(path) = Keymap::path() else {
+            return;
+        };
+        if let Err(err) = self.keymap.save(&path) {
+            self.toasts
+                .error(format!("キーマップファイルを保存できませんでした: {err}"));
+        }
+    }
+
+    /// 押されたショートカットキーの操作を実行する
+    fn handle_keymap(&mut self, ctx: &egui::Context) {
+        if self.modal.keymap.is_capturing() {
+            return;
+        }
+        let Some(action) = self.keymap.pressed(ctx) else {
+            return;
+        };
+        let last = self.scenario.scenes.len().saturating_sub(1);
+        match action {
+            Action::NextScene => {
+                self.selected_scene_index = (self.selected_scene_index + 1).min(last);
+            }
+            Action::PrevScene => {
+                self.selected_scene_index = self.selected_scene_index.saturating_sub(1);
+            }
+            Action::NextMode | Action::PrevMode => self.cycle_mode(action == Action::NextMode),
+            Action::SelectJudge(number) => self.select_judge(number),
+            Action::CopySlot => self.copy_focused_slot(ctx),
+            Action::AddSlot => {
+                let modes = &self.scenario.modes;
+                if let Some(scene) = self.scenario.scenes.get_mut(self.selected_scene_index) {
+                    if let Some(mode) = scene::find_mode(modes, scene.mode_id) {
+                        let slot = scene.push_slot(mode);
+                        self.history.push(
+                            "スロットの追加",
+                            Change::RemoveSlot {
+                                scene: scene.id,
+                                slot,
+                            },
+                        );
+                    }
+                }
+            }
+            Action::AddScene => {
+                self.apply_scene_action(SceneAction::Insert(self.selected_scene_index + 1));
+            }
+            Action::ToggleAppMode => {
+                self.app_mode = match self.app_mode {
+                    AppMode::Edit => AppMode::Copy,
+                    AppMode::Copy => AppMode::Edit,
+                };
+            }
+        }
+    }
+
+    /// 選択中のシーンのモードを前後に切り替える（端では反対側へ回る）
+    fn cycle_mode(&mut self, forward: bool) {
+        let modes = &self.scenario.modes;
+        let Some(scene) = self.scenario.scenes.get_mut(self.selected_scene_index) else {
+            return;
+        };
+        let count = modes.len();
+        if count == 0 {
+            return;
+        }
+        let position = scene::mode_position(modes, scene.mode_id).unwrap_or(0);
+        let next = if forward {
+            (position + 1) % count
         } else {
-            Default::default()
+            (position + count - 1) % count
+        };
+        if let Some(mode) = modes.get(next) {
+            scene.set_mode(mode);
+        }
+    }
+
+    /// フォーカス中のスロット（選択中のシーンとモードにある場合）
+    fn focused_slot_mut(&mut self) -> Option<&mut scene::TextSlot> {
+        let focused = self.modal.focused_slot?;
+        self.scenario
+            .scenes
+            .get_mut(self.selected_scene_index)?
+            .current_slots_mut()
+            .iter_mut()
+            .find(|slot| slot.id == focused)
+    }
+
+    /// フォーカス中のスロットのn番目の判定を選ぶ
+    fn select_judge(&mut self, number: usize) {
+        let judge = self
+            .scenario
+            .scenes
+            .get(self.selected_scene_index)
+            .and_then(|scene| self.scenario.mode(scene.mode_id))
+            .and_then(|mode| mode.judges.get(number.checked_sub(1)?))
+            .map(|judge| judge.id);
+        if let (Some(judge), Some(slot)) = (judge, self.focused_slot_mut()) {
+            slot.selected_judge = judge;
+        }
+    }
+
+    /// フォーカス中のスロットをコピー先に合わせて整形してコピーする
+    fn copy_focused_slot(&mut self, ctx: &egui::Context) {
+        let Some(focused) = self.modal.focused_slot else {
+            self.toasts.info("コピーするスロットをクリックしてください");
+            return;
+        };
+        let Some(scene) = self.scenario.scenes.get(self.selected_scene_index) else {
+            return;
+        };
+        let Some(slot) = scene.current_slots().iter().find(|slot| slot.id == focused) else {
+            return;
+        };
+        let bindings = Bindings {
+            players: &self.scenario.player,
+            variables: &self.scenario.variables,
+        };
+        panels::central::copy_slot(
+            ctx,
+            self.copy,
+            slot,
+            self.scenario.mode(scene.mode_id),
+            bindings,
+            &mut self.toasts,
+        );
+        if let Some(revealed) = Revealed::from_slot(slot, slot.selected_judge, bindings) {
+            self.modal.player_view.reveal(revealed);
+        }
+    }
+
+    /// 読み込んだ設定ファイルを反映する（不正な場合は前の設定のまま）
+    fn apply_config(&mut self, result: Result<Config, ConfigError>) {
+        match result {
+            Ok(config) => {
+                // コピーの設定は画面でも変更して保存されるため、起動時と`copy`が書き換えられたときだけ置き換える
+                if config.copy != self.config.copy {
+                    if let Some(copy) = config.copy {
+                        self.copy = copy;
+                    }
+                }
+                self.config = config;
+            }
+            Err(err) => {
+                self.toasts
+                    .error(err.to_string())
+                    .duration(Some(std::time::Duration::from_secs(10)));
+            }
+        }
+    }
+
+    /// 設定ファイルが変わっていれば読み直す
+    ///
+    /// 設定ファイルがある間は、操作していなくても変更を反映できるよう一定間隔で再描画する。
+    fn watch_config(&mut self, ctx: &egui::Context) {
+        if let Some(result) = self.config_watcher.poll(ctx.input(|i| i.time)) {
+            if result.is_ok() {
+                self.toasts.info("設定ファイルを読み直しました");
+            }
+            self.apply_config(result);
+        }
+        if let Some(interval) = self.config_watcher.check_interval() {
+            ctx.request_repaint_after(interval);
+        }
+    }
+
+    /// 選択中のシーンIDから表示用のインデックスを求める
+    /// （シーンが削除されていれば近い位置のシーンを選ぶ）
+    fn resolve_selected_scene(&mut self) {
+        let last = self.scenario.scenes.len().saturating_sub(1);
+        self.selected_scene_index = self
+            .scenario
+            .scene_position(self.selected_scene)
+            .unwrap_or(self.selected_scene_index.min(last));
+    }
+
+    /// 表示用のインデックスで選ばれたシーンのIDを記録する
+    fn remember_selected_scene(&mut self) {
+        if let Some(scene) = self.scenario.scenes.get(self.selected_scene_index) {
+            self.selected_scene = scene.id;
         }
     }
 
@@ -267,8 +580,20 @@ impl MemoApp {
                         // シーン名を更新
 
                         if !self.modal.editing_scene_name_buffer.is_empty() {
-                            if let Some(scene) = self.scenes.get_mut(self.selected_scene_index) {
-                                scene.title = self.modal.editing_scene_name_buffer.clone();
+                            if let Some(scene) =
+                                self.scenario.scenes.get_mut(self.selected_scene_index)
+                            {
+                                let title = std::mem::replace(
+                                    &mut scene.title,
+                                    self.modal.editing_scene_name_buffer.clone(),
+                                );
+                                self.history.push(
+                                    "シーン名の変更",
+                                    Change::Title {
+                                        scene: scene.id,
+                                        title,
+                                    },
+                                );
                             }
                         }
                         // モーダルを閉じる
@@ -286,28 +611,756 @@ impl MemoApp {
     }
 
     fn show_scene_delete_modal(&mut self, ctx: &egui::Context) {
-        egui::Window::new("シーンを削除しますか？")
+        if self.modal.scene_delete.is_empty() {
+            if let Some(scene) = self.scenario.scenes.get(self.selected_scene_index) {
+                self.modal.scene_delete.push(scene.id);
+            }
+        }
+        let title = if self.modal.scene_delete.len() > 1 {
+            format!(
+                "{}個のシーンを削除しますか？",
+                self.modal.scene_delete.len()
+            )
+        } else {
+            String::from("シーンを削除しますか？")
+        };
+
+        egui::Window::new(title)
             .collapsible(false)
             .resizable(false)
             .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
             .show(ctx, |ui| {
                 ui.horizontal(|ui| {
                     if ui.button("OK").clicked() || ctx.input(|i| i.key_pressed(Key::Enter)) {
-                        self.scenes.remove(self.selected_scene_index);
-                        self.selected_scene_index = self.selected_scene_index.saturating_sub(1);
+                        let targets = std::mem::take(&mut self.modal.scene_delete);
                         // モーダルを閉じる
                         self.modal.editing_scene_delete_modal_open = false;
+                        if self
+                            .scenario
+                            .scenes
+                            .iter()
+                            .all(|scene| targets.contains(&scene.id))
+                        {
+                            self.toasts.error("すべてのシーンは削除できません");
+                            return;
+                        }
+                        // 選択中のシーンが消える場合は削除したシーンの1つ前を選択する
+                        if targets.contains(&self.selected_scene) {
+                            let first = self
+                                .scenario
+                                .scenes
+                                .iter()
+                                .position(|scene| targets.contains(&scene.id))
+                                .unwrap_or(self.selected_scene_index);
+                            self.selected_scene_index = first.saturating_sub(1);
+                        }
+                        // 元の位置に前から挿入し直せば元の並びに戻る
+                        let undo = self
+                            .scenario
+                            .scenes
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, scene)| targets.contains(&scene.id))
+                            .map(|(index, scene)| Change::InsertScene {
+                                index,
+                                scene: Box::new(scene.clone()),
+                            })
+                            .collect();
+                        self.scenario.remove_scenes(&targets);
+                        self.history.push("シーンの削除", Change::Batch(undo));
+                        self.modal.scene_list.selected.clear();
                     }
 
                     if ui.button("キャンセル").clicked()
                         || ctx.input(|i| i.key_pressed(Key::Escape))
                     {
                         // モーダルを閉じる
+                        self.modal.scene_delete.clear();
                         self.modal.editing_scene_delete_modal_open = false;
                     }
                 });
             });
     }
+
+    /// シーン一覧で選ばれた操作を適用（選択中のシーンはIDで追従する）
+    fn apply_scene_action(&mut self, action: SceneAction) {
+        self.remember_selected_scene();
+        match action {
+            SceneAction::Rename => self.modal.editing_scene_name_modal_open = true,
+            SceneAction::Duplicate(ids) => {
+                let copies = self.scenario.duplicate_scenes(&ids);
+                let undo = copies
+                    .into_iter()
+                    .map(|scene| Change::RemoveScene { scene })
+                    .collect();
+                self.history.push("シーンの複製", Change::Batch(undo));
+            }
+            SceneAction::Delete(ids) => {
+                self.modal.scene_delete = ids;
+                self.modal.editing_scene_delete_modal_open = true;
+            }
+            SceneAction::Insert(at) => {
+                let scene = Scene::new(
+                    self.create_index,
+                    &self.scenario.modes,
+                    self.config.new_scene_mode(&self.scenario.modes),
+                );
+                self.create_index += 1;
+                self.selected_scene = scene.id;
+                self.history.execute(
+                    &mut self.scenario,
+                    "シーンの挿入",
+                    Change::InsertScene {
+                        index: at,
+                        scene: Box::new(scene),
+                    },
+                );
+                self.modal.scene_list.selected.clear();
+            }
+            SceneAction::Move(ids, to) => {
+                let order = self.scenario.scenes.iter().map(|scene| scene.id).collect();
+                self.scenario.move_scenes(&ids, to);
+                self.history
+                    .push("シーンの並べ替え", Change::SceneOrder(order));
+            }
+        }
+        self.resolve_selected_scene();
+    }
+
+    /// スロット削除確認モーダルを表示
+    fn show_slot_delete_modal(&mut self, ctx: &egui::Context) {
+        let Some((scene_id, slot_id)) = self.modal.slot_delete else {
+            return;
+        };
+        let Some(slot) = self
+            .scenario
+            .scenes
+            .iter()
+            .find(|scene| scene.id == scene_id)
+            .and_then(|scene| scene.find_slot(slot_id))
+        else {
+            self.modal.slot_delete = None;
+            return;
+        };
+        let filled = slot
+            .texts
+            .values()
+            .filter(|text| !text.trim().is_empty())
+            .count();
+
+        egui::Window::new("スロットを削除しますか？")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                if filled > 0 {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!("{filled}件のテキストも削除されます"),
+                    );
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("OK").clicked() || ctx.input(|i| i.key_pressed(Key::Enter)) {
+                        self.history.execute(
+                            &mut self.scenario,
+                            "スロットの削除",
+                            Change::RemoveSlot {
+                                scene: scene_id,
+                                slot: slot_id,
+                            },
+                        );
+                        self.modal.slot_delete = None;
+                    }
+
+                    if ui.button("キャンセル").clicked()
+                        || ctx.input(|i| i.key_pressed(Key::Escape))
+                    {
+                        self.modal.slot_delete = None;
+                    }
+                });
+            });
+    }
+
+    /// メニューバーのコマンドを処理
+    fn handle_menu_command(&mut self, ctx: &egui::Context, command: MenuCommand) {
+        match command {
+            MenuCommand::New => self.request(ctx, PendingAction::New),
+            MenuCommand::Open => self.request(ctx, PendingAction::Open),
+            MenuCommand::Save => {
+                self.save_scenario(false);
+            }
+            MenuCommand::SaveAs => {
+                self.save_scenario(true);
+            }
+            MenuCommand::ImportMarkdown => self.import_markdown(),
+            MenuCommand::Export(format) => self.export(format),
+            MenuCommand::Quit => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+            MenuCommand::SwitchSystem(system) => {
+                self.modal.system_switch_plan = Some(SystemSwitchPlan::new(&self.scenario, system));
+            }
+            MenuCommand::OpenModeEditor => self.modal.mode_editor.open = true,
+            MenuCommand::OpenRoster => self.modal.roster.open = true,
+            MenuCommand::OpenVariables => self.modal.variables.open = true,
+            MenuCommand::OpenDice => self.modal.dice.open = true,
+            MenuCommand::OpenChatPalette => self.modal.palette.open = true,
+            MenuCommand::OpenPlayerView => self.modal.player_view.open = true,
+            MenuCommand::ClearPlayerView => self.modal.player_view.clear(),
+            MenuCommand::OpenKeymap => self.modal.keymap.open = true,
+            MenuCommand::CopyConfigPath => match self.config_watcher.create_template() {
+                Ok(path) => {
+                    ctx.copy_text(path.display().to_string());
+                    self.toasts.info(format!(
+                        "設定ファイルの場所をコピーしました: {}",
+                        path.display()
+                    ));
+                }
+                Err(err) => {
+                    self.toasts.error(err.to_string());
+                }
+            },
+            MenuCommand::Undo => self.undo(),
+            MenuCommand::Redo => self.redo(),
+            MenuCommand::OpenSearch => {
+                self.modal.search.open = true;
+                self.modal.search.request_focus = true;
+            }
+        }
+    }
+
+    /// 直前の操作を元に戻す
+    fn undo(&mut self) {
+        if let Some(label) = self.history.undo(&mut self.scenario) {
+            self.toasts
+                .info(format!("元に戻しました: {label}"))
+                .duration(Some(std::time::Duration::from_secs(2)));
+        }
+    }
+
+    /// 元に戻した操作をやり直す
+    fn redo(&mut self) {
+        if let Some(label) = self.history.redo(&mut self.scenario) {
+            self.toasts
+                .info(format!("やり直しました: {label}"))
+                .duration(Some(std::time::Duration::from_secs(2)));
+        }
+    }
+
+    /// 検索結果の場所へ移動（シーン、モード、判定を選択してスロットまでスクロール）
+    fn jump_to(&mut self, hit: &Hit) {
+        self.selected_scene = hit.scene_id;
+        self.resolve_selected_scene();
+        let modes = &self.scenario.modes;
+        let Some(scene) = self
+            .scenario
+            .scenes
+            .iter_mut()
+            .find(|scene| scene.id == hit.scene_id)
+        else {
+            return;
+        };
+        match hit.location {
+            Location::SceneTitle => {}
+            Location::SlotLabel { mode_id, slot_id } => {
+                // モード切り替えと同じく判定の選択とレイアウトキャッシュをリセットする
+                if let Some(mode) = scene::find_mode(modes, mode_id) {
+                    scene.set_mode(mode);
+                }
+                self.modal.scroll_to_slot = Some(slot_id);
+            }
+            Location::Text {
+                mode_id,
+                slot_id,
+                judge_id,
+            } => {
+                if let Some(mode) = scene::find_mode(modes, mode_id) {
+                    scene.set_mode(mode);
+                }
+                // 見つかったテキストの判定を選択する（set_modeのリセットの後に行う）
+                if let Some(slot) = scene
+                    .slots_mut(mode_id)
+                    .iter_mut()
+                    .find(|slot| slot.id == slot_id)
+                {
+                    slot.selected_judge = judge_id;
+                }
+                self.modal.scroll_to_slot = Some(slot_id);
+            }
+        }
+    }
+
+    /// ゲームシステム切り替えのプレビューを表示
+    fn show_system_switch_modal(&mut self, ctx: &egui::Context) {
+        let Some(plan) = &self.modal.system_switch_plan else {
+            return;
+        };
+
+        let mut apply = false;
+        let mut close = ctx.input(|i| i.key_pressed(Key::Escape));
+        egui::Window::new(format!("{}に切り替え", plan.system.name()))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                if plan.modes.is_empty() {
+                    ui.label("判定を持つモードがないため、テキストは変更されません");
+                }
+                for remap in &plan.modes {
+                    let Some(mode) = self.scenario.mode(remap.mode_id) else {
+                        continue;
+                    };
+                    ui.strong(&mode.name);
+                    egui::Grid::new(("system_switch", remap.mode_id)).show(ui, |ui| {
+                        for judge in &mode.judges {
+                            ui.label(&judge.name);
+                            ui.label("→");
+                            match remap.target_name(judge.id) {
+                                Some(new_judge) => ui.label(new_judge),
+                                None => ui.colored_label(ui.visuals().warn_fg_color, "破棄"),
+                            };
+                            ui.end_row();
+                        }
+                    });
+                    if remap.merged_slots > 0 {
+                        ui.label(format!(
+                            "{}個のスロットで複数の判定のテキストが統合されます",
+                            remap.merged_slots
+                        ));
+                    }
+                    if remap.dropped_texts > 0 {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!("{}件のテキストが破棄されます", remap.dropped_texts),
+                        );
+                    }
+                    ui.separator();
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("適用").clicked() {
+                        apply = true;
+                    }
+                    if ui.button("キャンセル").clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if apply {
+            // プレビュー中にモードが編集されていても、今のモードで計画を作り直して適用する
+            let plan = SystemSwitchPlan::new(&self.scenario, plan.system);
+            let mode_ids: Vec<Id> = plan.modes.iter().map(|remap| remap.mode_id).collect();
+            let undo = Change::modes(&self.scenario, &mode_ids);
+            plan.apply(&mut self.scenario);
+            self.history
+                .push(format!("{}に切り替え", plan.system.name()), undo);
+            self.toasts
+                .success(format!("{}に切り替えました", plan.system.name()));
+            close = true;
+        }
+        if close {
+            self.modal.system_switch_plan = None;
+        }
+    }
+
+    /// プレイヤーのアイコン画像をダイアログで選択して設定する
+    fn pick_player_icon(&mut self, player_id: Id) {
+        let Some(path) = pick_image_path() else {
+            return;
+        };
+        let before = self.scenario.player.clone();
+        if let Some(player) = self
+            .scenario
+            .player
+            .iter_mut()
+            .find(|player| player.id == player_id)
+        {
+            player.icon_path = Some(path);
+            self.history.push("アイコンの変更", Change::Players(before));
+        }
+    }
+
+    /// Markdownファイルを読み込み、シーンを末尾に追加する
+    fn import_markdown(&mut self) {
+        let Some(path) = pick_markdown_path() else {
+            return;
+        };
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(err) => {
+                self.toasts
+                    .error(format!("ファイルの読み込みに失敗しました: {err}"));
+                return;
+            }
+        };
+
+        let result = markdown::parse(&source, &self.scenario.modes, &self.scenario.player);
+        let imported = result.scenes.len();
+        if imported > 0 {
+            self.selected_scene_index = self.scenario.scenes.len();
+            let undo = result
+                .scenes
+                .iter()
+                .map(|scene| Change::RemoveScene { scene: scene.id })
+                .collect();
+            self.scenario.scenes.extend(result.scenes);
+            self.scenario.normalize();
+            self.history
+                .push("Markdownのインポート", Change::Batch(undo));
+            self.create_index = self.create_index.max(self.scenario.scenes.len() + 1);
+            self.toasts
+                .success(format!("{imported}個のシーンを取り込みました"));
+        }
+        self.modal.import_warnings = result.warnings;
+    }
+
+    /// シナリオ全体を指定形式で書き出す
+    fn export(&mut self, format: ExportFormat) {
+        let exporter = format.exporter();
+        let file_name = self.file.display_name();
+        let stem = file_name
+            .strip_suffix(&format!(".{}", crate::scenario::SCENARIO_EXTENSION))
+            .unwrap_or(&file_name);
+        let Some(path) = pick_export_path(exporter.name(), exporter.extension(), stem) else {
+            return;
+        };
+
+        match exporter.export(&self.scenario) {
+            Ok(bytes) => match std::fs::write(&path, bytes) {
+                Ok(()) => {
+                    self.toasts
+                        .success(format!("{}形式で書き出しました", exporter.name()));
+                }
+                Err(err) => {
+                    self.toasts.error(format!("書き出しに失敗しました: {err}"));
+                }
+            },
+            Err(err) => {
+                self.toasts.error(err.to_string());
+            }
+        }
+    }
+
+    /// チャットパレットのコピー/保存
+    fn handle_palette_action(&mut self, ctx: &egui::Context, action: PaletteAction) {
+        match action {
+            PaletteAction::Copy(palette) => {
+                ctx.copy_text(palette.text());
+                self.toasts.success(format!(
+                    "{}のチャットパレットをコピーしました",
+                    palette.name
+                ));
+            }
+            PaletteAction::Save(palette) => {
+                let stem = export::file_stem(&palette.name);
+                let Some(path) = pick_export_path("テキスト", "txt", &stem) else {
+                    return;
+                };
+                match std::fs::write(&path, palette.text()) {
+                    Ok(()) => {
+                        self.toasts
+                            .success(format!("{}のチャットパレットを保存しました", palette.name));
+                    }
+                    Err(err) => {
+                        self.toasts.error(format!("書き出しに失敗しました: {err}"));
+                    }
+                }
+            }
+            PaletteAction::SaveAll(palettes) => {
+                let Some(folder) = pick_export_folder() else {
+                    return;
+                };
+                let result = palettes.iter().try_for_each(|palette| {
+                    let path = folder.join(format!("{}.txt", export::file_stem(&palette.name)));
+                    std::fs::write(path, palette.text())
+                });
+                match result {
+                    Ok(()) => {
+                        self.toasts.success(format!(
+                            "{}人分のチャットパレットを保存しました",
+                            palettes.len()
+                        ));
+                    }
+                    Err(err) => {
+                        self.toasts.error(format!("書き出しに失敗しました: {err}"));
+                    }
+                }
+            }
+        }
+    }
+
+    /// インポート時の警告一覧ウィンドウを表示
+    fn show_import_report(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        egui::Window::new("インポートの警告")
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{}件の内容を取り込めませんでした",
+                    self.modal.import_warnings.len()
+                ));
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("import_warnings")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for warning in &self.modal.import_warnings {
+                                    ui.label(format!("{}行目", warning.line));
+                                    ui.label(&warning.message);
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+        if !open {
+            self.modal.import_warnings.clear();
+        }
+    }
+
+    /// 未保存の変更があれば確認モーダルを開き、なければそのまま実行
+    fn request(&mut self, ctx: &egui::Context, action: PendingAction) {
+        if self.file.dirty {
+            self.file.pending = Some(action);
+        } else {
+            self.perform(ctx, action);
+        }
+    }
+
+    /// 保留されていた操作を実行
+    fn perform(&mut self, ctx: &egui::Context, action: PendingAction) {
+        match action {
+            PendingAction::New => {
+                self.replace_scenario(Scenario::blank(self.scenario.system, &self.config), None);
+            }
+            PendingAction::Open => {
+                if let Some(path) = pick_open_path() {
+                    match Scenario::load(&path) {
+                        Ok((scenario, dropped)) => {
+                            self.replace_scenario(scenario, Some(path));
+                            self.toasts.success("シナリオを開きました");
+                            self.warn_dropped_texts(dropped);
+                        }
+                        Err(err) => {
+                            self.toasts.error(err.to_string());
+                        }
+                    }
+                }
+            }
+            PendingAction::Quit => {
+                self.file.close_confirmed = true;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        }
+    }
+
+    /// 編集中のシナリオを差し替える
+    fn replace_scenario(&mut self, scenario: Scenario, path: Option<PathBuf>) {
+        self.history = History::new();
+        self.file = FileState::new(&self.history);
+        self.file.path = path;
+        self.modal.mode_editor = ModeEditorState::default();
+        self.modal.roster = RosterState::default();
+        self.modal.variables = VariablesState::default();
+        self.create_index = scenario.scenes.len() + 1;
+        self.selected_scene = scenario
+            .scenes
+            .first()
+            .map(|scene| scene.id)
+            .unwrap_or_default();
+        self.selected_scene_index = 0;
+        self.scenario = scenario;
+    }
+
+    /// シナリオを保存（パス未設定または`save_as`ならダイアログで選択）
+    /// 返り値: 保存できたかどうか
+    fn save_scenario(&mut self, save_as: bool) -> bool {
+        let path = match &self.file.path {
+            Some(path) if !save_as => path.clone(),
+            _ => {
+                let Some(path) = pick_save_path(&self.file.display_name()) else {
+                    return false;
+                };
+                path
+            }
+        };
+
+        match self.scenario.save(&path) {
+            Ok(()) => {
+                self.file.saved_revision = self.history.revision();
+                self.file.dirty = false;
+                self.file.path = Some(path);
+                self.toasts
+                    .success("保存しました")
+                    .duration(Some(std::time::Duration::from_secs(2)));
+                true
+            }
+            Err(err) => {
+                self.toasts.error(err.to_string());
+                false
+            }
+        }
+    }
+
+    /// メニューから開くウィンドウ（開いているものだけ表示）
+    fn show_tool_windows(&mut self, ctx: &egui::Context) {
+        // モード/判定編集ウィンドウ
+        if self.modal.mode_editor.open {
+            panels::mode_editor::show(
+                ctx,
+                &mut self.scenario,
+                &mut self.modal.mode_editor,
+                &mut self.history,
+            );
+        }
+
+        // ダイスロールウィンドウ
+        if self.modal.dice.open {
+            let mode = self
+                .scenario
+                .scenes
+                .get(self.selected_scene_index)
+                .and_then(|scene| self.scenario.mode(scene.mode_id));
+            panels::dice::show(
+                ctx,
+                self.scenario.system,
+                mode,
+                &mut self.rng,
+                &mut self.modal.dice,
+            );
+        }
+
+        // 変数一覧の編集ウィンドウ
+        if self.modal.variables.open {
+            panels::variables::show(
+                ctx,
+                &mut self.scenario,
+                &mut self.modal.variables,
+                &mut self.history,
+            );
+        }
+
+        // プレイヤー一覧の編集ウィンドウ
+        if self.modal.roster.open {
+            let icon_request = panels::roster::show(
+                ctx,
+                &mut self.scenario.player,
+                &mut self.modal.roster,
+                &mut self.history,
+            );
+            if let Some(player_id) = icon_request {
+                self.pick_player_icon(player_id);
+            }
+        }
+
+        // 検索ウィンドウ
+        if self.modal.search.open {
+            match panels::search::show(
+                ctx,
+                &self.scenario,
+                self.history.revision(),
+                &mut self.modal.search,
+            ) {
+                Some(SearchAction::Jump(hit)) => self.jump_to(&hit),
+                Some(SearchAction::Replace(replacements)) => {
+                    let undo = replace_undo(&self.scenario, &replacements);
+                    let count = search::replace_all(&mut self.scenario, &replacements);
+                    self.history.push(format!("置換（{count}件）"), undo);
+                    self.toasts.info(format!("{count}件を置換しました"));
+                }
+                None => {}
+            }
+        }
+
+        // チャットパレットウィンドウ
+        if self.modal.palette.open {
+            if let Some(action) =
+                panels::palette::show(ctx, &self.scenario, &mut self.modal.palette)
+            {
+                self.handle_palette_action(ctx, action);
+            }
+        }
+
+        // プレイヤー用ウィンドウ
+        if self.modal.player_view.open {
+            panels::player_view::show(ctx, &mut self.modal.player_view, &mut self.icons);
+        }
+
+        // ショートカットキー設定ウィンドウ
+        if self.modal.keymap.open
+            && panels::keymap::show(
+                ctx,
+                &mut self.keymap,
+                &mut self.modal.keymap,
+                &mut self.toasts,
+            )
+        {
+            self.save_keymap();
+        }
+    }
+
+    /// 未保存の変更を破棄するか確認するモーダルを表示
+    fn show_unsaved_changes_modal(&mut self, ctx: &egui::Context) {
+        let Some(action) = self.file.pending else {
+            return;
+        };
+
+        egui::Window::new("保存されていない変更があります")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "「{}」への変更を保存しますか？",
+                    self.file.display_name()
+                ));
+
+                ui.horizontal(|ui| {
+                    if ui.button("保存").clicked() {
+                        self.file.pending = None;
+                        if self.save_scenario(false) {
+                            self.perform(ctx, action);
+                        }
+                    }
+
+                    if ui.button("保存しない").clicked() {
+                        self.file.pending = None;
+                        self.perform(ctx, action);
+                    }
+
+                    if ui.button("キャンセル").clicked()
+                        || ctx.input(|i| i.key_pressed(Key::Escape))
+                    {
+                        self.file.pending = None;
+                    }
+                });
+            });
+    }
+
+    /// 変更の有無を更新し、タイトルバーにファイル名を反映
+    fn update_file_state(&mut self, ctx: &egui::Context) {
+        self.file.dirty = self.history.revision() != self.file.saved_revision;
+
+        // 未保存のままウィンドウを閉じようとした場合は確認する
+        if ctx.input(|i| i.viewport().close_requested())
+            && self.file.dirty
+            && !self.file.close_confirmed
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.file.pending = Some(PendingAction::Quit);
+        }
+
+        let title = format!(
+            "{}{} - MemoApp",
+            if self.file.dirty { "*" } else { "" },
+            self.file.display_name()
+        );
+        if title != self.file.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.file.window_title = title;
+        }
+    }
 }
 
 impl eframe::App for MemoApp {
@@ -316,27 +1369,70 @@ impl eframe::App for MemoApp {
     }
 
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        ctx.set_pixels_per_point(DEFAULT_PIXELS_PER_POINT);
+        self.watch_config(ctx);
+        ctx.set_pixels_per_point(self.config.ui_scale());
+
+        self.resolve_selected_scene();
+
+        // 元に戻す/やり直し（Ctrl+Shift+ZはCtrl+Zにも一致するため先に判定する）
+        if ctx.input_mut(|i| i.consume_shortcut(&REDO_SHORTCUT)) {
+            self.redo();
+        } else if ctx.input_mut(|i| i.consume_shortcut(&UNDO_SHORTCUT)) {
+            self.undo();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&SEARCH_SHORTCUT)) {
+            self.handle_menu_command(ctx, MenuCommand::OpenSearch);
+        }
+        self.handle_keymap(ctx);
 
         // 前のフレームでモーダルが開いていたかを記録
         let was_edit_modal_open = self.modal.editing_scene_name_modal_open;
 
-        panels::top::show(ctx, &mut self.app_mode);
-        panels::side::show(ctx, &self.scenes, &mut self.selected_scene_index);
+        if let Some(command) = panels::top::show(
+            ctx,
+            &mut self.app_mode,
+            &mut self.copy,
+            self.scenario.system,
+            &self.history,
+        ) {
+            self.handle_menu_command(ctx, command);
+        }
+        if let Some(action) = panels::side::show(
+            ctx,
+            &self.scenario.scenes,
+            &mut self.selected_scene_index,
+            &mut self.modal.scene_list,
+        ) {
+            self.apply_scene_action(action);
+        }
         panels::central::show(
             ctx,
-            &self.modes,
-            &mut self.scenes,
+            &self.scenario.modes,
+            &mut self.scenario.scenes,
             &mut self.selected_scene_index,
             &mut self.create_index,
+            self.config.new_scene_mode(&self.scenario.modes),
             &self.app_mode,
             &mut self.modal,
             &mut self.toasts,
+            Bindings {
+                players: &self.scenario.player,
+                variables: &self.scenario.variables,
+            },
+            &mut self.icons,
+            SlotContext {
+                system: self.scenario.system,
+                rng: &mut self.rng,
+                copy: self.copy,
+                revealed: None,
+                focused: None,
+                history: &mut self.history,
+            },
         );
 
         // モーダルが新しく開かれた場合のみバッファを初期化
         if self.modal.editing_scene_name_modal_open && !was_edit_modal_open {
-            if let Some(scene) = self.scenes.get(self.selected_scene_index) {
+            if let Some(scene) = self.scenario.scenes.get(self.selected_scene_index) {
                 self.modal.editing_scene_name_buffer = scene.title.clone();
             }
         }
@@ -351,6 +1447,149 @@ impl eframe::App for MemoApp {
             self.show_scene_delete_modal(ctx);
         }
 
+        // スロット削除確認モーダル
+        self.show_slot_delete_modal(ctx);
+
+        // インポート結果
+        if !self.modal.import_warnings.is_empty() {
+            self.show_import_report(ctx);
+        }
+
+        // モード/判定編集、ダイスロールなどのウィンドウ
+        self.show_tool_windows(ctx);
+
+        // ゲームシステム切り替えのプレビュー
+        self.show_system_switch_modal(ctx);
+
+        self.remember_selected_scene();
+        self.history.update(ctx);
+
+        // 未保存確認モーダル
+        self.update_file_state(ctx);
+        self.show_unsaved_changes_modal(ctx);
+
         self.toasts.show(ctx);
     }
 }
+
+/// 置換する前の値に戻す変更（置換する欄ごとに1つ）
+fn replace_undo(scenario: &Scenario, replacements: &[Replacement]) -> Change {
+    let mut fields: Vec<(Id, Location)> = Vec::new();
+    for replacement in replacements {
+        let field = (replacement.hit.scene_id, replacement.hit.location);
+        if !fields.contains(&field) {
+            fields.push(field);
+        }
+    }
+    let changes = fields
+        .into_iter()
+        .filter_map(|(scene_id, location)| {
+            let scene = scenario.scenes.iter().find(|scene| scene.id == scene_id)?;
+            match location {
+                Location::SceneTitle => Some(Change::Title {
+                    scene: scene_id,
+                    title: scene.title.clone(),
+                }),
+                Location::SlotLabel { slot_id, .. } => Some(Change::Slot {
+                    scene: scene_id,
+                    slot: Box::new(scene.find_slot(slot_id)?.clone()),
+                }),
+                Location::Text {
+                    slot_id, judge_id, ..
+                } => Some(Change::Text {
+                    scene: scene_id,
+                    slot: slot_id,
+                    judge: judge_id,
+                    text: String::from(scene.find_slot(slot_id)?.text(judge_id)),
+                }),
+            }
+        })
+        .collect();
+    Change::Batch(changes)
+}
+
+/// 開くシナリオファイルをダイアログで選択
+#[cfg(not(target_arch = "wasm32"))]
+fn pick_open_path() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("シナリオ", &[crate::scenario::SCENARIO_EXTENSION])
+        .pick_file()
+}
+
+/// 保存先のシナリオファイルをダイアログで選択
+#[cfg(not(target_arch = "wasm32"))]
+fn pick_save_path(file_name: &str) -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("シナリオ", &[crate::scenario::SCENARIO_EXTENSION])
+        .set_file_name(file_name)
+        .save_file()
+        .map(|path| {
+            if path.extension().is_some() {
+                path
+            } else {
+                path.with_extension(crate::scenario::SCENARIO_EXTENSION)
+            }
+        })
+}
+
+/// インポートするMarkdownファイルをダイアログで選択
+#[cfg(not(target_arch = "wasm32"))]
+fn pick_markdown_path() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("Markdown", &["md", "markdown", "txt"])
+        .pick_file()
+}
+
+/// アイコン画像をダイアログで選択
+#[cfg(not(target_arch = "wasm32"))]
+fn pick_image_path() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("画像", &["png", "jpg", "jpeg", "gif", "bmp", "webp"])
+        .pick_file()
+}
+
+/// エクスポート先のファイルをダイアログで選択
+#[cfg(not(target_arch = "wasm32"))]
+fn pick_export_path(filter_name: &str, extension: &str, stem: &str) -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter(filter_name, &[extension])
+        .set_file_name(format!("{stem}.{extension}"))
+        .save_file()
+}
+
+/// エクスポート先のフォルダをダイアログで選択
+#[cfg(not(target_arch = "wasm32"))]
+fn pick_export_folder() -> Option<PathBuf> {
+    rfd::FileDialog::new().pick_folder()
+}
+
+// Webではファイルダイアログを使用しない
+#[cfg(target_arch = "wasm32")]
+fn pick_open_path() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+fn pick_save_path(_file_name: &str) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+fn pick_markdown_path() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+fn pick_image_path() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+fn pick_export_path(_filter_name: &str, _extension: &str, _stem: &str) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+fn pick_export_folder() -> Option<PathBuf> {
+    None
+}
@@ -0,0 +1,590 @@
+use crate::app::{CopySettings, Player};
+use crate::constants::constants::DEFAULT_PIXELS_PER_POINT;
+use crate::scenario;
+use crate::scene::{Id, Mode};
+use crate::system::GameSystem;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// 設定ファイルを置くディレクトリ（ユーザーの設定ディレクトリの下）
+#[cfg(not(target_arch = "wasm32"))]
+const CONFIG_DIR_NAME: &str = "memoapp";
+
+/// 設定ファイルの名前
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// 設定ファイルの変更を確認する間隔（秒）
+const RELOAD_INTERVAL: f64 = 1.0;
+
+/// 画面の拡大率として受け付ける範囲
+const UI_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.5..=4.0;
+
+/// 新しく作る設定ファイルの内容（すべてコメントにしてあり、既定値と同じ）
+const CONFIG_TEMPLATE: &str = r#"# MemoAppの設定ファイル
+# 保存すると自動で読み直します。コメント（#）を外した項目だけが使われます。
+
+# 画面の拡大率（0.5〜4.0）
+# ui_scale = 1.5
+
+# 新規シーンで最初に表示するモードの名前
+# new_scene_mode = "探索"
+
+# コピーの設定（読み込んだときに反映します）
+# [copy]
+# target = "Discord"   # "Plain" / "Ccfolia" / "Discord"
+# after_check = false  # 技能判定で判定を選んだらコピーする
+# split = false        # 長いテキストを分割してコピーする
+# split_limit = 300    # 分割するときの1回の最大文字数（20〜2000）
+
+# 新しいシナリオのモードと判定（書かなければゲームシステムの既定のモード）
+# [[modes]]
+# name = "地の文"
+# judges = ["本文"]
+# slots = 1            # 新規シーンのスロット数（0〜20）
+#
+# [[modes]]
+# name = "探索"
+# judges = ["成功", "失敗"]
+# slots = 4
+# copy_prefix = "【{モード}】\n"
+# copy_suffix = ""
+
+# 新しいシナリオのプレイヤー一覧（書かなければ既定のプレイヤー一覧）
+# [[players]]
+# name = "探偵"
+# character = "明智小五郎"
+# icon = "/path/to/icon.png"
+"#;
+
+/// 設定ファイル（TOML）の内容
+///
+/// 新しいシナリオのモード/判定とプレイヤー一覧、新規シーンで最初に表示するモード、画面の拡大率、コピーの設定を決める。
+/// 書かなかった項目は組み込みの既定値を使う。
+#[derive(serde::Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// 画面の拡大率
+    pub ui_scale: Option<f32>,
+    /// 新規シーンで最初に表示するモードの名前
+    pub new_scene_mode: Option<String>,
+    /// コピーの設定（起動時と、この項目が書き換えられたときにアプリの設定を置き換える）
+    pub copy: Option<CopySettings>,
+    /// 新しいシナリオのモード（空ならゲームシステムの既定のモード）
+    pub modes: Vec<ModeConfig>,
+    /// 新しいシナリオのプレイヤー一覧（空なら既定のプレイヤー一覧）
+    pub players: Vec<PlayerConfig>,
+}
+
+/// 設定ファイルのモード1つ分
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModeConfig {
+    pub name: String,
+    pub judges: Vec<String>,
+    /// 新規シーンのスロット数
+    #[serde(default = "default_slots")]
+    pub slots: usize,
+    #[serde(default)]
+    pub copy_prefix: String,
+    #[serde(default)]
+    pub copy_suffix: String,
+}
+
+fn default_slots() -> usize {
+    1
+}
+
+/// 設定ファイルのプレイヤー1人分
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlayerConfig {
+    pub name: String,
+    #[serde(default)]
+    pub character: String,
+    #[serde(default)]
+    pub icon: Option<PathBuf>,
+}
+
+/// 設定ファイルの読み込みで発生するエラー
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    /// 書式は正しいが値が使えない（問題の一覧）
+    Invalid(Vec<String>),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "設定ファイルを読み込めませんでした: {err}"),
+            Self::Toml(err) => write!(f, "設定ファイルの書式が不正です: {err}"),
+            Self::Invalid(problems) => {
+                write!(f, "設定ファイルの内容が不正です:\n{}", problems.join("\n"))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(err: toml::de::Error) -> Self {
+        Self::Toml(err)
+    }
+}
+
+impl Config {
+    /// 設定ファイルを読み込んで値を検証する
+    ///
+    /// # Errors
+    /// ファイルが読めない、TOMLとして不正、または使えない値がある場合
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path)?;
+        let config: Self = toml::from_str(&text)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 値を検証する（問題をすべて集めて返す）
+    fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+        if let Some(scale) = self.ui_scale {
+            if !UI_SCALE_RANGE.contains(&scale) {
+                problems.push(format!(
+                    "ui_scaleは{:.1}〜{:.1}で指定してください（{scale}）",
+                    UI_SCALE_RANGE.start(),
+                    UI_SCALE_RANGE.end()
+                ));
+            }
+        }
+        if let Some(copy) = &self.copy {
+            if !(20..=2000).contains(&copy.split_limit) {
+                problems.push(format!(
+                    "copy.split_limitは20〜2000で指定してください（{}）",
+                    copy.split_limit
+                ));
+            }
+        }
+        for (index, mode) in self.modes.iter().enumerate() {
+            let number = index + 1;
+            if mode.name.trim().is_empty() {
+                problems.push(format!("{number}番目のモードの名前が空です"));
+            } else if self
+                .modes
+                .iter()
+                .take(index)
+                .any(|other| other.name == mode.name)
+            {
+                problems.push(format!("モード「{}」が重複しています", mode.name));
+            }
+            if mode.judges.is_empty() {
+                problems.push(format!("モード「{}」に判定がありません", mode.name));
+            }
+            if mode.judges.iter().any(|judge| judge.trim().is_empty()) {
+                problems.push(format!("モード「{}」に名前が空の判定があります", mode.name));
+            }
+            if mode.slots > 20 {
+                problems.push(format!(
+                    "モード「{}」のslotsは0〜20で指定してください（{}）",
+                    mode.name, mode.slots
+                ));
+            }
+        }
+        if let Some(name) = &self.new_scene_mode {
+            let names: Vec<String> = if self.modes.is_empty() {
+                GameSystem::Generic
+                    .modes()
+                    .into_iter()
+                    .map(|mode| mode.name)
+                    .collect()
+            } else {
+                self.modes.iter().map(|mode| mode.name.clone()).collect()
+            };
+            if !names.contains(name) {
+                problems.push(format!(
+                    "new_scene_modeのモード「{name}」がありません（{}）",
+                    names.join("、")
+                ));
+            }
+        }
+        for (index, player) in self.players.iter().enumerate() {
+            if player.name.trim().is_empty() {
+                problems.push(format!("{}番目のプレイヤーの名前が空です", index + 1));
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    /// 画面の拡大率
+    pub fn ui_scale(&self) -> f32 {
+        self.ui_scale.unwrap_or(DEFAULT_PIXELS_PER_POINT)
+    }
+
+    /// 新しいシナリオのモード
+    pub fn modes(&self, system: GameSystem) -> Vec<Mode> {
+        if self.modes.is_empty() {
+            return system.modes();
+        }
+        self.modes
+            .iter()
+            .map(|config| {
+                let mut mode = Mode::new(config.name.clone(), config.judges.clone(), config.slots);
+                mode.copy_prefix.clone_from(&config.copy_prefix);
+                mode.copy_suffix.clone_from(&config.copy_suffix);
+                mode
+            })
+            .collect()
+    }
+
+    /// 新しいシナリオのプレイヤー一覧
+    pub fn players(&self) -> Vec<Player> {
+        if self.players.is_empty() {
+            return scenario::default_players();
+        }
+        self.players
+            .iter()
+            .map(|config| Player {
+                id: Id::new(),
+                name: config.name.clone(),
+                icon_path: config.icon.clone(),
+                character: config.character.clone(),
+            })
+            .collect()
+    }
+
+    /// 新規シーンで最初に表示するモード（指定がなければNone）
+    pub fn new_scene_mode(&self, modes: &[Mode]) -> Option<Id> {
+        let name = self.new_scene_mode.as_ref()?;
+        modes
+            .iter()
+            .find(|mode| &mode.name == name)
+            .map(|mode| mode.id)
+    }
+
+    /// シナリオの内容（モード/プレイヤー一覧）を設定しているか
+    pub fn has_scenario_defaults(&self) -> bool {
+        !self.modes.is_empty() || !self.players.is_empty()
+    }
+}
+
+/// 設定ディレクトリのファイルの場所（設定ディレクトリがない環境ではNone）
+#[cfg(not(target_arch = "wasm32"))]
+pub fn config_file(file_name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(CONFIG_DIR_NAME).join(file_name))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn config_file(_file_name: &str) -> Option<PathBuf> {
+    None
+}
+
+/// ファイルの更新日時（ファイルがなければNone）
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// 設定ファイルの変更の監視（一定間隔で更新日時を確認する）
+#[derive(Default)]
+pub struct ConfigWatcher {
+    path: Option<PathBuf>,
+    /// 最後に読み込んだときの更新日時
+    modified: Option<SystemTime>,
+    /// 最後に確認した時刻（`egui::InputState::time`）
+    last_check: f64,
+}
+
+impl ConfigWatcher {
+    pub fn new() -> Self {
+        Self::with_path(config_file(CONFIG_FILE_NAME))
+    }
+
+    /// 指定した場所の設定ファイルを監視する
+    fn with_path(path: Option<PathBuf>) -> Self {
+        Self {
+            modified: path.as_deref().and_then(modified),
+            path,
+            last_check: 0.0,
+        }
+    }
+
+    /// 設定ファイルの場所
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// 現在の設定ファイルを読み込む（ファイルがなければ既定値）
+    ///
+    /// # Errors
+    /// 設定ファイルが読めない、または不正な場合
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        match &self.path {
+            Some(path) if path.exists() => Config::load(path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    /// 次に変更を確認するまでの間隔（設定ファイルがなければ確認のための再描画は不要なのでNone）
+    pub fn check_interval(&self) -> Option<Duration> {
+        self.modified
+            .map(|_| Duration::from_secs_f64(RELOAD_INTERVAL))
+    }
+
+    /// 前回から設定ファイルが変わっていれば読み直す
+    /// 返り値: 読み直した結果（変わっていなければNone、削除された場合は既定値）
+    pub fn poll(&mut self, time: f64) -> Option<Result<Config, ConfigError>> {
+        if time - self.last_check < RELOAD_INTERVAL {
+            return None;
+        }
+        self.last_check = time;
+        let modified = modified(self.path.as_deref()?);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(self.load())
+    }
+
+    /// 設定ファイルがなければ雛形を作る
+    /// 返り値: 設定ファイルの場所
+    ///
+    /// # Errors
+    /// ディレクトリまたはファイルを作れない場合
+    pub fn create_template(&self) -> Result<&Path, ConfigError> {
+        let path = self.path().ok_or_else(|| {
+            ConfigError::Io(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "設定ディレクトリがありません",
+            ))
+        })?;
+        if !path.exists() {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, CONFIG_TEMPLATE)?;
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// TOMLを読み込んで検証する
+    fn parse(text: &str) -> Result<Config, ConfigError> {
+        let config: Config = toml::from_str(text)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 検証で見つかった問題の一覧
+    fn problems(text: &str) -> Vec<String> {
+        match parse(text) {
+            Err(ConfigError::Invalid(problems)) => problems,
+            Err(err) => panic!("検証の前に失敗した: {err}"),
+            Ok(_) => panic!("検証に通ってしまった"),
+        }
+    }
+
+    #[test]
+    fn empty_file_uses_defaults() {
+        let config = parse("").expect("空の設定ファイルは既定値");
+        assert_eq!(
+            config.ui_scale(),
+            DEFAULT_PIXELS_PER_POINT,
+            "拡大率は既定値"
+        );
+        assert!(
+            !config.has_scenario_defaults(),
+            "シナリオの内容は設定しない"
+        );
+    }
+
+    #[test]
+    fn template_is_valid() {
+        assert!(parse(CONFIG_TEMPLATE).is_ok(), "雛形はそのまま読み込める");
+    }
+
+    #[test]
+    fn rejects_ui_scale_out_of_range() {
+        assert_eq!(
+            problems("ui_scale = 5.0").len(),
+            1,
+            "範囲外の拡大率は問題になる"
+        );
+        assert!(parse("ui_scale = 4.0").is_ok(), "上限ちょうどは使える");
+    }
+
+    #[test]
+    fn rejects_duplicate_and_empty_mode_names() {
+        let found = problems(
+            r#"
+            [[modes]]
+            name = "探索"
+            judges = ["成功"]
+            [[modes]]
+            name = "探索"
+            judges = ["成功"]
+            [[modes]]
+            name = " "
+            judges = ["成功"]
+            "#,
+        );
+        assert_eq!(
+            found,
+            [
+                "モード「探索」が重複しています",
+                "3番目のモードの名前が空です"
+            ],
+            "重複と空の名前を報告する"
+        );
+    }
+
+    #[test]
+    fn rejects_modes_without_judges() {
+        let found = problems(
+            r#"
+            [[modes]]
+            name = "探索"
+            judges = []
+            "#,
+        );
+        assert_eq!(found, ["モード「探索」に判定がありません"], "判定が必要");
+    }
+
+    #[test]
+    fn rejects_too_many_slots() {
+        let config = r#"
+            [[modes]]
+            name = "探索"
+            judges = ["成功"]
+            slots = 21
+            "#;
+        assert_eq!(problems(config).len(), 1, "スロット数は20まで");
+        assert!(
+            parse(&config.replace("21", "20")).is_ok(),
+            "20スロットは使える"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_new_scene_mode() {
+        assert_eq!(
+            problems(r#"new_scene_mode = "戦闘""#).len(),
+            1,
+            "既定のモードにない名前"
+        );
+        assert!(
+            parse(r#"new_scene_mode = "探索""#).is_ok(),
+            "既定のモードの名前は使える"
+        );
+        let custom = r#"
+            new_scene_mode = "探索"
+            [[modes]]
+            name = "戦闘"
+            judges = ["成功"]
+            "#;
+        assert_eq!(
+            problems(custom).len(),
+            1,
+            "モードを書いた場合はその中から探す"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!(
+            matches!(parse("ui_scal = 1.5"), Err(ConfigError::Toml(_))),
+            "綴りを間違えた項目は書式エラー"
+        );
+        let typo = r#"
+            [[modes]]
+            name = "探索"
+            judge = ["成功"]
+            "#;
+        assert!(
+            matches!(parse(typo), Err(ConfigError::Toml(_))),
+            "モードの項目も検査する"
+        );
+    }
+
+    #[test]
+    fn collects_every_problem() {
+        let found = problems(
+            r#"
+            ui_scale = 0.1
+            new_scene_mode = "戦闘"
+            [copy]
+            split_limit = 1
+            [[modes]]
+            name = "探索"
+            judges = [""]
+            slots = 30
+            [[players]]
+            name = ""
+            "#,
+        );
+        assert_eq!(found.len(), 6, "問題をすべて集める: {found:?}");
+    }
+
+    #[test]
+    fn watcher_reloads_when_file_changes() {
+        let dir = std::env::temp_dir().join(format!("memoapp-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("一時ディレクトリを作れる");
+        let path = dir.join(CONFIG_FILE_NAME);
+        std::fs::write(&path, "ui_scale = 1.0").expect("設定ファイルを書ける");
+
+        let mut watcher = ConfigWatcher::with_path(Some(path.clone()));
+        assert!(
+            watcher.check_interval().is_some(),
+            "ファイルがあれば監視する"
+        );
+        assert!(
+            watcher.poll(10.0).is_none(),
+            "変わっていなければ読み直さない"
+        );
+
+        std::fs::write(&path, "ui_scale = 2.0").expect("設定ファイルを書ける");
+        let later = SystemTime::now() + Duration::from_secs(10);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(later))
+            .expect("更新日時を変えられる");
+        assert!(
+            watcher.poll(10.5).is_none(),
+            "確認の間隔が空くまでは読み直さない"
+        );
+        let reloaded = watcher
+            .poll(11.0)
+            .expect("更新日時が変われば読み直す")
+            .expect("正しい設定ファイル");
+        assert_eq!(reloaded.ui_scale(), 2.0, "新しい内容を読み込む");
+        assert!(watcher.poll(12.0).is_none(), "同じ内容は読み直さない");
+
+        std::fs::remove_file(&path).expect("設定ファイルを消せる");
+        let removed = watcher
+            .poll(13.0)
+            .expect("削除も変更として扱う")
+            .expect("削除されたら既定値");
+        assert!(removed.ui_scale.is_none(), "既定値に戻る");
+        assert!(
+            watcher.check_interval().is_none(),
+            "ファイルがなければ監視しない"
+        );
+        std::fs::remove_dir_all(&dir).expect("一時ディレクトリを消せる");
+    }
+}
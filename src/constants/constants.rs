@@ -2,3 +2,9 @@ pub const TEXTBOX_MIN_HEIGHT: f32 = 60.0;
 pub const SLOT_SPACING: f32 = 3.0;
 pub const DEFAULT_PIXELS_PER_POINT: f32 = 1.5;
 pub const JUDGE_BUTTON_WIDTH: f32 = 120.0;
+pub const ICON_SIZE: f32 = 48.0;
+pub const ICON_AREA_WIDTH: f32 = 64.0;
+pub const SLOT_HANDLE_WIDTH: f32 = 16.0;
+pub const DEFAULT_SPLIT_LIMIT: usize = 300;
+pub const PLAYER_VIEW_FONT_SIZE: f32 = 32.0;
+pub const PLAYER_VIEW_ICON_SIZE: f32 = 96.0;
@@ -1 +1,2 @@
+#[expect(clippy::module_inception)]
 pub mod constants;
@@ -0,0 +1,472 @@
+use super::{ExportError, Exporter, Image, text, write_zip};
+use crate::parser::dice::{self, Command};
+use crate::placeholder::{self, Bindings};
+use crate::scenario::Scenario;
+use crate::scene::{Id, TextSlot};
+use serde_json::{Map, Value, json};
+
+/// ccfoliaのルームデータのバージョン
+const ROOM_DATA_VERSION: &str = "1.1.0";
+
+/// ccfoliaのルームデータ（zip）
+///
+/// ccfoliaの「ルームデータの読み込み」にドラッグして読み込める形にする。
+/// zipには`__data.json`と、SHA-256のハッシュをファイル名にした画像を入れる。
+pub struct RoomExporter;
+
+impl Exporter for RoomExporter {
+    fn name(&self) -> &'static str {
+        "ccfoliaルーム"
+    }
+
+    fn extension(&self) -> &'static str {
+        "zip"
+    }
+
+    fn export(&self, scenario: &Scenario) -> Result<Vec<u8>, ExportError> {
+        let room = build_room(scenario);
+        let mut files = vec![(String::from("__data.json"), serde_json::to_vec(&room.data)?)];
+        files.extend(
+            room.images
+                .into_iter()
+                .map(|image| (image.hash, image.bytes)),
+        );
+        write_zip(&files)
+    }
+}
+
+/// zipに入れる内容
+struct Room {
+    /// `__data.json`の内容
+    data: Value,
+    /// ハッシュをファイル名にして入れる画像
+    images: Vec<Image>,
+}
+
+/// シナリオをccfoliaのルームデータに変換する
+///
+/// - シーン → シーン（名前のみ）
+/// - プレイヤー一覧 → キャラクター（アイコン画像、チャットパレット付き）
+/// - 話者のないスロット（地の文など） → シーンごとのメモ
+///
+/// 読み込めないアイコン画像は省略する。
+fn build_room(scenario: &Scenario) -> Room {
+    let mut images: Vec<Image> = Vec::new();
+    let mut resources = Map::new();
+    let palettes = chat_palettes(scenario);
+
+    let mut characters = Map::new();
+    for (index, player) in scenario.player.iter().enumerate() {
+        let icon = player
+            .icon_path
+            .as_ref()
+            .and_then(|path| Image::read(path))
+            .map(|image| {
+                let hash = image.hash.clone();
+                if !resources.contains_key(&hash) {
+                    resources.insert(hash.clone(), json!({ "type": image.format.to_mime_type() }));
+                    images.push(image);
+                }
+                hash
+            });
+        // キャラクター名があればプレイヤー名はメモに残す
+        let (name, memo) = if player.character.is_empty() {
+            (&player.name, String::new())
+        } else {
+            (&player.character, format!("PL: {}", player.name))
+        };
+        let commands = palettes
+            .iter()
+            .find(|palette| palette.player == Some(player.id))
+            .map(ChatPalette::text)
+            .unwrap_or_default();
+        characters.insert(
+            format!("character{}", index + 1),
+            json!({
+                "name": name,
+                "memo": memo,
+                "initiative": 0,
+                "externalUrl": "",
+                "status": [],
+                "params": [],
+                "iconUrl": icon,
+                "faces": [],
+                "x": 0,
+                "y": 0,
+                "angle": 0,
+                "width": 4,
+                "height": 4,
+                "active": true,
+                "secret": false,
+                "invisible": false,
+                "hideStatus": false,
+                "color": "",
+                "commands": commands,
+                "owner": null,
+            }),
+        );
+    }
+
+    let mut scenes = Map::new();
+    let mut notes = Map::new();
+    for (index, scene) in scenario.scenes.iter().enumerate() {
+        scenes.insert(
+            format!("scene{}", index + 1),
+            json!({
+                "name": scene.title,
+                "order": index,
+                "backgroundUrl": null,
+                "foregroundUrl": null,
+                "text": "",
+            }),
+        );
+        let narration = text::narration_text(scenario, scene);
+        if !narration.is_empty() {
+            notes.insert(
+                format!("note{}", index + 1),
+                json!({
+                    "name": scene.title,
+                    "text": narration,
+                    "order": index,
+                }),
+            );
+        }
+    }
+
+    let data = json!({
+        "meta": { "version": ROOM_DATA_VERSION },
+        "entities": {
+            "room": {},
+            "items": {},
+            "decks": {},
+            "characters": characters,
+            "effects": {},
+            "scenes": scenes,
+            "notes": notes,
+        },
+        "resources": resources,
+    });
+    Room { data, images }
+}
+
+/// ccfoliaのキャラクター1人分のチャットパレット
+#[derive(Clone)]
+pub struct ChatPalette {
+    /// キャラクター名
+    pub name: String,
+    /// プレイヤー一覧のプレイヤーの場合はそのID（カスタムラベルはNone）
+    pub player: Option<Id>,
+    /// ダイスコマンド（出現順、重複なし）
+    pub commands: Vec<String>,
+    /// セリフ（出現順、重複なし、複数行のテキストは1行にまとめる）
+    pub lines: Vec<String>,
+}
+
+impl ChatPalette {
+    /// チャットパレット欄に貼り付けるテキスト（コマンド、セリフの順に1行ずつ）
+    pub fn text(&self) -> String {
+        let mut text = self.commands.join("\n");
+        if !self.commands.is_empty() && !self.lines.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&self.lines.join("\n"));
+        text
+    }
+}
+
+/// シナリオ全体から話者ごとのチャットパレットを作る
+///
+/// 話者はスロットに設定したプレイヤーまたはカスタムラベル（話者のないスロットは含めない）。
+/// プレイヤーはキャラクター名があればキャラクター名をパレット名にする。
+/// スロットの技能判定と、本文中のダイスコマンドだけの行はコマンドとして先頭に並べる。
+/// プレースホルダーは置き換えた後の文字列を使う。
+pub fn chat_palettes(scenario: &Scenario) -> Vec<ChatPalette> {
+    let bindings = Bindings {
+        players: &scenario.player,
+        variables: &scenario.variables,
+    };
+    let mut palettes: Vec<ChatPalette> = Vec::new();
+
+    for scene in &scenario.scenes {
+        for mode in &scenario.modes {
+            for slot in scene.slots(mode.id) {
+                let Some((player, name)) = speaker(scenario, slot) else {
+                    continue;
+                };
+                // プレイヤーはIDで、カスタムラベルは名前で同じ話者とみなす
+                let index = palettes
+                    .iter()
+                    .position(|palette| {
+                        palette.player == player && (player.is_some() || palette.name == name)
+                    })
+                    .unwrap_or_else(|| {
+                        palettes.push(ChatPalette {
+                            name,
+                            player,
+                            commands: Vec::new(),
+                            lines: Vec::new(),
+                        });
+                        palettes.len() - 1
+                    });
+                let Some(palette) = palettes.get_mut(index) else {
+                    continue;
+                };
+
+                if let Some(check) = &slot.check {
+                    let command = format!(
+                        "{} {}",
+                        Command::skill_check(scenario.system, check),
+                        check.skill
+                    );
+                    push_unique(&mut palette.commands, command.trim_end());
+                }
+                for judge in &mode.judges {
+                    let resolved = placeholder::resolve(slot.text(judge.id), bindings);
+                    let mut line = String::new();
+                    for text_line in resolved.text.lines().map(str::trim) {
+                        if dice::is_command(text_line) {
+                            push_unique(&mut palette.commands, text_line);
+                        } else if !text_line.is_empty() {
+                            if !line.is_empty() {
+                                line.push(' ');
+                            }
+                            line.push_str(text_line);
+                        }
+                    }
+                    push_unique(&mut palette.lines, &line);
+                }
+            }
+        }
+    }
+    palettes
+        .into_iter()
+        .filter(|palette| !palette.commands.is_empty() || !palette.lines.is_empty())
+        .collect()
+}
+
+/// スロットの話者（プレイヤーのID、パレット名）
+fn speaker(scenario: &Scenario, slot: &TextSlot) -> Option<(Option<Id>, String)> {
+    if let Some(player) = slot
+        .speaker
+        .and_then(|id| scenario.player.iter().find(|player| player.id == id))
+    {
+        let name = if player.character.is_empty() {
+            &player.name
+        } else {
+            &player.character
+        };
+        return Some((Some(player.id), name.clone()));
+    }
+    let label = slot
+        .label
+        .as_ref()
+        .filter(|label| !label.trim().is_empty())?;
+    Some((None, label.clone()))
+}
+
+fn push_unique(items: &mut Vec<String>, item: &str) {
+    if !item.is_empty() && !items.iter().any(|existing| existing == item) {
+        items.push(item.to_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{Scene, SkillCheck};
+    use crate::system::GameSystem;
+    use sha2::{Digest as _, Sha256};
+
+    /// 地の文、探偵（キャラクター名あり）のスロット2つ、ラベルだけのスロットを持つ1シーンのシナリオ
+    fn scenario() -> Scenario {
+        let mut scenario = Scenario {
+            system: GameSystem::Coc6,
+            modes: GameSystem::Coc6.modes(),
+            ..Scenario::default()
+        };
+        if let Some(detective) = scenario.player.get_mut(1) {
+            detective.character = String::from("明智");
+        }
+        let detective = scenario.player.get(1).map(|player| player.id);
+
+        let mut scene = Scene::new(1, &scenario.modes, None);
+        scene.title = String::from("図書館");
+        scene.contents.clear();
+        let (Some(narration), Some(player)) = (scenario.modes.first(), scenario.modes.get(1))
+        else {
+            panic!("地の文とプレイヤーのモードがある");
+        };
+        let mut narration_slot = TextSlot::new_empty(&narration.judges);
+        *narration_slot.text_mut(narration_slot.selected_judge) = String::from("静かな図書館。");
+        scene.slots_mut(narration.id).push(narration_slot);
+
+        let success = player
+            .judges
+            .get(2)
+            .map(|judge| judge.id)
+            .expect("成功がある");
+        let failure = player
+            .judges
+            .get(3)
+            .map(|judge| judge.id)
+            .expect("失敗がある");
+        let mut check_slot = TextSlot::new_empty(&player.judges);
+        check_slot.speaker = detective;
+        check_slot.check = Some(SkillCheck {
+            skill: String::from("図書館"),
+            target: 60,
+            ..SkillCheck::default()
+        });
+        *check_slot.text_mut(success) = String::from("1d100<=60\n本が見つかった。\n古い日記だ。");
+        *check_slot.text_mut(failure) = String::from("何もない。");
+        let mut repeat_slot = TextSlot::new_empty(&player.judges);
+        repeat_slot.speaker = detective;
+        *repeat_slot.text_mut(success) = String::from("何もない。");
+        let mut label_slot = TextSlot::new_empty(&player.judges).with_label(String::from("司書"));
+        *label_slot.text_mut(success) = String::from("お静かに。");
+        scene
+            .slots_mut(player.id)
+            .extend([check_slot, repeat_slot, label_slot]);
+
+        scenario.scenes = vec![scene];
+        scenario
+    }
+
+    #[test]
+    fn chat_palettes_per_speaker() {
+        let scenario = scenario();
+        let palettes = chat_palettes(&scenario);
+        let names: Vec<&str> = palettes
+            .iter()
+            .map(|palette| palette.name.as_str())
+            .collect();
+        assert_eq!(names, ["明智", "司書"], "話者のないスロットは含めない");
+
+        let detective = palettes.first().expect("探偵のパレットがある");
+        assert_eq!(
+            detective.player,
+            scenario.player.get(1).map(|player| player.id),
+            "プレイヤーのID"
+        );
+        assert_eq!(
+            detective.commands,
+            ["CCB<=60 図書館", "1d100<=60"],
+            "技能判定と本文中のダイスコマンド"
+        );
+        assert_eq!(
+            detective.lines,
+            ["本が見つかった。 古い日記だ。", "何もない。"],
+            "複数行は1行にまとめ、同じセリフは1回だけ"
+        );
+        assert_eq!(
+            detective.text(),
+            "CCB<=60 図書館\n1d100<=60\n本が見つかった。 古い日記だ。\n何もない。",
+            "コマンド、セリフの順に1行ずつ"
+        );
+
+        let librarian = palettes.get(1).expect("司書のパレットがある");
+        assert_eq!(librarian.player, None, "カスタムラベルはプレイヤーではない");
+        assert_eq!(
+            librarian.text(),
+            "お静かに。",
+            "コマンドがなければセリフだけ"
+        );
+    }
+
+    /// 一時ディレクトリにアイコン画像を書き出す
+    fn write_icon(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("ccfolia-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("一時ディレクトリを作れる");
+        let path = dir.join(name);
+        image::RgbaImage::new(2, 2)
+            .save(&path)
+            .expect("画像を書き込める");
+        path
+    }
+
+    /// 画像ファイルの内容のハッシュ
+    fn file_hash(path: &std::path::Path) -> String {
+        let bytes = std::fs::read(path).expect("画像を読める");
+        Sha256::digest(&bytes)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// JSONポインタの位置の値（なければnull）
+    fn at<'a>(value: &'a Value, pointer: &str) -> &'a Value {
+        value.pointer(pointer).unwrap_or(&Value::Null)
+    }
+
+    #[test]
+    fn room_data_has_characters_scenes_and_notes() {
+        let mut scenario = scenario();
+        let icon = write_icon("room.png");
+        for player in scenario.player.iter_mut().take(2) {
+            player.icon_path = Some(icon.clone());
+        }
+        let room = build_room(&scenario);
+        let data = &room.data;
+
+        let detective = at(data, "/entities/characters/character2");
+        assert_eq!(at(detective, "/name"), "明智", "キャラクター名");
+        assert_eq!(
+            at(detective, "/memo"),
+            "PL: 探偵",
+            "プレイヤー名はメモに残す"
+        );
+        assert_eq!(
+            at(detective, "/commands"),
+            "CCB<=60 図書館\n1d100<=60\n本が見つかった。 古い日記だ。\n何もない。",
+            "チャットパレット"
+        );
+        assert_eq!(
+            at(data, "/entities/characters/character3/iconUrl"),
+            &Value::Null,
+            "アイコンのないキャラクター"
+        );
+        assert_eq!(
+            at(data, "/entities/scenes/scene1/name"),
+            "図書館",
+            "シーン名"
+        );
+        assert_eq!(
+            at(data, "/entities/notes/note1/text"),
+            "◆ 地の文\n\n静かな図書館。",
+            "話者のないスロットはメモ"
+        );
+
+        let hash = file_hash(&icon);
+        assert_eq!(
+            at(detective, "/iconUrl"),
+            hash.as_str(),
+            "アイコンは内容のハッシュで参照する"
+        );
+        assert_eq!(
+            data.get("resources")
+                .and_then(|resources| resources.get(&hash))
+                .and_then(|resource| resource.get("type")),
+            Some(&json!("image/png")),
+            "画像の種類"
+        );
+        assert_eq!(room.images.len(), 1, "同じ画像は1つだけ入れる");
+    }
+
+    #[test]
+    fn room_zip_entries_are_named_by_hash() {
+        let mut scenario = scenario();
+        let icon = write_icon("zip.png");
+        if let Some(player) = scenario.player.get_mut(1) {
+            player.icon_path = Some(icon.clone());
+        }
+        let bytes = RoomExporter.export(&scenario).expect("書き出せる");
+        let archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).expect("zipとして読める");
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort_unstable();
+
+        let hash = file_hash(&icon);
+        let mut expected = vec!["__data.json", hash.as_str()];
+        expected.sort_unstable();
+        assert_eq!(names, expected, "データと、ハッシュを名前にした画像");
+    }
+}
@@ -0,0 +1,146 @@
+pub mod ccfolia;
+pub mod text;
+pub mod udonarium;
+
+use crate::scenario::Scenario;
+use sha2::{Digest as _, Sha256};
+use std::io::Write as _;
+use std::path::Path;
+
+/// エクスポート時のエラー
+#[derive(Debug)]
+pub enum ExportError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Zip(zip::result::ZipError),
+}
+
+impl std::fmt::Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "書き出しに失敗しました: {err}"),
+            Self::Json(err) => write!(f, "JSONの作成に失敗しました: {err}"),
+            Self::Zip(err) => write!(f, "zipの作成に失敗しました: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<std::io::Error> for ExportError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ExportError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl From<zip::result::ZipError> for ExportError {
+    fn from(err: zip::result::ZipError) -> Self {
+        Self::Zip(err)
+    }
+}
+
+/// ファイル名に使えない文字を`_`に置き換える（空なら`untitled`）
+pub fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_control() || matches!(c, '\\' | '/' | ':' | '*' | '?' | '"' | '<' | '>' | '|') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    if stem.is_empty() {
+        String::from("untitled")
+    } else {
+        stem
+    }
+}
+
+/// 書き出すzipに入れる画像
+struct Image {
+    /// 内容のSHA-256（16進数の小文字）
+    hash: String,
+    format: image::ImageFormat,
+    bytes: Vec<u8>,
+}
+
+impl Image {
+    /// 画像ファイルを読み込む（読み込めない、画像でない場合はNone）
+    fn read(path: &Path) -> Option<Self> {
+        let bytes = std::fs::read(path).ok()?;
+        let format = image::guess_format(&bytes).ok()?;
+        let hash = Sha256::digest(&bytes)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        Some(Self {
+            hash,
+            format,
+            bytes,
+        })
+    }
+}
+
+/// (ファイル名, 内容)の一覧をzipにまとめる
+fn write_zip(files: &[(String, Vec<u8>)]) -> Result<Vec<u8>, ExportError> {
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for (name, bytes) in files {
+        zip.start_file(name.as_str(), options)?;
+        zip.write_all(bytes)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+/// シナリオを外部形式に書き出すためのインターフェース
+pub trait Exporter {
+    /// メニューに表示する形式名
+    fn name(&self) -> &'static str;
+
+    /// 出力ファイルの拡張子
+    fn extension(&self) -> &'static str;
+
+    /// シナリオを書き出したバイト列を返す
+    ///
+    /// # Errors
+    /// 書き出しに必要なファイルの読み込みなどに失敗した場合
+    fn export(&self, scenario: &Scenario) -> Result<Vec<u8>, ExportError>;
+}
+
+/// Fileメニューから選べるエクスポート形式
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    PlainText,
+    CcfoliaRoom,
+    Udonarium,
+}
+
+impl ExportFormat {
+    pub const ALL: [Self; 4] = [
+        Self::Markdown,
+        Self::PlainText,
+        Self::CcfoliaRoom,
+        Self::Udonarium,
+    ];
+
+    /// 形式に対応するExporter
+    pub fn exporter(self) -> &'static dyn Exporter {
+        match self {
+            Self::Markdown => &text::MarkdownExporter,
+            Self::PlainText => &text::PlainTextExporter,
+            Self::CcfoliaRoom => &ccfolia::RoomExporter,
+            Self::Udonarium => &udonarium::UdonariumExporter,
+        }
+    }
+}
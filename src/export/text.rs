@@ -0,0 +1,289 @@
+use super::{ExportError, Exporter};
+use crate::parser::markdown;
+use crate::placeholder::{self, Bindings};
+use crate::scenario::Scenario;
+use crate::scene::{Judge, Mode, Scene, TextSlot};
+
+/// Markdown形式（`parser::markdown`で読み込める形）
+pub struct MarkdownExporter;
+
+/// プレーンテキスト形式
+pub struct PlainTextExporter;
+
+impl Exporter for MarkdownExporter {
+    fn name(&self) -> &'static str {
+        "Markdown"
+    }
+
+    fn extension(&self) -> &'static str {
+        "md"
+    }
+
+    fn export(&self, scenario: &Scenario) -> Result<Vec<u8>, ExportError> {
+        Ok(to_markdown(scenario).into_bytes())
+    }
+}
+
+impl Exporter for PlainTextExporter {
+    fn name(&self) -> &'static str {
+        "テキスト"
+    }
+
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn export(&self, scenario: &Scenario) -> Result<Vec<u8>, ExportError> {
+        Ok(to_plain_text(scenario).into_bytes())
+    }
+}
+
+/// シナリオ全体をMarkdownに変換する
+///
+/// シーンは`#`、モードは`##`、ラベル付きスロットは`###`、話者がプレイヤーのスロットは`### @プレイヤー名`、
+/// 判定は`【判定名】`で表す。ラベルのないスロットの区切りには`---`を使う。空のテキストは出力しない。
+/// 見出しや区切りと同じ形の本文の行は`\`を付けて書き出す。
+pub fn to_markdown(scenario: &Scenario) -> String {
+    let mut out = String::new();
+    for scene in &scenario.scenes {
+        out.push_str(&format!("# {}\n\n", scene.title));
+        for (mode, slots) in filled_modes(&scenario.modes, scene) {
+            out.push_str(&format!("## {}\n\n", mode.name));
+            let single_judge = mode.judges.len() == 1;
+            for (slot_index, slot) in slots.iter().filter(|slot| has_text(mode, slot)).enumerate() {
+                // 話者が設定されていれば話者名を見出しにする（プレイヤーは読み込みで話者に戻せるよう印を付ける）
+                let player = slot
+                    .speaker
+                    .and_then(|id| scenario.player.iter().find(|player| player.id == id));
+                match (player, &slot.label) {
+                    (Some(player), _) => out.push_str(&format!(
+                        "### {}{}\n\n",
+                        markdown::SPEAKER_MARKER,
+                        player.name
+                    )),
+                    (None, Some(label)) => out.push_str(&format!("### {label}\n\n")),
+                    (None, None) if slot_index > 0 => out.push_str("---\n\n"),
+                    (None, None) => {}
+                }
+                for (judge, text) in filled_texts(mode, slot) {
+                    // 判定が1つだけのモードはラベルを省略する（先頭の判定として読み込まれる）
+                    if !single_judge {
+                        out.push_str(&format!("【{}】\n", judge.name));
+                    }
+                    for line in text.lines() {
+                        out.push_str(&markdown::escape_line(line));
+                        out.push('\n');
+                    }
+                    out.push('\n');
+                }
+            }
+        }
+    }
+    out
+}
+
+/// シナリオ全体をプレーンテキストに変換する
+pub fn to_plain_text(scenario: &Scenario) -> String {
+    let mut out = String::new();
+    for scene in &scenario.scenes {
+        out.push_str(&format!("■ {}\n\n", scene.title));
+        for (mode, slots) in filled_modes(&scenario.modes, scene) {
+            out.push_str(&format!("◆ {}\n\n", mode.name));
+            let single_judge = mode.judges.len() == 1;
+            for slot in slots.iter().filter(|slot| has_text(mode, slot)) {
+                if let Some(speaker) = slot.speaker(&scenario.player) {
+                    out.push_str(&format!("・{}\n", speaker.name));
+                }
+                for (judge, text) in filled_texts(mode, slot) {
+                    if !single_judge {
+                        out.push_str(&format!("［{}］\n", judge.name));
+                    }
+                    out.push_str(&format!("{text}\n\n"));
+                }
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// シーンの話者のないスロットのテキスト（モードごと、判定が複数あれば判定名付き）
+pub fn narration_text(scenario: &Scenario, scene: &Scene) -> String {
+    let bindings = Bindings {
+        players: &scenario.player,
+        variables: &scenario.variables,
+    };
+    let mut sections = Vec::new();
+    for mode in &scenario.modes {
+        let single_judge = mode.judges.len() == 1;
+        let mut texts = Vec::new();
+        for slot in scene.slots(mode.id) {
+            if slot.speaker(&scenario.player).is_some() {
+                continue;
+            }
+            for judge in &mode.judges {
+                let text = slot.text(judge.id).trim();
+                if text.is_empty() {
+                    continue;
+                }
+                let resolved = placeholder::resolve(text, bindings).text;
+                texts.push(if single_judge {
+                    resolved.into_owned()
+                } else {
+                    format!("［{}］\n{resolved}", judge.name)
+                });
+            }
+        }
+        if !texts.is_empty() {
+            sections.push(format!("◆ {}\n\n{}", mode.name, texts.join("\n\n")));
+        }
+    }
+    sections.join("\n\n")
+}
+
+/// テキストのあるスロットを持つモードとそのスロット一覧（モードの並び順）
+fn filled_modes<'a>(
+    modes: &'a [Mode],
+    scene: &'a Scene,
+) -> impl Iterator<Item = (&'a Mode, &'a [TextSlot])> {
+    modes
+        .iter()
+        .map(|mode| (mode, scene.slots(mode.id)))
+        .filter(|(mode, slots)| slots.iter().any(|slot| has_text(mode, slot)))
+}
+
+/// 空でない判定テキスト（判定の並び順）
+fn filled_texts<'a>(
+    mode: &'a Mode,
+    slot: &'a TextSlot,
+) -> impl Iterator<Item = (&'a Judge, &'a str)> {
+    mode.judges
+        .iter()
+        .map(|judge| (judge, slot.text(judge.id).trim()))
+        .filter(|(_, text)| !text.is_empty())
+}
+
+fn has_text(mode: &Mode, slot: &TextSlot) -> bool {
+    filled_texts(mode, slot).next().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::markdown;
+    use crate::scene::Id;
+
+    /// シーンごと、モードごとの空でないスロットの判定テキスト
+    fn texts(scenario: &Scenario, scenes: &[Scene]) -> Vec<Vec<String>> {
+        scenes
+            .iter()
+            .map(|scene| {
+                filled_modes(&scenario.modes, scene)
+                    .flat_map(|(mode, slots)| {
+                        slots
+                            .iter()
+                            .filter(|slot| has_text(mode, slot))
+                            .flat_map(|slot| {
+                                filled_texts(mode, slot).map(|(_, text)| text.to_owned())
+                            })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn markdown_round_trip_keeps_structural_lines() {
+        let mut scenario = Scenario::default();
+        let mode = scenario.modes.get(1).expect("判定が複数のモードがある");
+        let (mode_id, judge_id) = (mode.id, mode.judges.first().expect("判定がある").id);
+        let scene = scenario.scenes.first_mut().expect("サンプルのシーンがある");
+        let slot = scene
+            .slots_mut(mode_id)
+            .first_mut()
+            .expect("スロットがある");
+        *slot.text_mut(judge_id) = String::from(
+            "# シーン見出し\n## モード見出し\n### ラベル\n---\n***\n【失敗】\n\\円記号\n  # 字下げした見出し\n本文",
+        );
+
+        let source = to_markdown(&scenario);
+        let result = markdown::parse(&source, &scenario.modes, &scenario.player);
+        assert!(
+            result.warnings.is_empty(),
+            "警告はない: {:?}",
+            result.warnings
+        );
+        assert_eq!(
+            result.scenes.len(),
+            scenario.scenes.len(),
+            "シーンは増えない"
+        );
+        assert_eq!(
+            texts(&scenario, &result.scenes),
+            texts(&scenario, &scenario.scenes),
+            "書き出したテキストがそのまま読み込まれる"
+        );
+    }
+
+    #[test]
+    fn markdown_round_trip_keeps_speakers() {
+        let mut scenario = Scenario::default();
+        let player = scenario.player.first().map(|player| player.id);
+        let mode_id = scenario.modes.get(1).expect("判定が複数のモードがある").id;
+        let scene = scenario.scenes.first_mut().expect("サンプルのシーンがある");
+        let slots = scene.slots_mut(mode_id);
+        let mut copy = slots.first().cloned().expect("スロットがある");
+        copy.id = Id::new();
+        slots.push(copy);
+        let [speaker, label, ..] = slots.as_mut_slice() else {
+            panic!("スロットが2つ以上ある");
+        };
+        speaker.speaker = player;
+        label.label = Some(String::from("図書館"));
+
+        let source = to_markdown(&scenario);
+        let result = markdown::parse(&source, &scenario.modes, &scenario.player);
+        let imported = result
+            .scenes
+            .first()
+            .map(|scene| scene.slots(mode_id))
+            .expect("シーンを読み込める");
+        assert_eq!(
+            imported.first().map(|slot| slot.speaker),
+            Some(player),
+            "話者のプレイヤーに戻る"
+        );
+        assert_eq!(
+            imported.get(1).and_then(|slot| slot.label.as_deref()),
+            Some("図書館"),
+            "ラベルはラベルのまま"
+        );
+    }
+
+    #[test]
+    fn escapes_only_structural_lines() {
+        assert_eq!(markdown::escape_line("# 見出し"), "\\# 見出し", "見出し");
+        assert_eq!(markdown::escape_line("---"), "\\---", "区切り");
+        assert_eq!(
+            markdown::escape_line("【成功】"),
+            "\\【成功】",
+            "判定ラベル"
+        );
+        assert_eq!(markdown::escape_line("\\"), "\\\\", "円記号で始まる行");
+        assert_eq!(
+            markdown::escape_line("#タグ"),
+            "#タグ",
+            "空白のない#は見出しではない"
+        );
+        assert_eq!(
+            markdown::escape_line("#### 小見出し"),
+            "#### 小見出し",
+            "4階層目は本文"
+        );
+        assert_eq!(
+            markdown::escape_line("【成功】した"),
+            "【成功】した",
+            "ラベルだけの行ではない"
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use super::ccfolia::{self, ChatPalette};
+use super::{ExportError, Exporter, Image, text, write_zip};
+use crate::scenario::Scenario;
+use crate::system::GameSystem;
+
+/// テーブル上のマス目の大きさ（px）
+const GRID_SIZE: usize = 50;
+
+/// メモの大きさ（マス）
+const NOTE_SIZE: usize = 6;
+
+/// Udonariumのセーブデータ（zip）
+///
+/// Udonariumの画面にドラッグして読み込める形にする。
+/// zipにはキャラクターと共有メモのXMLを1つずつ入れ、画像はSHA-256のハッシュをファイル名にして入れる
+/// （Udonariumは画像をハッシュで識別する）。
+pub struct UdonariumExporter;
+
+impl Exporter for UdonariumExporter {
+    fn name(&self) -> &'static str {
+        "Udonarium"
+    }
+
+    fn extension(&self) -> &'static str {
+        "zip"
+    }
+
+    fn export(&self, scenario: &Scenario) -> Result<Vec<u8>, ExportError> {
+        let palettes = ccfolia::chat_palettes(scenario);
+        let mut files = Vec::new();
+        let mut images: Vec<Image> = Vec::new();
+
+        for (index, player) in scenario.player.iter().enumerate() {
+            let image = player.icon_path.as_deref().and_then(Image::read);
+            let name = if player.character.is_empty() {
+                &player.name
+            } else {
+                &player.character
+            };
+            let character = Character {
+                name,
+                player: (!player.character.is_empty()).then_some(player.name.as_str()),
+                image: image.as_ref().map(|image| image.hash.as_str()),
+                palette: palettes
+                    .iter()
+                    .find(|palette| palette.player == Some(player.id)),
+                x: index * GRID_SIZE,
+            };
+            files.push((
+                format!("character{}.xml", index + 1),
+                character.to_xml(scenario.system).into_bytes(),
+            ));
+            if let Some(image) = image {
+                if !images.iter().any(|existing| existing.hash == image.hash) {
+                    images.push(image);
+                }
+            }
+        }
+
+        // シーンごとの地の文を共有メモとして横に並べる
+        let notes = scenario
+            .scenes
+            .iter()
+            .map(|scene| (scene, text::narration_text(scenario, scene)))
+            .filter(|(_, narration)| !narration.is_empty());
+        for (index, (scene, narration)) in notes.enumerate() {
+            let x = index * (NOTE_SIZE + 1) * GRID_SIZE;
+            files.push((
+                format!("note{}.xml", index + 1),
+                note_xml(&scene.title, &narration, x).into_bytes(),
+            ));
+        }
+
+        files.extend(images.into_iter().map(|image| {
+            let extension = image
+                .format
+                .extensions_str()
+                .first()
+                .copied()
+                .unwrap_or("png");
+            (format!("{}.{extension}", image.hash), image.bytes)
+        }));
+        write_zip(&files)
+    }
+}
+
+/// キャラクター1人分の内容
+struct Character<'a> {
+    name: &'a str,
+    /// キャラクター名と別にプレイヤー名がある場合
+    player: Option<&'a str>,
+    /// アイコン画像のハッシュ
+    image: Option<&'a str>,
+    palette: Option<&'a ChatPalette>,
+    /// テーブル上の横位置（px）
+    x: usize,
+}
+
+impl Character<'_> {
+    fn to_xml(&self, system: GameSystem) -> String {
+        let detail = self.player.map_or_else(String::new, |player| {
+            format!(
+                r#"
+      <data name="情報">
+        <data type="note" name="PL">{}</data>
+      </data>
+    "#,
+                escape(player)
+            )
+        });
+        let palette = self
+            .palette
+            .map(|palette| format!("{}\n", escape(&palette.text())))
+            .unwrap_or_default();
+        format!(
+            r#"<character location.name="table" location.x="{x}" location.y="0" posZ="0" rotate="0" roll="0">
+  <data name="character">
+    <data name="image">
+      <data type="image" name="imageIdentifier">{image}</data>
+    </data>
+    <data name="common">
+      <data name="name">{name}</data>
+      <data name="size">1</data>
+    </data>
+    <data name="detail">{detail}</data>
+  </data>
+  <chat-palette dicebot="{dicebot}">
+{palette}  </chat-palette>
+</character>
+"#,
+            x = self.x,
+            image = escape(self.image.unwrap_or_default()),
+            name = escape(self.name),
+            dicebot = dicebot(system),
+        )
+    }
+}
+
+/// 共有メモのXML
+fn note_xml(title: &str, text: &str, x: usize) -> String {
+    format!(
+        r#"<text-note location.name="table" location.x="{x}" location.y="{y}" posZ="0" password="">
+  <data name="text-note">
+    <data name="image">
+      <data type="image" name="imageIdentifier"></data>
+    </data>
+    <data name="common">
+      <data name="title">{title}</data>
+      <data name="height">{NOTE_SIZE}</data>
+      <data name="width">{NOTE_SIZE}</data>
+      <data name="fontsize">5</data>
+      <data type="note" name="text">{text}</data>
+    </data>
+    <data name="detail"></data>
+  </data>
+</text-note>
+"#,
+        y = 2 * GRID_SIZE,
+        title = escape(title),
+        text = escape(text),
+    )
+}
+
+/// Udonariumのダイスボットの名前
+fn dicebot(system: GameSystem) -> &'static str {
+    match system {
+        GameSystem::Generic => "DiceBot",
+        GameSystem::Coc6 => "Cthulhu",
+        GameSystem::Coc7 => "Cthulhu7th",
+        GameSystem::SwordWorld25 => "SwordWorld2.5",
+        GameSystem::Emoklore => "Emoklore",
+    }
+}
+
+/// XMLの特殊文字をエスケープする
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest as _, Sha256};
+    use std::io::Read as _;
+
+    fn unzip(bytes: Vec<u8>) -> zip::ZipArchive<std::io::Cursor<Vec<u8>>> {
+        zip::ZipArchive::new(std::io::Cursor::new(bytes)).expect("zipとして読める")
+    }
+
+    fn read_entry(archive: &mut zip::ZipArchive<std::io::Cursor<Vec<u8>>>, name: &str) -> String {
+        let mut text = String::new();
+        archive
+            .by_name(name)
+            .expect("ファイルがある")
+            .read_to_string(&mut text)
+            .expect("UTF-8で読める");
+        text
+    }
+
+    #[test]
+    fn exports_characters_notes_and_hashed_images() {
+        let dir = std::env::temp_dir().join(format!("udonarium-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("一時ディレクトリを作れる");
+        let icon = dir.join("icon.png");
+        image::RgbaImage::new(2, 2)
+            .save(&icon)
+            .expect("画像を書き込める");
+        let hash: String = Sha256::digest(std::fs::read(&icon).expect("画像を読める"))
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+
+        let mut scenario = Scenario {
+            system: GameSystem::Coc7,
+            ..Scenario::default()
+        };
+        if let Some(detective) = scenario.player.get_mut(1) {
+            detective.character = String::from("明智 & <小林>");
+            detective.icon_path = Some(icon.clone());
+        }
+        let players = scenario.player.len();
+        let bytes = UdonariumExporter.export(&scenario).expect("書き出せる");
+        let mut archive = unzip(bytes);
+
+        let mut names: Vec<String> = archive.file_names().map(str::to_owned).collect();
+        names.sort_unstable();
+        let mut expected: Vec<String> = (1..=players)
+            .map(|index| format!("character{index}.xml"))
+            .chain((1..=scenario.scenes.len()).map(|index| format!("note{index}.xml")))
+            .chain(std::iter::once(format!("{hash}.png")))
+            .collect();
+        expected.sort_unstable();
+        assert_eq!(
+            names, expected,
+            "キャラクター、シーンごとのメモ、ハッシュを名前にした画像"
+        );
+
+        let character = read_entry(&mut archive, "character2.xml");
+        assert!(
+            character.contains(r#"<data name="name">明智 &amp; &lt;小林&gt;</data>"#),
+            "キャラクター名をエスケープする: {character}"
+        );
+        assert!(
+            character.contains(r#"<data type="note" name="PL">探偵</data>"#),
+            "プレイヤー名: {character}"
+        );
+        assert!(
+            character.contains(&format!(r#"name="imageIdentifier">{hash}</data>"#)),
+            "画像はハッシュで参照する: {character}"
+        );
+        assert!(
+            character.contains(r#"<chat-palette dicebot="Cthulhu7th">"#),
+            "ゲームシステムのダイスボット: {character}"
+        );
+
+        let note = read_entry(&mut archive, "note1.xml");
+        let title = scenario
+            .scenes
+            .first()
+            .map(|scene| scene.title.clone())
+            .unwrap_or_default();
+        assert!(
+            note.contains(&format!(r#"<data name="title">{title}</data>"#)),
+            "メモの題名はシーン名: {note}"
+        );
+        std::fs::remove_dir_all(&dir).expect("一時ディレクトリを消せる");
+    }
+
+    #[test]
+    fn escapes_xml_special_characters() {
+        assert_eq!(
+            escape(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&apos;&amp;&apos;&lt;/a&gt;",
+            "5つの特殊文字"
+        );
+    }
+}
@@ -0,0 +1,985 @@
+use crate::app::Player;
+use crate::placeholder::Variable;
+use crate::scenario::Scenario;
+use crate::scene::{Id, Mode, Scene, TextSlot, find_mode};
+use crate::system::GameSystem;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 元に戻す
+pub const UNDO_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+
+/// やり直し
+pub const REDO_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+    egui::Key::Z,
+);
+
+/// 元に戻す履歴に保持する変更の大きさの上限（バイト数の目安）
+const MAX_HISTORY_BYTES: usize = 16 * 1024 * 1024;
+
+/// 1つの操作あたりの大きさの目安（変更の中身とは別にかかる分）
+const ENTRY_OVERHEAD: usize = 64;
+
+/// この秒数以内に続いた同じ箇所への入力は1つの操作にまとめる
+const BURST_SECONDS: f64 = 1.0;
+
+/// シナリオへの1つの変更
+///
+/// 変更する箇所と、そこに入れる値だけを持つ（シナリオ全体は複製しない）。
+/// 適用すると、元の値を持つ反対向きの変更を返す。
+pub enum Change {
+    /// スロットの1つの判定のテキスト
+    Text {
+        scene: Id,
+        slot: Id,
+        judge: Id,
+        text: String,
+    },
+    /// シーン名
+    Title { scene: Id, title: String },
+    /// スロット1つ（話者、ラベル、技能判定など。選択中の判定は今のまま）
+    Slot { scene: Id, slot: Box<TextSlot> },
+    /// スロットを`mode`の`index`の位置に挿入
+    InsertSlot {
+        scene: Id,
+        mode: Id,
+        index: usize,
+        slot: Box<TextSlot>,
+    },
+    /// スロットの削除
+    RemoveSlot { scene: Id, slot: Id },
+    /// スロットを`from`から取り出して`to`の位置に挿入
+    MoveSlot {
+        scene: Id,
+        mode: Id,
+        from: usize,
+        to: usize,
+    },
+    /// シーンを`index`の位置に挿入
+    InsertScene { index: usize, scene: Box<Scene> },
+    /// シーンの削除
+    RemoveScene { scene: Id },
+    /// シーンの並び順
+    SceneOrder(Vec<Id>),
+    /// ゲームシステムとモード/判定一覧（`contents`は差し戻す（シーンID, モードID, スロット一覧））
+    Modes {
+        system: GameSystem,
+        modes: Vec<Mode>,
+        contents: Vec<(Id, Id, Vec<TextSlot>)>,
+    },
+    /// プレイヤー一覧
+    Players(Vec<Player>),
+    /// 変数一覧
+    Variables(Vec<Variable>),
+    /// 順に適用する変更
+    Batch(Vec<Change>),
+}
+
+/// 続けて入力したときに1つの操作にまとめる変更先
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Text(Id, Id),
+    Title(Id),
+    Slot(Id),
+    Modes,
+    Players,
+    Variables,
+}
+
+impl Change {
+    /// 何もしない変更
+    fn none() -> Self {
+        Self::Batch(Vec::new())
+    }
+
+    /// モード/判定を変える前の状態（`mode_ids`のモードのスロットも戻せるよう取っておく）
+    pub fn modes(scenario: &Scenario, mode_ids: &[Id]) -> Self {
+        Self::Modes {
+            system: scenario.system,
+            modes: scenario.modes.clone(),
+            contents: saved_contents(scenario, mode_ids),
+        }
+    }
+
+    /// 変更を適用する
+    /// 返り値: 適用した変更を取り消す変更（変更先がなければ何もしない変更）
+    pub fn apply(self, scenario: &mut Scenario) -> Self {
+        if let Some(scene) = self.scene() {
+            return match scenario.scenes.iter_mut().find(|target| target.id == scene) {
+                Some(target) => self.apply_to_scene(target),
+                None => Self::none(),
+            };
+        }
+        match self {
+            Self::InsertScene { index, scene } => {
+                let id = scene.id;
+                let index = index.min(scenario.scenes.len());
+                scenario.scenes.insert(index, *scene);
+                Self::RemoveScene { scene: id }
+            }
+            Self::RemoveScene { scene } => {
+                let Some(index) = scenario.scene_position(scene) else {
+                    return Self::none();
+                };
+                let removed = scenario.scenes.remove(index);
+                Self::InsertScene {
+                    index,
+                    scene: Box::new(removed),
+                }
+            }
+            Self::SceneOrder(order) => {
+                let current = scenario.scenes.iter().map(|scene| scene.id).collect();
+                // 並び順にないシーンは末尾に残す
+                scenario.scenes.sort_by_key(|scene| {
+                    order
+                        .iter()
+                        .position(|id| *id == scene.id)
+                        .unwrap_or(usize::MAX)
+                });
+                Self::SceneOrder(current)
+            }
+            Self::Modes {
+                system,
+                modes,
+                contents,
+            } => apply_modes(scenario, system, modes, contents),
+            Self::Players(players) => {
+                Self::Players(std::mem::replace(&mut scenario.player, players))
+            }
+            Self::Variables(variables) => {
+                Self::Variables(std::mem::replace(&mut scenario.variables, variables))
+            }
+            Self::Batch(changes) => {
+                let mut undo: Vec<Self> = changes
+                    .into_iter()
+                    .map(|change| change.apply(scenario))
+                    .collect();
+                undo.reverse();
+                Self::Batch(undo)
+            }
+            Self::Text { .. }
+            | Self::Title { .. }
+            | Self::Slot { .. }
+            | Self::InsertSlot { .. }
+            | Self::RemoveSlot { .. }
+            | Self::MoveSlot { .. } => Self::none(),
+        }
+    }
+
+    /// シーン1つの中で完結する変更の対象のシーン
+    fn scene(&self) -> Option<Id> {
+        match self {
+            Self::Text { scene, .. }
+            | Self::Title { scene, .. }
+            | Self::Slot { scene, .. }
+            | Self::InsertSlot { scene, .. }
+            | Self::RemoveSlot { scene, .. }
+            | Self::MoveSlot { scene, .. } => Some(*scene),
+            _ => None,
+        }
+    }
+
+    /// シーン1つの中で完結する変更を適用する
+    fn apply_to_scene(self, target: &mut Scene) -> Self {
+        let scene = target.id;
+        match self {
+            Self::Text {
+                slot, judge, text, ..
+            } => {
+                let Some(field) = target.find_slot_mut(slot).map(|slot| slot.text_mut(judge))
+                else {
+                    return Self::none();
+                };
+                let text = std::mem::replace(field, text);
+                Self::Text {
+                    scene,
+                    slot,
+                    judge,
+                    text,
+                }
+            }
+            Self::Title { title, .. } => {
+                let title = std::mem::replace(&mut target.title, title);
+                Self::Title { scene, title }
+            }
+            Self::Slot { slot, .. } => {
+                let Some(current) = target.find_slot_mut(slot.id) else {
+                    return Self::none();
+                };
+                let mut slot = *slot;
+                if slot.texts.contains_key(&current.selected_judge) {
+                    slot.selected_judge = current.selected_judge;
+                }
+                let old = std::mem::replace(current, slot);
+                target.layout_cache = None;
+                Self::Slot {
+                    scene,
+                    slot: Box::new(old),
+                }
+            }
+            Self::InsertSlot {
+                mode, index, slot, ..
+            } => {
+                let id = slot.id;
+                let slots = target.slots_mut(mode);
+                slots.insert(index.min(slots.len()), *slot);
+                target.layout_cache = None;
+                Self::RemoveSlot { scene, slot: id }
+            }
+            Self::RemoveSlot { slot, .. } => {
+                let Some((mode, index)) = target.contents.iter().find_map(|(mode, slots)| {
+                    slots
+                        .iter()
+                        .position(|candidate| candidate.id == slot)
+                        .map(|index| (*mode, index))
+                }) else {
+                    return Self::none();
+                };
+                let removed = target.slots_mut(mode).remove(index);
+                target.layout_cache = None;
+                Self::InsertSlot {
+                    scene,
+                    mode,
+                    index,
+                    slot: Box::new(removed),
+                }
+            }
+            Self::MoveSlot { mode, from, to, .. } => {
+                let slots = target.slots_mut(mode);
+                if from >= slots.len() {
+                    return Self::none();
+                }
+                let slot = slots.remove(from);
+                let to = to.min(slots.len());
+                slots.insert(to, slot);
+                target.layout_cache = None;
+                Self::MoveSlot {
+                    scene,
+                    mode,
+                    from: to,
+                    to: from,
+                }
+            }
+            _ => Self::none(),
+        }
+    }
+
+    /// 保持に使う大きさの目安（バイト数）
+    fn size(&self) -> usize {
+        match self {
+            Self::Text { text, .. } => text.len(),
+            Self::Title { title, .. } => title.len(),
+            Self::Slot { slot, .. } | Self::InsertSlot { slot, .. } => slot_size(slot),
+            Self::RemoveSlot { .. } | Self::MoveSlot { .. } | Self::RemoveScene { .. } => 0,
+            Self::InsertScene { scene, .. } => scene
+                .contents
+                .values()
+                .flatten()
+                .map(slot_size)
+                .sum::<usize>()
+                .saturating_add(scene.title.len()),
+            Self::SceneOrder(order) => order.len() * size_of::<Id>(),
+            Self::Modes {
+                modes, contents, ..
+            } => contents
+                .iter()
+                .flat_map(|(_, _, slots)| slots)
+                .map(slot_size)
+                .sum::<usize>()
+                .saturating_add(modes.len() * ENTRY_OVERHEAD),
+            Self::Players(players) => players.len() * ENTRY_OVERHEAD,
+            Self::Variables(variables) => variables.len() * ENTRY_OVERHEAD,
+            Self::Batch(changes) => changes.iter().map(Self::size).sum(),
+        }
+    }
+
+    /// 続けて入力したときにまとめる変更先（まとめない変更はNone）
+    fn target(&self) -> Option<Target> {
+        match self {
+            Self::Text { slot, judge, .. } => Some(Target::Text(*slot, *judge)),
+            Self::Title { scene, .. } => Some(Target::Title(*scene)),
+            Self::Slot { slot, .. } => Some(Target::Slot(slot.id)),
+            Self::Modes { .. } => Some(Target::Modes),
+            Self::Players(_) => Some(Target::Players),
+            Self::Variables(_) => Some(Target::Variables),
+            _ => None,
+        }
+    }
+}
+
+/// モード/判定一覧を差し替え、`contents`のスロット一覧を戻して正規化する
+fn apply_modes(
+    scenario: &mut Scenario,
+    system: GameSystem,
+    modes: Vec<Mode>,
+    contents: Vec<(Id, Id, Vec<TextSlot>)>,
+) -> Change {
+    // 判定が変わるモードのスロットは正規化で形が変わるので、戻せるよう取っておく
+    let mut affected: Vec<Id> = scenario
+        .modes
+        .iter()
+        .filter(|old| find_mode(&modes, old.id).is_none_or(|new| !same_judges(old, new)))
+        .map(|mode| mode.id)
+        .collect();
+    affected.extend(contents.iter().map(|(_, mode, _)| *mode));
+    affected.sort();
+    affected.dedup();
+    let saved = saved_contents(scenario, &affected);
+
+    let system = std::mem::replace(&mut scenario.system, system);
+    let modes = std::mem::replace(&mut scenario.modes, modes);
+    for (scene, mode, slots) in contents {
+        if let Some(target) = scenario.scenes.iter_mut().find(|target| target.id == scene) {
+            target.contents.insert(mode, slots);
+        }
+    }
+    scenario.normalize();
+    Change::Modes {
+        system,
+        modes,
+        contents: saved,
+    }
+}
+
+/// 各シーンの`mode_ids`のモードのスロット一覧
+fn saved_contents(scenario: &Scenario, mode_ids: &[Id]) -> Vec<(Id, Id, Vec<TextSlot>)> {
+    scenario
+        .scenes
+        .iter()
+        .flat_map(|scene| {
+            mode_ids.iter().filter_map(|mode| {
+                scene
+                    .contents
+                    .get(mode)
+                    .map(|slots| (scene.id, *mode, slots.clone()))
+            })
+        })
+        .collect()
+}
+
+/// 判定の組み合わせが同じか（並び順と名前は比べない）
+fn same_judges(a: &Mode, b: &Mode) -> bool {
+    a.judges.len() == b.judges.len() && a.judges.iter().all(|judge| b.judge(judge.id).is_some())
+}
+
+fn slot_size(slot: &TextSlot) -> usize {
+    slot.texts
+        .values()
+        .map(String::len)
+        .sum::<usize>()
+        .saturating_add(slot.label.as_ref().map_or(0, String::len))
+        .saturating_add(ENTRY_OVERHEAD)
+}
+
+/// 1つの操作（元に戻す/やり直しの単位）
+struct Entry {
+    /// メニューに表示する操作名
+    label: String,
+    /// 適用すると操作の前（やり直し側では後）に戻す変更
+    change: Change,
+    /// `change`を適用した後の状態の番号
+    revision: u64,
+    /// 保持に使う大きさの目安
+    size: usize,
+}
+
+impl Entry {
+    fn new(label: String, change: Change, revision: u64) -> Self {
+        let size = change.size().saturating_add(ENTRY_OVERHEAD);
+        Self {
+            label,
+            change,
+            revision,
+            size,
+        }
+    }
+}
+
+/// シナリオの編集履歴（元に戻す/やり直し）
+///
+/// 編集する側が変更の前の値を`push`し、フレームの終わりにまとめて1つの操作として記録する。
+/// 操作ごとに変わった箇所だけを持ち、保持する大きさの合計が上限を超えたら古い操作から捨てる。
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Entry>,
+    redo: Vec<Entry>,
+    /// `undo`の大きさの合計
+    size: usize,
+    /// このフレームで記録した（操作名, 元に戻す変更）
+    pending: Vec<(String, Change)>,
+    /// 最後に記録した変更先、操作名と時刻（続けて入力した分をまとめる）
+    burst: Option<(Target, String, f64)>,
+    /// 今のシナリオの状態の番号（記録するたびに新しい番号、元に戻すと記録時の番号）
+    revision: u64,
+}
+
+/// 新しい状態の番号（シナリオを開き直しても前のシナリオの番号と重ならないよう、全履歴で通し番号にする）
+fn next_revision() -> u64 {
+    static LAST_REVISION: AtomicU64 = AtomicU64::new(0);
+    LAST_REVISION.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            revision: next_revision(),
+            ..Default::default()
+        }
+    }
+
+    /// 今のシナリオの状態の番号（保存した時点と比べて未保存の変更を判定する）
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// 元に戻す操作名
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo.last().map(|entry| entry.label.as_str())
+    }
+
+    /// やり直す操作名
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo.last().map(|entry| entry.label.as_str())
+    }
+
+    /// 行った変更を記録する（`undo`は変更の前の値に戻す変更）
+    pub fn push(&mut self, label: impl Into<String>, undo: Change) {
+        self.pending.push((label.into(), undo));
+    }
+
+    /// 変更を適用して記録する
+    pub fn execute(&mut self, scenario: &mut Scenario, label: impl Into<String>, change: Change) {
+        let undo = change.apply(scenario);
+        self.push(label, undo);
+    }
+
+    /// このフレームで記録した変更を1つの操作にする（毎フレームの終わりに呼ぶ）
+    ///
+    /// 同じ箇所への続けての入力は、最初の入力の前に戻す1つの操作にまとめる。
+    pub fn update(&mut self, ctx: &egui::Context) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let pending = std::mem::take(&mut self.pending);
+        let now = ctx.input(|i| i.time);
+        let label = pending
+            .first()
+            .map(|(label, _)| label.clone())
+            .unwrap_or_default();
+        let target = match pending.as_slice() {
+            [(_, change)] => change.target(),
+            _ => None,
+        };
+        let continues_burst = !self.undo.is_empty()
+            && matches!(
+                (target, &self.burst),
+                (Some(target), Some((last_target, last_label, last_time)))
+                    if target == *last_target
+                        && label == *last_label
+                        && now - last_time < BURST_SECONDS
+            );
+
+        // まとめる場合は最初の入力の前の値を持つ操作をそのまま使う
+        if !continues_burst {
+            let mut changes: Vec<Change> = pending.into_iter().map(|(_, change)| change).collect();
+            let change = if changes.len() == 1 {
+                changes.pop().unwrap_or_else(Change::none)
+            } else {
+                Change::Batch(changes)
+            };
+            self.push_undo(Entry::new(label.clone(), change, self.revision));
+        }
+        self.burst = target.map(|target| (target, label, now));
+        self.redo.clear();
+        // まとめた入力の途中で保存した場合も未保存になるよう、変更のたびに新しい番号にする
+        self.revision = next_revision();
+    }
+
+    /// 直前の操作を元に戻す
+    /// 返り値: 元に戻した操作名
+    pub fn undo(&mut self, scenario: &mut Scenario) -> Option<String> {
+        let entry = self.undo.pop()?;
+        self.size = self.size.saturating_sub(entry.size);
+        let label = entry.label.clone();
+        let redo = self.restore(scenario, entry);
+        self.redo.push(redo);
+        Some(label)
+    }
+
+    /// 元に戻した操作をやり直す
+    /// 返り値: やり直した操作名
+    pub fn redo(&mut self, scenario: &mut Scenario) -> Option<String> {
+        let entry = self.redo.pop()?;
+        let label = entry.label.clone();
+        let undo = self.restore(scenario, entry);
+        self.push_undo(undo);
+        Some(label)
+    }
+
+    /// 操作をシナリオに適用し、反対側の履歴に積む操作を返す
+    fn restore(&mut self, scenario: &mut Scenario, entry: Entry) -> Entry {
+        let change = entry.change.apply(scenario);
+        self.burst = None;
+        self.pending.clear();
+        Entry::new(
+            entry.label,
+            change,
+            std::mem::replace(&mut self.revision, entry.revision),
+        )
+    }
+
+    /// 元に戻す履歴に積み、大きさの上限を超えた分を古い操作から捨てる（直前の操作は残す）
+    fn push_undo(&mut self, entry: Entry) {
+        self.size = self.size.saturating_add(entry.size);
+        self.undo.push(entry);
+        while self.size > MAX_HISTORY_BYTES && self.undo.len() > 1 {
+            let oldest = self.undo.remove(0);
+            self.size = self.size.saturating_sub(oldest.size);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1フレーム分の記録（`edit`でシナリオを編集して変更を記録する）
+    fn frame(
+        history: &mut History,
+        scenario: &mut Scenario,
+        time: f64,
+        edit: impl FnOnce(&mut History, &mut Scenario),
+    ) {
+        let ctx = egui::Context::default();
+        let raw = egui::RawInput {
+            time: Some(time),
+            ..Default::default()
+        };
+        edit(history, scenario);
+        let _output: egui::FullOutput = ctx.run(raw, |ctx| history.update(ctx));
+    }
+
+    /// 先頭のシーンのタイトルを変更して記録する
+    fn rename(history: &mut History, scenario: &mut Scenario, title: &str) {
+        let scene = scenario.scenes.first_mut().expect("サンプルのシーンがある");
+        let before = std::mem::replace(&mut scene.title, String::from(title));
+        history.push(
+            title,
+            Change::Title {
+                scene: scene.id,
+                title: before,
+            },
+        );
+    }
+
+    fn title(scenario: &Scenario) -> &str {
+        scenario
+            .scenes
+            .first()
+            .map_or("", |scene| scene.title.as_str())
+    }
+
+    /// 先頭のシーンの最後のモードの最初のスロット（シーンID, モードID, スロット）
+    fn first_slot(scenario: &mut Scenario) -> (Id, Id, &mut TextSlot) {
+        let mode = scenario
+            .modes
+            .last()
+            .map(|mode| mode.id)
+            .expect("モードがある");
+        let scene = scenario.scenes.first_mut().expect("サンプルのシーンがある");
+        let scene_id = scene.id;
+        let slot = scene
+            .slots_mut(mode)
+            .first_mut()
+            .expect("サンプルのスロットがある");
+        (scene_id, mode, slot)
+    }
+
+    /// 先頭のシーンの最初のスロットの判定に入力して記録する
+    fn type_text(history: &mut History, scenario: &mut Scenario, text: &str) {
+        let (scene, _, slot) = first_slot(scenario);
+        let judge = slot.selected_judge;
+        let before = std::mem::replace(slot.text_mut(judge), String::from(text));
+        history.push(
+            "テキストの編集",
+            Change::Text {
+                scene,
+                slot: slot.id,
+                judge,
+                text: before,
+            },
+        );
+    }
+
+    fn typed(scenario: &mut Scenario) -> String {
+        let (_, _, slot) = first_slot(scenario);
+        String::from(slot.text(slot.selected_judge))
+    }
+
+    #[test]
+    fn undo_and_redo_in_order() {
+        let mut scenario = Scenario::default();
+        let mut history = History::new();
+        for (time, name) in [(0.0, "A"), (1.0, "B"), (2.0, "C")] {
+            frame(&mut history, &mut scenario, time, |history, scenario| {
+                rename(history, scenario, name);
+            });
+        }
+
+        assert_eq!(
+            history.undo(&mut scenario).as_deref(),
+            Some("C"),
+            "最後の操作から戻す"
+        );
+        assert_eq!(title(&scenario), "B", "Cの前に戻る");
+        assert_eq!(history.undo(&mut scenario).as_deref(), Some("B"), "次はB");
+        assert_eq!(title(&scenario), "A", "Bの前に戻る");
+        assert_eq!(
+            history.redo_label(),
+            Some("B"),
+            "やり直しは直前に戻した操作から"
+        );
+
+        assert_eq!(
+            history.redo(&mut scenario).as_deref(),
+            Some("B"),
+            "Bをやり直す"
+        );
+        assert_eq!(title(&scenario), "B", "Bの後に進む");
+        assert_eq!(
+            history.redo(&mut scenario).as_deref(),
+            Some("C"),
+            "Cをやり直す"
+        );
+        assert_eq!(title(&scenario), "C", "Cの後に進む");
+        assert!(
+            history.redo(&mut scenario).is_none(),
+            "やり直す操作はもうない"
+        );
+        assert_eq!(
+            history.undo_label(),
+            Some("C"),
+            "やり直した操作は元に戻せる"
+        );
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut scenario = Scenario::default();
+        let mut history = History::new();
+        frame(&mut history, &mut scenario, 0.0, |history, scenario| {
+            rename(history, scenario, "A");
+        });
+        frame(&mut history, &mut scenario, 1.0, |history, scenario| {
+            rename(history, scenario, "B");
+        });
+
+        history.undo(&mut scenario);
+        assert_eq!(history.redo_label(), Some("B"), "戻した操作はやり直せる");
+
+        frame(&mut history, &mut scenario, 2.0, |history, scenario| {
+            rename(history, scenario, "X");
+        });
+        assert!(
+            history.redo_label().is_none(),
+            "新しい編集でやり直しは消える"
+        );
+        assert_eq!(
+            history.undo(&mut scenario).as_deref(),
+            Some("X"),
+            "新しい編集を戻す"
+        );
+        assert_eq!(title(&scenario), "A", "Bではなく新しい編集の前に戻る");
+        assert_eq!(
+            history.undo(&mut scenario).as_deref(),
+            Some("A"),
+            "その前はA"
+        );
+        assert!(history.undo_label().is_none(), "最初の状態まで戻った");
+    }
+
+    #[test]
+    fn typing_bursts_become_one_operation() {
+        let mut scenario = Scenario::default();
+        let original = typed(&mut scenario);
+        let mut history = History::new();
+        for (time, text) in [(0.0, "a"), (0.5, "ab"), (0.9, "abc")] {
+            frame(&mut history, &mut scenario, time, |history, scenario| {
+                type_text(history, scenario, text);
+            });
+        }
+        frame(&mut history, &mut scenario, 5.0, |history, scenario| {
+            type_text(history, scenario, "abcd");
+        });
+
+        history.undo(&mut scenario);
+        assert_eq!(typed(&mut scenario), "abc", "間が空いた入力は別の操作");
+        history.undo(&mut scenario);
+        assert_eq!(
+            typed(&mut scenario),
+            original,
+            "続けた入力は最初の入力の前に戻る"
+        );
+        assert!(history.undo_label().is_none(), "操作は2つだけ");
+    }
+
+    #[test]
+    fn undo_keeps_current_selection() {
+        let mut scenario = Scenario::default();
+        let mut history = History::new();
+        frame(&mut history, &mut scenario, 0.0, |history, scenario| {
+            let (scene, _, slot) = first_slot(scenario);
+            let before = slot.clone();
+            slot.hidden = !slot.hidden;
+            history.push(
+                "スロットの変更",
+                Change::Slot {
+                    scene,
+                    slot: Box::new(before),
+                },
+            );
+        });
+
+        let (_, _, slot) = first_slot(&mut scenario);
+        let judge = slot.texts.keys().last().copied().expect("判定がある");
+        slot.selected_judge = judge;
+        let hidden = slot.hidden;
+        history.undo(&mut scenario);
+        let (_, _, slot) = first_slot(&mut scenario);
+        assert_ne!(slot.hidden, hidden, "スロットの内容は元に戻る");
+        assert_eq!(slot.selected_judge, judge, "選択中の判定はそのまま");
+    }
+
+    #[test]
+    fn frames_without_changes_are_not_recorded() {
+        let mut scenario = Scenario::default();
+        let mut history = History::new();
+        let revision = history.revision();
+        frame(&mut history, &mut scenario, 0.0, |_, _| {});
+        assert!(
+            history.undo_label().is_none(),
+            "変更のないフレームは記録しない"
+        );
+        assert_eq!(history.revision(), revision, "番号も変わらない");
+    }
+
+    #[test]
+    fn one_frame_of_changes_is_one_operation() {
+        let mut scenario = Scenario::default();
+        let mut history = History::new();
+        frame(&mut history, &mut scenario, 0.0, |history, scenario| {
+            rename(history, scenario, "A");
+            type_text(history, scenario, "本文");
+        });
+        assert_eq!(history.undo_label(), Some("A"), "最初の操作名を使う");
+        history.undo(&mut scenario);
+        assert_ne!(title(&scenario), "A", "タイトルも戻る");
+        assert_ne!(typed(&mut scenario), "本文", "テキストも戻る");
+        assert!(history.undo_label().is_none(), "まとめて1つの操作");
+    }
+
+    #[test]
+    fn slot_operations_round_trip() {
+        let mut scenario = Scenario::default();
+        let (scene, mode, slot) = first_slot(&mut scenario);
+        let slot_id = slot.id;
+        let extra = TextSlot::new_empty(&[]);
+        let extra_id = extra.id;
+        let mut history = History::new();
+        frame(&mut history, &mut scenario, 0.0, |history, scenario| {
+            history.execute(
+                scenario,
+                "スロットの追加",
+                Change::InsertSlot {
+                    scene,
+                    mode,
+                    index: 0,
+                    slot: Box::new(extra),
+                },
+            );
+        });
+        frame(&mut history, &mut scenario, 1.0, |history, scenario| {
+            history.execute(
+                scenario,
+                "スロットの並べ替え",
+                Change::MoveSlot {
+                    scene,
+                    mode,
+                    from: 0,
+                    to: 9,
+                },
+            );
+        });
+        frame(&mut history, &mut scenario, 2.0, |history, scenario| {
+            history.execute(
+                scenario,
+                "スロットの削除",
+                Change::RemoveSlot {
+                    scene,
+                    slot: slot_id,
+                },
+            );
+        });
+
+        let order = |scenario: &Scenario| -> Vec<Id> {
+            scenario
+                .scenes
+                .first()
+                .map(|scene| scene.slots(mode).iter().map(|slot| slot.id).collect())
+                .unwrap_or_default()
+        };
+        assert_eq!(order(&scenario), vec![extra_id], "元のスロットを削除した");
+        history.undo(&mut scenario);
+        assert_eq!(
+            order(&scenario),
+            vec![slot_id, extra_id],
+            "削除したスロットは元の位置に戻る"
+        );
+        history.undo(&mut scenario);
+        assert_eq!(
+            order(&scenario),
+            vec![extra_id, slot_id],
+            "並べ替えは元の位置に戻る（末尾を超えた位置は末尾）"
+        );
+        history.undo(&mut scenario);
+        assert_eq!(order(&scenario), vec![slot_id], "追加したスロットは消える");
+        history.redo(&mut scenario);
+        history.redo(&mut scenario);
+        history.redo(&mut scenario);
+        assert_eq!(order(&scenario), vec![extra_id], "やり直すと同じ結果になる");
+    }
+
+    #[test]
+    fn scene_operations_round_trip() {
+        let mut scenario = Scenario::default();
+        let titles = |scenario: &Scenario| -> String {
+            scenario
+                .scenes
+                .iter()
+                .map(|scene| scene.title.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let original = titles(&scenario);
+        let order: Vec<Id> = scenario.scenes.iter().rev().map(|scene| scene.id).collect();
+        let second = scenario
+            .scenes
+            .get(1)
+            .map(|scene| scene.id)
+            .expect("2つ目のシーン");
+        let mut history = History::new();
+        frame(&mut history, &mut scenario, 0.0, |history, scenario| {
+            history.execute(scenario, "シーンの並べ替え", Change::SceneOrder(order));
+        });
+        assert_eq!(
+            titles(&scenario),
+            "シーン3,シーン2,シーン1",
+            "逆順に並べ替えた"
+        );
+        frame(&mut history, &mut scenario, 1.0, |history, scenario| {
+            history.execute(
+                scenario,
+                "シーンの削除",
+                Change::RemoveScene { scene: second },
+            );
+        });
+        assert_eq!(
+            titles(&scenario),
+            "シーン3,シーン1",
+            "2つ目のシーンを削除した"
+        );
+
+        history.undo(&mut scenario);
+        assert_eq!(
+            titles(&scenario),
+            "シーン3,シーン2,シーン1",
+            "削除したシーンが元の位置に戻る"
+        );
+        history.undo(&mut scenario);
+        assert_eq!(titles(&scenario), original, "元の並び順に戻る");
+    }
+
+    #[test]
+    fn removing_a_mode_can_be_undone() {
+        let mut scenario = Scenario::default();
+        let mode = scenario
+            .modes
+            .last()
+            .map(|mode| mode.id)
+            .expect("モードがある");
+        let before = typed(&mut scenario);
+        let mut history = History::new();
+        frame(&mut history, &mut scenario, 0.0, |history, scenario| {
+            let undo = Change::modes(scenario, &[mode]);
+            scenario.remove_mode(mode);
+            history.push("モードの削除", undo);
+        });
+        assert!(scenario.mode(mode).is_none(), "モードを削除した");
+        assert!(
+            scenario
+                .scenes
+                .iter()
+                .all(|scene| scene.slots(mode).is_empty()),
+            "スロットも消える"
+        );
+
+        history.undo(&mut scenario);
+        assert!(scenario.mode(mode).is_some(), "モードが戻る");
+        assert_eq!(typed(&mut scenario), before, "スロットのテキストも戻る");
+        history.redo(&mut scenario);
+        assert!(scenario.mode(mode).is_none(), "やり直すと再び削除する");
+        history.undo(&mut scenario);
+        assert_eq!(typed(&mut scenario), before, "2回目も元に戻せる");
+    }
+
+    #[test]
+    fn history_is_bounded_by_size() {
+        let mut scenario = Scenario::default();
+        let mut history = History::new();
+        let large = "あ".repeat(MAX_HISTORY_BYTES / 9);
+        for (time, text) in [(0.0, "1"), (10.0, "2"), (20.0, "3"), (30.0, "4")] {
+            frame(&mut history, &mut scenario, time, |history, scenario| {
+                let scene = scenario.scenes.first_mut().expect("サンプルのシーンがある");
+                let before = std::mem::replace(&mut scene.title, format!("{text}{large}"));
+                history.push(
+                    text,
+                    Change::Title {
+                        scene: scene.id,
+                        title: before,
+                    },
+                );
+            });
+        }
+        assert!(history.size <= MAX_HISTORY_BYTES, "上限を超えない");
+        let mut undone = Vec::new();
+        while let Some(label) = history.undo(&mut scenario) {
+            undone.push(label);
+        }
+        assert_eq!(undone, ["4", "3"], "古い操作から捨てる");
+    }
+
+    #[test]
+    fn revision_follows_undo_and_redo() {
+        let mut scenario = Scenario::default();
+        let mut history = History::new();
+        let saved = history.revision();
+        frame(&mut history, &mut scenario, 0.0, |history, scenario| {
+            rename(history, scenario, "A");
+        });
+        let edited = history.revision();
+        assert_ne!(edited, saved, "編集すると番号が変わる");
+
+        history.undo(&mut scenario);
+        assert_eq!(history.revision(), saved, "元に戻すと保存時の番号に戻る");
+        history.redo(&mut scenario);
+        assert_eq!(history.revision(), edited, "やり直すと編集後の番号に戻る");
+
+        history.undo(&mut scenario);
+        frame(&mut history, &mut scenario, 1.0, |history, scenario| {
+            rename(history, scenario, "B");
+        });
+        assert_ne!(history.revision(), edited, "別の編集には新しい番号を付ける");
+        assert_ne!(history.revision(), saved, "別の編集は未保存になる");
+    }
+}
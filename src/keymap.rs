@@ -0,0 +1,448 @@
+use crate::config::{self, ConfigError};
+use crate::history::{REDO_SHORTCUT, UNDO_SHORTCUT};
+use crate::panels::queue::{QUEUE_BACK_SHORTCUT, QUEUE_NEXT_SHORTCUT, QUEUE_SKIP_SHORTCUT};
+use crate::panels::search::SEARCH_SHORTCUT;
+use egui::{Key, KeyboardShortcut, Modifiers};
+use std::path::{Path, PathBuf};
+
+/// キーマップファイルの名前（設定ファイルと同じディレクトリに置く）
+const KEYMAP_FILE_NAME: &str = "keymap.toml";
+
+/// 変更できない固定のショートカットキー（操作の名前, キー）
+///
+/// 修飾キーの多いキーを先に並べる（判定する順と同じ）。
+pub const FIXED_SHORTCUTS: [(&str, KeyboardShortcut); 6] = [
+    ("やり直し", REDO_SHORTCUT),
+    ("元に戻す", UNDO_SHORTCUT),
+    ("検索", SEARCH_SHORTCUT),
+    ("コピーキュー: スキップ", QUEUE_SKIP_SHORTCUT),
+    ("コピーキュー: コピーして次へ", QUEUE_NEXT_SHORTCUT),
+    ("コピーキュー: 戻る", QUEUE_BACK_SHORTCUT),
+];
+
+/// 判定を選ぶ操作に使う数字キー（1〜9）
+const JUDGE_KEYS: [Key; 9] = [
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+];
+
+/// ショートカットキーで実行できる操作
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    NextScene,
+    PrevScene,
+    NextMode,
+    PrevMode,
+    /// フォーカス中のスロットのn番目（1〜9）の判定を選ぶ
+    SelectJudge(usize),
+    /// フォーカス中のスロットをコピー
+    CopySlot,
+    AddSlot,
+    AddScene,
+    /// 編集/コピーの切り替え
+    ToggleAppMode,
+}
+
+impl Action {
+    pub const ALL: [Self; 17] = [
+        Self::NextScene,
+        Self::PrevScene,
+        Self::NextMode,
+        Self::PrevMode,
+        Self::SelectJudge(1),
+        Self::SelectJudge(2),
+        Self::SelectJudge(3),
+        Self::SelectJudge(4),
+        Self::SelectJudge(5),
+        Self::SelectJudge(6),
+        Self::SelectJudge(7),
+        Self::SelectJudge(8),
+        Self::SelectJudge(9),
+        Self::CopySlot,
+        Self::AddSlot,
+        Self::AddScene,
+        Self::ToggleAppMode,
+    ];
+
+    pub fn label(self) -> String {
+        match self {
+            Self::NextScene => String::from("次のシーン"),
+            Self::PrevScene => String::from("前のシーン"),
+            Self::NextMode => String::from("次のモード"),
+            Self::PrevMode => String::from("前のモード"),
+            Self::SelectJudge(number) => format!("{number}番目の判定を選択"),
+            Self::CopySlot => String::from("スロットをコピー"),
+            Self::AddSlot => String::from("スロットを追加"),
+            Self::AddScene => String::from("シーンを追加"),
+            Self::ToggleAppMode => String::from("編集/コピーの切り替え"),
+        }
+    }
+
+    /// キーマップファイルでの名前
+    fn name(self) -> String {
+        match self {
+            Self::NextScene => String::from("next_scene"),
+            Self::PrevScene => String::from("prev_scene"),
+            Self::NextMode => String::from("next_mode"),
+            Self::PrevMode => String::from("prev_mode"),
+            Self::SelectJudge(number) => format!("judge_{number}"),
+            Self::CopySlot => String::from("copy_slot"),
+            Self::AddSlot => String::from("add_slot"),
+            Self::AddScene => String::from("add_scene"),
+            Self::ToggleAppMode => String::from("toggle_app_mode"),
+        }
+    }
+
+    /// 既定のショートカットキー
+    ///
+    /// 入力中の文字と重ならないよう、修飾キーとの組み合わせにする。
+    pub fn default_shortcut(self) -> Option<KeyboardShortcut> {
+        let ctrl_shift = Modifiers::COMMAND | Modifiers::SHIFT;
+        let (modifiers, key) = match self {
+            Self::NextScene => (Modifiers::COMMAND, Key::PageDown),
+            Self::PrevScene => (Modifiers::COMMAND, Key::PageUp),
+            Self::NextMode => (ctrl_shift, Key::PageDown),
+            Self::PrevMode => (ctrl_shift, Key::PageUp),
+            Self::SelectJudge(number) => (Modifiers::ALT, *JUDGE_KEYS.get(number.checked_sub(1)?)?),
+            Self::CopySlot => (ctrl_shift, Key::C),
+            Self::AddSlot => (Modifiers::ALT, Key::N),
+            Self::AddScene => (Modifiers::ALT | Modifiers::SHIFT, Key::N),
+            Self::ToggleAppMode => (Modifiers::COMMAND, Key::E),
+        };
+        Some(KeyboardShortcut::new(modifiers, key))
+    }
+}
+
+/// 操作ごとのショートカットキーの割り当て
+#[derive(Clone, PartialEq, Eq)]
+pub struct Keymap {
+    /// `Action::ALL`の順（Noneは割り当てなし）
+    bindings: Vec<(Action, Option<KeyboardShortcut>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_shortcut()))
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// キーマップファイルの場所（設定ディレクトリがない環境ではNone）
+    pub fn path() -> Option<PathBuf> {
+        config::config_file(KEYMAP_FILE_NAME)
+    }
+
+    /// 操作のショートカットキー
+    pub fn shortcut(&self, action: Action) -> Option<KeyboardShortcut> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .and_then(|(_, shortcut)| *shortcut)
+    }
+
+    /// ショートカットキーを割り当てる（同じキーを使っていた操作からは外す）
+    /// 返り値: 割り当てを外した操作
+    pub fn set(&mut self, action: Action, shortcut: Option<KeyboardShortcut>) -> Option<Action> {
+        let mut displaced = None;
+        for (bound, bound_shortcut) in &mut self.bindings {
+            if *bound == action {
+                *bound_shortcut = shortcut;
+            } else if shortcut.is_some() && *bound_shortcut == shortcut {
+                *bound_shortcut = None;
+                displaced = Some(*bound);
+            }
+        }
+        displaced
+    }
+
+    /// 押されたショートカットキーの操作（キー入力は消費する）
+    ///
+    /// `Ctrl+Shift+C`が`Ctrl+C`にも一致しないよう、修飾キーの多いものから判定する。
+    pub fn pressed(&self, ctx: &egui::Context) -> Option<Action> {
+        let mut bindings: Vec<(Action, KeyboardShortcut)> = self
+            .bindings
+            .iter()
+            .filter_map(|(action, shortcut)| shortcut.map(|shortcut| (*action, shortcut)))
+            .collect();
+        bindings.sort_by_key(|(_, shortcut)| std::cmp::Reverse(modifier_count(shortcut.modifiers)));
+        ctx.input_mut(|i| {
+            bindings
+                .iter()
+                .find(|(_, shortcut)| i.consume_shortcut(shortcut))
+                .map(|(action, _)| *action)
+        })
+    }
+
+    /// キーマップファイルを読み込む（書かれていない操作は既定のキーのまま）
+    ///
+    /// 操作名/キーが不正な行は読み飛ばして既定のキーのままにする。
+    /// 返り値: キーマップと、読み飛ばした行の問題の一覧
+    ///
+    /// # Errors
+    /// ファイルが読めない、またはTOMLとして不正な場合
+    pub fn load(path: &Path) -> Result<(Self, Vec<String>), ConfigError> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text).map_err(ConfigError::from)
+    }
+
+    /// キーマップファイルの内容を読む（`load`を参照）
+    fn parse(text: &str) -> Result<(Self, Vec<String>), toml::de::Error> {
+        let table: toml::Table = toml::from_str(text)?;
+        let mut keymap = Self::default();
+        let mut problems = Vec::new();
+        for (name, value) in &table {
+            let Some(action) = Action::ALL
+                .into_iter()
+                .find(|action| action.name() == *name)
+            else {
+                problems.push(format!("不明な操作です: {name}"));
+                continue;
+            };
+            let shortcut = match value.as_str() {
+                Some("") => None,
+                Some(text) => {
+                    let Some(shortcut) = parse_shortcut(text) else {
+                        problems.push(format!("{name}のキーが不正です: {text}"));
+                        continue;
+                    };
+                    if let Some(fixed) = fixed_action(shortcut) {
+                        problems.push(format!("{name}のキーは「{fixed}」に使われています: {text}"));
+                        continue;
+                    }
+                    Some(shortcut)
+                }
+                None => {
+                    problems.push(format!("{name}のキーは文字列で指定してください"));
+                    continue;
+                }
+            };
+            keymap.set(action, shortcut);
+        }
+        Ok((keymap, problems))
+    }
+
+    /// キーマップファイルに保存する（全操作を`Action::ALL`の順に書く）
+    ///
+    /// # Errors
+    /// ディレクトリまたはファイルを作れない場合
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut text = String::from(
+            "# ショートカットキー（\"Ctrl+Shift+C\"の形、空文字で割り当てなし）\n\
+             # CtrlはmacOSではCmdになります。\n",
+        );
+        for (action, shortcut) in &self.bindings {
+            let key = shortcut.map(format_shortcut).unwrap_or_default();
+            text.push_str(&format!(
+                "{} = {}\n",
+                action.name(),
+                toml::Value::String(key)
+            ));
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, text)
+    }
+}
+
+fn modifier_count(modifiers: Modifiers) -> usize {
+    [
+        modifiers.command || modifiers.ctrl,
+        modifiers.alt,
+        modifiers.shift,
+    ]
+    .into_iter()
+    .filter(|pressed| *pressed)
+    .count()
+}
+
+/// 固定のショートカットキーと重なるキーなら、その操作の名前を返す
+///
+/// 固定のキーは先に判定され、Shiftや余分な修飾キーを押していても一致する（`consume_shortcut`と同じ）ため、
+/// そうしたキーも重なるとみなす。
+pub fn fixed_action(shortcut: KeyboardShortcut) -> Option<&'static str> {
+    FIXED_SHORTCUTS
+        .iter()
+        .find(|(_, fixed)| {
+            fixed.logical_key == shortcut.logical_key
+                && shortcut.modifiers.matches_logically(fixed.modifiers)
+        })
+        .map(|(label, _)| *label)
+}
+
+/// 押されたキーからショートカットを作る（CtrlとCmdは区別しない）
+pub fn shortcut_from(modifiers: Modifiers, key: Key) -> KeyboardShortcut {
+    let mut normalized = Modifiers::NONE;
+    if modifiers.command || modifiers.ctrl {
+        normalized |= Modifiers::COMMAND;
+    }
+    if modifiers.alt {
+        normalized |= Modifiers::ALT;
+    }
+    if modifiers.shift {
+        normalized |= Modifiers::SHIFT;
+    }
+    KeyboardShortcut::new(normalized, key)
+}
+
+/// キーマップファイルに書く形（`Ctrl+Shift+C`）
+fn format_shortcut(shortcut: KeyboardShortcut) -> String {
+    let modifiers = shortcut.modifiers;
+    let mut parts = Vec::new();
+    if modifiers.command || modifiers.ctrl {
+        parts.push("Ctrl");
+    }
+    if modifiers.alt {
+        parts.push("Alt");
+    }
+    if modifiers.shift {
+        parts.push("Shift");
+    }
+    parts.push(shortcut.logical_key.name());
+    parts.join("+")
+}
+
+/// キーマップファイルの`Ctrl+Shift+C`の形を読む（修飾キーは大文字/小文字を区別しない）
+fn parse_shortcut(text: &str) -> Option<KeyboardShortcut> {
+    let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+    let key = Key::from_name(parts.pop()?)?;
+    let mut modifiers = Modifiers::NONE;
+    for part in parts {
+        match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "cmd" | "command" => modifiers |= Modifiers::COMMAND,
+            "alt" | "option" => modifiers |= Modifiers::ALT,
+            "shift" => modifiers |= Modifiers::SHIFT,
+            _ => return None,
+        }
+    }
+    Some(shortcut_from(modifiers, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_entries_keep_default_keys() {
+        let (keymap, problems) = Keymap::parse(
+            "next_scene = \"Alt+Shift+N\"\nprev_scene = \"Ctrl+Nope\"\nunknown = \"Ctrl+A\"\nadd_slot = 1\ncopy_slot = \"\"\n",
+        )
+        .expect("TOMLとしては正しい");
+        assert_eq!(problems.len(), 3, "不正な行だけ報告する: {problems:?}");
+        assert_eq!(
+            keymap.shortcut(Action::NextScene),
+            Some(KeyboardShortcut::new(
+                Modifiers::ALT | Modifiers::SHIFT,
+                Key::N
+            )),
+            "正しい行は読み込む"
+        );
+        assert_eq!(
+            keymap.shortcut(Action::AddScene),
+            None,
+            "同じキーだった操作からは外す"
+        );
+        assert_eq!(
+            keymap.shortcut(Action::PrevScene),
+            Action::PrevScene.default_shortcut(),
+            "キーが不正な操作は既定のキー"
+        );
+        assert_eq!(
+            keymap.shortcut(Action::AddSlot),
+            Action::AddSlot.default_shortcut(),
+            "文字列でない操作は既定のキー"
+        );
+        assert_eq!(
+            keymap.shortcut(Action::CopySlot),
+            None,
+            "空文字は割り当てなし"
+        );
+    }
+
+    #[test]
+    fn fixed_shortcuts_are_rejected() {
+        let ctrl_z = shortcut_from(Modifiers::CTRL, Key::Z);
+        assert_eq!(fixed_action(ctrl_z), Some("元に戻す"), "Ctrl+Z");
+        assert_eq!(
+            fixed_action(shortcut_from(Modifiers::CTRL | Modifiers::SHIFT, Key::Z)),
+            Some("やり直し"),
+            "Ctrl+Shift+Zはやり直し"
+        );
+        assert_eq!(
+            fixed_action(KeyboardShortcut::new(Modifiers::SHIFT, Key::F9)),
+            Some("コピーキュー: スキップ"),
+            "Shift+F9はスキップ"
+        );
+        assert_eq!(
+            fixed_action(KeyboardShortcut::new(Modifiers::ALT, Key::F8)),
+            Some("コピーキュー: 戻る"),
+            "Alt+F8もF8に一致する"
+        );
+        assert_eq!(
+            fixed_action(shortcut_from(Modifiers::CTRL | Modifiers::SHIFT, Key::F)),
+            Some("検索"),
+            "Ctrl+Shift+FはCtrl+Fに一致する"
+        );
+        assert_eq!(
+            fixed_action(KeyboardShortcut::new(Modifiers::NONE, Key::Z)),
+            None,
+            "修飾キーの足りないキーは使える"
+        );
+        for action in Action::ALL {
+            let shortcut = action.default_shortcut().expect("既定のキーがある");
+            assert_eq!(
+                fixed_action(shortcut),
+                None,
+                "既定のキーは固定のキーと重ならない: {}",
+                action.label()
+            );
+        }
+
+        let (keymap, problems) =
+            Keymap::parse("next_scene = \"F9\"\n").expect("TOMLとしては正しい");
+        assert_eq!(problems.len(), 1, "固定のキーは報告する: {problems:?}");
+        assert_eq!(
+            keymap.shortcut(Action::NextScene),
+            Action::NextScene.default_shortcut(),
+            "固定のキーを書いた操作は既定のキー"
+        );
+    }
+
+    #[test]
+    fn invalid_toml_is_an_error() {
+        assert!(Keymap::parse("next_scene = ").is_err(), "TOMLとして不正");
+    }
+
+    #[test]
+    fn shortcuts_round_trip_through_text() {
+        for action in Action::ALL {
+            let shortcut = action.default_shortcut().expect("既定のキーがある");
+            assert_eq!(
+                parse_shortcut(&format_shortcut(shortcut)),
+                Some(shortcut),
+                "{}",
+                action.label()
+            );
+        }
+        assert_eq!(
+            parse_shortcut("cmd + shift + c"),
+            Some(KeyboardShortcut::new(
+                Modifiers::COMMAND | Modifiers::SHIFT,
+                Key::C
+            )),
+            "修飾キーの大文字/小文字と空白は問わない"
+        );
+    }
+}
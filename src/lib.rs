@@ -1,9 +1,21 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
+mod config;
 mod constants;
+pub mod export;
+mod history;
+mod keymap;
+mod message;
+mod migrate;
 mod panels;
+mod parser;
+mod placeholder;
+mod scenario;
 mod scene;
+mod search;
+mod system;
 mod widgets;
 pub use app::MemoApp;
+pub use scenario::Scenario;
 pub use scene::{Mode, Scene};
@@ -1,15 +1,7 @@
 #![warn(clippy::all, rust_2018_idioms)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 //
-mod app;
-mod constants;
-mod panels;
-mod scene;
-mod widgets;
-
-use app::MemoApp;
 use eframe::egui;
-use std::sync::Arc;
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result {
@@ -31,7 +23,7 @@ fn main() -> eframe::Result {
         native_options,
         Box::new(|cc| {
             setup_custom_fonts(&cc.egui_ctx);
-            Ok(Box::new(eframe_template::MemoApp::default()))
+            Ok(Box::new(eframe_template::MemoApp::new(cc)))
         }),
     )
 }
@@ -62,7 +54,7 @@ fn main() {
             .start(
                 canvas,
                 web_options,
-                Box::new(|cc| Ok(Box::new(eframe_template::TemplateApp::new(cc)))),
+                Box::new(|cc| Ok(Box::new(eframe_template::MemoApp::new(cc)))),
             )
             .await;
 
@@ -0,0 +1,538 @@
+use crate::parser::dice;
+use crate::placeholder::{self, Bindings, Resolved, Variable};
+use crate::scene::{Id, Mode, TextSlot};
+use std::borrow::Cow;
+
+/// 文の終わりとみなす文字
+const SENTENCE_ENDS: [char; 5] = ['。', '！', '？', '!', '?'];
+
+/// 文の終わりの直後に続けてよい閉じ括弧など
+const CLOSING: [char; 5] = ['」', '』', '）', ')', '】'];
+
+/// 長い文章をチャットに貼り付けやすい長さに分割する
+///
+/// 文の終わり（。！？）と改行の位置で区切り、1つが`limit`文字以下になるようにまとめる。
+/// 1文だけで`limit`文字を超える場合は文の途中で区切る。
+/// 区切った位置の改行は取り除き、空になった部分は返さない。
+pub fn split(text: &str, limit: usize) -> Vec<String> {
+    let limit = limit.max(1);
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    let mut chunk_len = 0;
+
+    for sentence in sentences(text) {
+        let sentence_len = sentence.chars().count();
+        if chunk_len + sentence_len > limit && chunk_len > 0 {
+            push_chunk(&mut chunks, &mut chunk);
+            chunk_len = 0;
+        }
+        if sentence_len > limit {
+            // 1文が長すぎる場合は文字数で区切る
+            let mut chars = sentence.chars().peekable();
+            while chars.peek().is_some() {
+                let part: String = chars.by_ref().take(limit).collect();
+                chunk.push_str(&part);
+                if chars.peek().is_some() {
+                    push_chunk(&mut chunks, &mut chunk);
+                }
+            }
+            chunk_len = chunk.chars().count();
+        } else {
+            chunk.push_str(sentence);
+            chunk_len += sentence_len;
+        }
+    }
+    push_chunk(&mut chunks, &mut chunk);
+    chunks
+}
+
+/// 前後の改行を取り除いて追加する（空なら追加しない）
+fn push_chunk(chunks: &mut Vec<String>, chunk: &mut String) {
+    let trimmed = chunk.trim_matches(['\n', '\r']);
+    if !trimmed.trim().is_empty() {
+        chunks.push(trimmed.to_owned());
+    }
+    chunk.clear();
+}
+
+/// 文の終わりと改行で区切った文（区切りの文字を含む）
+fn sentences(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let mut end = rest.len();
+        let mut chars = rest.char_indices().peekable();
+        while let Some((index, c)) = chars.next() {
+            if c == '\n' {
+                end = index + c.len_utf8();
+                break;
+            }
+            if SENTENCE_ENDS.contains(&c) {
+                end = index + c.len_utf8();
+                // 「！？」や「。」」のように続く記号も同じ文に含める
+                while let Some(&(index, c)) = chars.peek() {
+                    if !(SENTENCE_ENDS.contains(&c) || CLOSING.contains(&c)) {
+                        break;
+                    }
+                    end = index + c.len_utf8();
+                    chars.next();
+                }
+                break;
+            }
+        }
+        let (sentence, after) = rest.split_at(end);
+        rest = after;
+        Some(sentence)
+    })
+}
+
+/// コピー先（貼り付ける場所に合わせてコピーする文字列を整形する）
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CopyTarget {
+    /// テキストのまま
+    #[default]
+    Plain,
+    /// ccfolia（伏せる結果のダイスはシークレットダイスにする）
+    Ccfolia,
+    /// Discord（話者の見出し、ダイスのコードブロック、伏せる結果のスポイラー）
+    Discord,
+}
+
+impl CopyTarget {
+    pub const ALL: [Self; 3] = [Self::Plain, Self::Ccfolia, Self::Discord];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Plain => "プレーン",
+            Self::Ccfolia => "ccfolia",
+            Self::Discord => "Discord",
+        }
+    }
+}
+
+/// スロットの判定のテキストをコピー先に合わせて整形する
+///
+/// プレースホルダーを置き換えた本文をコピー先の書式にし、モードの前後の文字列を付ける。
+/// 前後の文字列では変数に加えて`{話者}`、`{判定}`（判定名）、`{モード}`（モード名）も使える。
+pub fn format_slot(
+    target: CopyTarget,
+    slot: &TextSlot,
+    judge: Id,
+    mode: Option<&Mode>,
+    bindings: Bindings<'_>,
+) -> Resolved<'static> {
+    let body = placeholder::resolve(slot.text(judge), bindings);
+    let mut undefined = body.undefined;
+    let formatter = Formatter::new(target, slot, judge, mode, bindings, &mut undefined);
+    Resolved {
+        text: Cow::Owned(formatter.format(&body.text, true, true)),
+        undefined,
+    }
+}
+
+/// スロットの判定のテキストを分割してからコピー先に合わせて整形する
+///
+/// 分割でスポイラーやコードブロックが途切れないよう、置き換えた本文を先に分割し、それぞれをコピー先の書式にする。
+/// モードの前の文字列は最初、後の文字列は最後の部分だけに付ける。
+/// 書式を付けた後の1つ分が`limit`文字以下になるよう、書式の分だけ本文を短く区切る。
+pub fn format_slot_split(
+    target: CopyTarget,
+    slot: &TextSlot,
+    judge: Id,
+    mode: Option<&Mode>,
+    bindings: Bindings<'_>,
+    limit: usize,
+) -> Vec<String> {
+    let body = placeholder::resolve(slot.text(judge), bindings);
+    let mut undefined = body.undefined;
+    let formatter = Formatter::new(target, slot, judge, mode, bindings, &mut undefined);
+    let limit = limit.max(1);
+    let mut budget = limit;
+    loop {
+        let chunks = split(&body.text, budget);
+        let last = chunks.len().saturating_sub(1);
+        let parts: Vec<String> = chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| formatter.format(chunk, index == 0, index == last))
+            .collect();
+        let over = parts
+            .iter()
+            .map(|part| part.chars().count().saturating_sub(limit))
+            .max()
+            .unwrap_or(0);
+        // 書式だけで上限を超える場合は本文を1文字ずつにしたところで諦める
+        if over == 0 || budget == 1 {
+            return parts;
+        }
+        budget = budget.saturating_sub(over).max(1);
+    }
+}
+
+/// 置き換えた本文に付けるコピー先の書式
+struct Formatter<'a> {
+    target: CopyTarget,
+    hidden: bool,
+    speaker: Option<&'a str>,
+    /// Discordの見出しに付ける判定名（判定が1つだけのモードではNone）
+    judge_name: Option<&'a str>,
+    prefix: String,
+    suffix: String,
+}
+
+impl<'a> Formatter<'a> {
+    /// 前後の文字列を置き換え、未定義の変数を`undefined`に追加する
+    fn new(
+        target: CopyTarget,
+        slot: &'a TextSlot,
+        judge: Id,
+        mode: Option<&'a Mode>,
+        bindings: Bindings<'a>,
+        undefined: &mut Vec<String>,
+    ) -> Self {
+        let speaker = slot.speaker(bindings.players).map(|speaker| speaker.name);
+        let judge_name = mode
+            .and_then(|mode| mode.judge(judge))
+            .map(|judge| judge.name.as_str());
+        let mut formatter = Self {
+            target,
+            hidden: slot.hidden,
+            speaker,
+            // 判定が1つだけのモード（地の文など）では判定名を付けない
+            judge_name: judge_name.filter(|_| mode.is_some_and(|mode| mode.judges.len() > 1)),
+            prefix: String::new(),
+            suffix: String::new(),
+        };
+        let Some(mode) = mode else {
+            return formatter;
+        };
+
+        // 話者/判定/モードはシナリオの変数より優先する
+        let mut variables = vec![
+            template_variable("話者", speaker.unwrap_or_default()),
+            template_variable("判定", judge_name.unwrap_or_default()),
+            template_variable("モード", &mode.name),
+        ];
+        variables.extend_from_slice(bindings.variables);
+        let template_bindings = Bindings {
+            players: bindings.players,
+            variables: &variables,
+        };
+        let mut expand = |template: &str| {
+            let resolved = placeholder::resolve(template, template_bindings);
+            for name in resolved.undefined {
+                if !undefined.contains(&name) {
+                    undefined.push(name);
+                }
+            }
+            resolved.text.into_owned()
+        };
+        formatter.prefix = expand(&mode.copy_prefix);
+        formatter.suffix = expand(&mode.copy_suffix);
+        formatter
+    }
+
+    /// 本文をコピー先の書式にする（`first`/`last`なら前/後の文字列を付ける）
+    fn format(&self, body: &str, first: bool, last: bool) -> String {
+        let body = match self.target {
+            CopyTarget::Plain => body.to_owned(),
+            CopyTarget::Ccfolia => ccfolia(body, self.hidden),
+            CopyTarget::Discord => discord(body, self.speaker, self.judge_name, self.hidden),
+        };
+        let prefix = if first { self.prefix.as_str() } else { "" };
+        let suffix = if last { self.suffix.as_str() } else { "" };
+        format!("{prefix}{body}{suffix}")
+    }
+}
+
+fn template_variable(name: &str, value: &str) -> Variable {
+    Variable {
+        name: name.to_owned(),
+        value: value.to_owned(),
+    }
+}
+
+/// ccfolia向け（伏せる場合はダイスコマンドの行の先頭に`S`を付けてシークレットダイスにする）
+fn ccfolia(text: &str, hidden: bool) -> String {
+    if !hidden {
+        return text.to_owned();
+    }
+    text.lines()
+        .map(|line| {
+            if dice::is_command(line) {
+                format!("S{}", line.trim_start())
+            } else {
+                line.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Discord向け（`**話者**`の見出し、判定名、ダイスコマンドの行はコードブロック、伏せる場合は`||スポイラー||`）
+fn discord(text: &str, speaker: Option<&str>, judge: Option<&str>, hidden: bool) -> String {
+    let mut body = String::new();
+    if let Some(judge) = judge.filter(|judge| !judge.is_empty()) {
+        body.push('【');
+        body.push_str(judge);
+        body.push_str("】\n");
+    }
+    // 続いたダイスコマンドの行は1つのコードブロックにまとめる
+    let mut in_code = false;
+    for line in text.lines() {
+        let is_command = dice::is_command(line);
+        if is_command != in_code {
+            body.push_str("```\n");
+            in_code = is_command;
+        }
+        body.push_str(line);
+        body.push('\n');
+    }
+    if in_code {
+        body.push_str("```");
+    }
+    let body = body.trim_end_matches('\n');
+
+    let mut message = String::new();
+    if let Some(speaker) = speaker.filter(|speaker| !speaker.is_empty()) {
+        message.push_str("**");
+        message.push_str(speaker);
+        message.push_str("**\n");
+    }
+    if hidden && !body.is_empty() {
+        message.push_str("||");
+        message.push_str(body);
+        message.push_str("||");
+    } else {
+        message.push_str(body);
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Player;
+
+    /// 判定が2つのモードと、話者と本文を設定したスロット
+    fn slot_with(text: &str, hidden: bool, player: &Player) -> (Mode, TextSlot) {
+        let mode = Mode::new(
+            String::from("探索"),
+            vec![String::from("成功"), String::from("失敗")],
+            1,
+        );
+        let mut slot = TextSlot::new_empty(&mode.judges);
+        *slot.text_mut(slot.selected_judge) = text.to_owned();
+        slot.speaker = Some(player.id);
+        slot.hidden = hidden;
+        (mode, slot)
+    }
+
+    fn player() -> Player {
+        Player {
+            name: String::from("KP"),
+            ..Player::default()
+        }
+    }
+
+    #[test]
+    fn splits_long_sentence_by_chars() {
+        assert_eq!(
+            split("あいうえおかきくけこ。", 4),
+            vec!["あいうえ", "おかきく", "けこ。"],
+            "区切りのない長い文は文字数で分ける"
+        );
+        // 長い文の残りには続きの文をまとめる
+        assert_eq!(
+            split("あいうえおか。きく。", 4),
+            vec!["あいうえ", "おか。", "きく。"],
+            "長い文の残りと次の文"
+        );
+    }
+
+    #[test]
+    fn splits_at_exact_boundary() {
+        assert_eq!(
+            split("あいう。えお。", 4),
+            vec!["あいう。", "えお。"],
+            "1文ちょうどの上限"
+        );
+        assert_eq!(
+            split("あいう。えお。", 7),
+            vec!["あいう。えお。"],
+            "全体ちょうどの上限なら分けない"
+        );
+        assert_eq!(
+            split("あいう。えお。", 6),
+            vec!["あいう。", "えお。"],
+            "1文字足りなければ文の区切りで分ける"
+        );
+    }
+
+    #[test]
+    fn keeps_closing_marks_with_sentence() {
+        assert_eq!(
+            split("「待て！」と言った。走った。", 6),
+            vec!["「待て！」", "と言った。", "走った。"],
+            "閉じ括弧は文の終わりに付ける"
+        );
+    }
+
+    #[test]
+    fn splits_crlf_lines() {
+        assert_eq!(
+            split("一行目\r\n二行目\r\n", 5),
+            vec!["一行目", "二行目"],
+            "CRLFの行で分ける"
+        );
+        assert_eq!(
+            split("一行目\r\n二行目", 10),
+            vec!["一行目\r\n二行目"],
+            "収まれば改行はそのまま"
+        );
+        assert_eq!(
+            split("一行目\r\n二行目\r\n", 4),
+            vec!["一行目", "二行目"],
+            "分けた境目の改行は残さない"
+        );
+    }
+
+    #[test]
+    fn counts_chars_not_bytes() {
+        // 15バイトだが5文字
+        assert_eq!(
+            split("あいうえお", 5),
+            vec!["あいうえお"],
+            "5文字なら分けない"
+        );
+        assert_eq!(
+            split("あいうえおか", 5),
+            vec!["あいうえお", "か"],
+            "6文字なら分ける"
+        );
+    }
+
+    #[test]
+    fn empty_body_has_no_chunks() {
+        assert!(split("", 10).is_empty(), "空の本文");
+        assert!(split("\n\r\n\n", 10).is_empty(), "改行だけの本文");
+        assert_eq!(split("あ", 0), vec!["あ"], "上限は1文字以上として扱う");
+    }
+
+    #[test]
+    fn sentences_keep_delimiters() {
+        let sentences: Vec<&str> = sentences("はい。いいえ！？\nどうして").collect();
+        assert_eq!(
+            sentences,
+            vec!["はい。", "いいえ！？", "\n", "どうして"],
+            "区切り文字は文に含め、改行は別に返す"
+        );
+    }
+
+    #[test]
+    fn discord_chunks_keep_spoilers_and_fences_balanced() {
+        let player = player();
+        let players = [player];
+        let bindings = Bindings {
+            players: &players,
+            variables: &[],
+        };
+        let text = "扉の向こうから物音がした。誰かがいるようだ。\n\
+                    CC<=50 聞き耳\n\
+                    1D100<=60 目星\n\
+                    床には古い日記が落ちている。ページは湿っていて読みにくい。\n\
+                    最後のページには震える字で名前が書かれていた。";
+        let (mode, slot) = slot_with(text, true, &players[0]);
+        for limit in [40, 60, 80] {
+            let chunks = format_slot_split(
+                CopyTarget::Discord,
+                &slot,
+                slot.selected_judge,
+                Some(&mode),
+                bindings,
+                limit,
+            );
+            assert!(chunks.len() > 1, "上限{limit}文字なら分割される");
+            for chunk in &chunks {
+                assert!(
+                    chunk.chars().count() <= limit,
+                    "上限{limit}文字に収まる（{}文字）\n{chunk}",
+                    chunk.chars().count()
+                );
+                assert_eq!(
+                    chunk.matches("||").count(),
+                    2,
+                    "伏せ字の記号が対になる\n{chunk}"
+                );
+                assert!(
+                    chunk.ends_with("||"),
+                    "各チャンクの最後で伏せ字を閉じる\n{chunk}"
+                );
+                assert_eq!(
+                    chunk.matches("```").count() % 2,
+                    0,
+                    "コードブロックの記号が対になる\n{chunk}"
+                );
+                assert!(
+                    chunk.starts_with("**KP**\n"),
+                    "各チャンクに話者を付ける\n{chunk}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn split_format_adds_prefix_and_suffix_once() {
+        let player = player();
+        let players = [player];
+        let bindings = Bindings {
+            players: &players,
+            variables: &[],
+        };
+        let (mut mode, slot) =
+            slot_with("一文目です。二文目です。三文目です。", false, &players[0]);
+        mode.copy_prefix = String::from("【{モード}】");
+        mode.copy_suffix = String::from("（{判定}）");
+        let chunks = format_slot_split(
+            CopyTarget::Plain,
+            &slot,
+            slot.selected_judge,
+            Some(&mode),
+            bindings,
+            12,
+        );
+        assert_eq!(
+            chunks,
+            vec![
+                "【探索】一文目です。",
+                "二文目です。",
+                "三文目です。（成功）"
+            ],
+            "前置きは最初、後書きは最後のチャンクにだけ付ける"
+        );
+    }
+
+    #[test]
+    fn ccfolia_hidden_dice_become_secret() {
+        let player = player();
+        let players = [player];
+        let bindings = Bindings {
+            players: &players,
+            variables: &[],
+        };
+        let (mode, slot) = slot_with("CC<=50 聞き耳\n物音がした。", true, &players[0]);
+        let resolved = format_slot(
+            CopyTarget::Ccfolia,
+            &slot,
+            slot.selected_judge,
+            Some(&mode),
+            bindings,
+        );
+        assert_eq!(
+            resolved.text, "SCC<=50 聞き耳\n物音がした。",
+            "結果を伏せるスロットのダイスはシークレットダイスにする"
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use crate::app::Player;
+use crate::scenario::{SCENARIO_FORMAT_VERSION, Scenario, default_players};
+use crate::scene::{Id, Mode, Scene, TextSlot};
+use crate::system::GameSystem;
+use std::collections::BTreeMap;
+
+/// バージョン1以前のシナリオ（`contents[mode_index][slot_index].texts[judge_index]`）
+#[derive(serde::Deserialize)]
+pub struct LegacyScenario {
+    #[serde(default)]
+    pub system: GameSystem,
+    #[serde(default)]
+    pub scenes: Vec<LegacyScene>,
+    #[serde(default)]
+    pub modes: Vec<LegacyMode>,
+    #[serde(default)]
+    pub player: Option<Vec<Player>>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct LegacyScene {
+    pub title: String,
+    #[serde(default)]
+    pub mode_index: usize,
+    #[serde(default)]
+    pub contents: Vec<Vec<LegacyTextSlot>>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct LegacyTextSlot {
+    #[serde(default)]
+    pub texts: Vec<String>,
+    #[serde(default)]
+    pub selected_judge_index: usize,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub icon_path: Option<std::path::PathBuf>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct LegacyMode {
+    pub name: String,
+    #[serde(default)]
+    pub judges: Vec<String>,
+    #[serde(default)]
+    pub default_text_num: usize,
+}
+
+/// eframeに保存されていた古いアプリ状態
+///
+/// 最初期はシーン/モード/プレイヤーを直接持ち、その後`scenario`にまとめられた。
+#[derive(serde::Deserialize)]
+pub struct LegacyAppState {
+    #[serde(default)]
+    pub scenario: Option<LegacyScenario>,
+    #[serde(default)]
+    pub scenes: Vec<LegacyScene>,
+    #[serde(default)]
+    pub modes: Vec<LegacyMode>,
+    #[serde(default)]
+    pub player: Option<Vec<Player>>,
+    #[serde(default)]
+    pub selected_scene_index: usize,
+}
+
+impl LegacyAppState {
+    /// シナリオと選択中のシーンIDに変換する
+    /// 返り値: (シナリオ, 選択中のシーンID, 破棄したテキストの数（`LegacyScenario::migrate`を参照）)
+    pub fn into_scenario(self) -> (Scenario, Id, usize) {
+        let legacy = self.scenario.unwrap_or(LegacyScenario {
+            system: GameSystem::Generic,
+            scenes: self.scenes,
+            modes: self.modes,
+            player: self.player,
+        });
+        let (scenario, dropped) = legacy.migrate();
+        let selected = scenario
+            .scenes
+            .get(self.selected_scene_index)
+            .or(scenario.scenes.first())
+            .map(|scene| scene.id)
+            .unwrap_or_default();
+        (scenario, selected, dropped)
+    }
+}
+
+impl LegacyScenario {
+    /// 各項目に新しいIDを割り当て、位置による参照をIDによる参照に置き換える
+    /// 返り値: (シナリオ, 対応するモード/判定がないため破棄した空でないテキストの数)
+    pub fn migrate(self) -> (Scenario, usize) {
+        let modes: Vec<Mode> = self
+            .modes
+            .into_iter()
+            .map(|mode| Mode::new(mode.name, mode.judges, mode.default_text_num))
+            .collect();
+
+        let mut dropped = 0;
+        let mut scenes = Vec::with_capacity(self.scenes.len());
+        for scene in self.scenes {
+            let mut contents = BTreeMap::new();
+            let mut legacy_contents = scene.contents.into_iter();
+            for (mode, slots) in modes.iter().zip(legacy_contents.by_ref()) {
+                let slots = slots
+                    .into_iter()
+                    .map(|slot| {
+                        let (slot, lost) = migrate_slot(mode, slot);
+                        dropped += lost;
+                        slot
+                    })
+                    .collect();
+                contents.insert(mode.id, slots);
+            }
+            // モードの数より多い内容も対応先がない
+            dropped += legacy_contents
+                .flatten()
+                .flat_map(|slot| slot.texts)
+                .filter(|text| !text.is_empty())
+                .count();
+            scenes.push(Scene {
+                id: Id::new(),
+                title: scene.title,
+                mode_id: modes
+                    .get(scene.mode_index)
+                    .map(|mode| mode.id)
+                    .unwrap_or_default(),
+                contents,
+                layout_cache: None,
+            });
+        }
+
+        let mut scenario = Scenario {
+            version: SCENARIO_FORMAT_VERSION,
+            system: self.system,
+            scenes,
+            modes,
+            player: self.player.unwrap_or_else(default_players),
+            variables: Vec::new(),
+        };
+        scenario.normalize();
+        (scenario, dropped)
+    }
+}
+
+/// 返り値: (変換したスロット, 対応する判定がないため破棄した空でないテキストの数)
+fn migrate_slot(mode: &Mode, slot: LegacyTextSlot) -> (TextSlot, usize) {
+    let mut migrated = TextSlot::new_empty(&mode.judges);
+    let mut texts = slot.texts.into_iter();
+    for (judge, text) in mode.judges.iter().zip(texts.by_ref()) {
+        *migrated.text_mut(judge.id) = text;
+    }
+    // 判定の数より多いテキストは対応先がない
+    let dropped = texts.filter(|text| !text.is_empty()).count();
+    if let Some(judge) = mode.judges.get(slot.selected_judge_index) {
+        migrated.selected_judge = judge.id;
+    }
+    migrated.label = slot.label;
+    migrated.icon_path = slot.icon_path;
+    (migrated, dropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 最初期のeframeの保存データ（シーン/モード/プレイヤーを直接持つ）
+    const LEGACY_STATE: &str = r#"{
+        "scenes": [
+            {
+                "title": "導入",
+                "mode_index": 0,
+                "contents": [
+                    [{ "texts": ["地の文のテキスト"], "selected_judge_index": 0 }],
+                    [
+                        { "texts": ["成功文", "失敗文", "余分な文"], "selected_judge_index": 1, "label": "PC1" }
+                    ]
+                ]
+            },
+            {
+                "title": "探索",
+                "mode_index": 1,
+                "contents": [[], []]
+            }
+        ],
+        "modes": [
+            { "name": "地の文", "judges": ["本文"], "default_text_num": 1 },
+            { "name": "探索", "judges": ["成功", "失敗"], "default_text_num": 2 }
+        ],
+        "player": [{ "name": "探索者", "icon_path": null }],
+        "selected_scene_index": 1
+    }"#;
+
+    #[test]
+    fn migrates_legacy_app_state() {
+        let legacy: LegacyAppState =
+            serde_json::from_str(LEGACY_STATE).expect("古い保存データを読み込める");
+        let (scenario, selected, dropped) = legacy.into_scenario();
+
+        assert_eq!(dropped, 1, "判定の数より多い「余分な文」を数える");
+        assert_eq!(
+            scenario.version, SCENARIO_FORMAT_VERSION,
+            "現在の形式になる"
+        );
+        assert_eq!(scenario.modes.len(), 2, "モードの数");
+        assert_eq!(scenario.scenes.len(), 2, "シーンの数");
+        assert_eq!(scenario.player.len(), 1, "プレイヤーの数");
+        assert_eq!(
+            scenario.scenes.get(1).map(|scene| scene.id),
+            Some(selected),
+            "選択中のシーンは位置からIDになる"
+        );
+
+        let narration = scenario.modes.first().expect("地の文のモードがある");
+        let explore = scenario.modes.get(1).expect("探索のモードがある");
+        let intro = scenario.scenes.first().expect("導入のシーンがある");
+        assert_eq!(intro.title, "導入", "シーン名");
+        assert_eq!(intro.mode_id, narration.id, "モードの位置はモードIDになる");
+
+        let narration_slot = intro
+            .slots(narration.id)
+            .first()
+            .expect("地の文のスロットがある");
+        let body = narration.judges.first().expect("本文の判定がある");
+        assert_eq!(
+            narration_slot.text(body.id),
+            "地の文のテキスト",
+            "地の文のテキスト"
+        );
+
+        let explore_slot = intro
+            .slots(explore.id)
+            .first()
+            .expect("探索のスロットがある");
+        let success = explore.judges.first().expect("成功の判定がある");
+        let failure = explore.judges.get(1).expect("失敗の判定がある");
+        assert_eq!(explore_slot.text(success.id), "成功文", "成功のテキスト");
+        assert_eq!(explore_slot.text(failure.id), "失敗文", "失敗のテキスト");
+        assert_eq!(explore_slot.texts.len(), 2, "判定のないテキストは破棄する");
+        assert_eq!(explore_slot.selected_judge, failure.id, "選択中の判定");
+        assert_eq!(explore_slot.label.as_deref(), Some("PC1"), "ラベル");
+    }
+
+    #[test]
+    fn migrates_scenario_wrapped_state() {
+        let legacy: LegacyAppState = serde_json::from_str(
+            r#"{
+                "scenario": {
+                    "system": "Coc6",
+                    "scenes": [{ "title": "導入", "mode_index": 5, "contents": [] }],
+                    "modes": [{ "name": "地の文", "judges": ["本文"], "default_text_num": 1 }]
+                }
+            }"#,
+        )
+        .expect("古い保存データを読み込める");
+        let (scenario, selected, dropped) = legacy.into_scenario();
+
+        assert_eq!(dropped, 0, "破棄するテキストはない");
+        assert_eq!(scenario.system, GameSystem::Coc6, "ゲームシステム");
+        let scene = scenario.scenes.first().expect("シーンがある");
+        assert_eq!(scene.id, selected, "先頭のシーンを選択する");
+        let mode = scenario.modes.first().expect("モードがある");
+        assert_eq!(
+            scene.mode_id, mode.id,
+            "存在しないモードの位置は先頭のモードにする"
+        );
+        assert_eq!(
+            scene.slots(mode.id).len(),
+            0,
+            "内容がなければスロットもない"
+        );
+        assert!(!scenario.player.is_empty(), "既定のプレイヤー一覧を使う");
+    }
+
+    #[test]
+    fn counts_texts_without_a_mode() {
+        let legacy: LegacyScenario = serde_json::from_str(
+            r#"{
+                "scenes": [{
+                    "title": "導入",
+                    "contents": [
+                        [{ "texts": ["本文", ""] }],
+                        [{ "texts": ["モードのない文", ""] }]
+                    ]
+                }],
+                "modes": [{ "name": "地の文", "judges": ["本文"], "default_text_num": 1 }]
+            }"#,
+        )
+        .expect("古いシナリオを読み込める");
+        let (_, dropped) = legacy.migrate();
+        assert_eq!(dropped, 1, "空のテキストは数えず、モードのない内容は数える");
+    }
+}
@@ -1,25 +1,52 @@
-use crate::constants::constants::*;
+use crate::constants::constants::{
+    ICON_AREA_WIDTH, ICON_SIZE, JUDGE_BUTTON_WIDTH, SLOT_HANDLE_WIDTH, SLOT_SPACING,
+    TEXTBOX_MIN_HEIGHT,
+};
+use crate::history::{Change, History};
+use crate::message;
+use crate::panels::player_view::Revealed;
+use crate::panels::queue;
+use crate::parser::dice::{Command, DiceRng, Verdict};
+use crate::placeholder::{self, Bindings, Resolved};
 use crate::scene;
+use crate::search::fold_width;
+use crate::system::GameSystem;
 use crate::widgets::combobox::enable_wheel;
+use crate::widgets::icon::{self, IconCache};
 use crate::{
-    app::{AppMode, Modal, Player_default},
-    scene::{LayoutCache, Mode, Scene},
+    app::{AppMode, CopySettings, Modal, Player},
+    scene::{Id, LayoutCache, Mode, Scene, find_mode, mode_position},
 };
-#[allow(unused_imports)]
-use eframe::{App, egui};
-use egui::widgets;
+use eframe::egui;
+
+/// スロットの技能判定、コピーと編集の記録に使う状態
+pub struct SlotContext<'a> {
+    pub system: GameSystem,
+    pub rng: &'a mut DiceRng,
+    pub copy: CopySettings,
+    /// このフレームでコピーしたスロット（プレイヤー用ウィンドウに表示する）
+    pub revealed: Option<Revealed>,
+    /// このフレームでフォーカスまたはクリックされたスロット（ショートカットキーの対象にする）
+    pub focused: Option<Id>,
+    /// 編集を記録する履歴
+    pub history: &'a mut History,
+}
 
-/// CentralPanelのメイン表示関数
-#[allow(clippy::too_many_arguments)]
+/// `CentralPanel`のメイン表示関数
+#[expect(clippy::too_many_arguments)]
 pub fn show(
     ctx: &egui::Context,
     modes: &[Mode],
     scenes: &mut Vec<Scene>,
     selected_scene_index: &mut usize,
     create_index: &mut usize,
+    new_scene_mode: Option<Id>,
     app_mode: &AppMode,
     mordal: &mut Modal,
     toasts: &mut egui_notify::Toasts,
+    bindings: Bindings<'_>,
+    icons: &mut IconCache,
+    mut context: SlotContext<'_>,
 ) {
     egui::CentralPanel::default().show(ctx, |ui| {
         // 上段: シーン選択、モード選択、追加/削除ボタン
@@ -33,16 +60,198 @@ pub fn show(
                 scenes,
                 selected_scene_index,
                 create_index,
+                new_scene_mode,
                 &mut mordal.editing_scene_delete_modal_open,
+                context.history,
             );
         });
 
+        // コピーキュー（コピーモードのみ）
+        let mut queue_cursor = None;
+        if *app_mode == AppMode::Copy {
+            if let Some(scene) = scenes.get(*selected_scene_index) {
+                let copy_queue = &mut mordal.copy_queue;
+                copy_queue.sync(scene);
+                let step =
+                    queue::show_bar(ui, copy_queue, scene.current_slots().len()).or_else(|| {
+                        copy_queue
+                            .active
+                            .then(|| queue::shortcut_step(ctx))
+                            .flatten()
+                    });
+                if let Some(step) = step {
+                    let mode = find_mode(modes, scene.mode_id);
+                    let mut revealed = None;
+                    let mut format = |slot: &scene::TextSlot| {
+                        revealed = Revealed::from_slot(slot, slot.selected_judge, bindings);
+                        message::format_slot(
+                            context.copy.target,
+                            slot,
+                            slot.selected_judge,
+                            mode,
+                            bindings,
+                        )
+                        .text
+                        .into_owned()
+                    };
+                    mordal.scroll_to_slot = copy_queue.step(step, ctx, scene, &mut format, toasts);
+                    if let Some(revealed) = revealed {
+                        mordal.player_view.reveal(revealed);
+                    }
+                }
+                queue_cursor = copy_queue.cursor();
+            }
+        }
+
         // 下段: 全スロットを縦に並べて表示
-        show_all_slots(ui, modes, scenes, selected_scene_index, app_mode, toasts);
+        let action = egui::ScrollArea::vertical()
+            .show(ui, |ui| {
+                show_all_slots(
+                    ui,
+                    modes,
+                    scenes,
+                    selected_scene_index,
+                    app_mode,
+                    toasts,
+                    bindings,
+                    icons,
+                    &mut context,
+                    queue_cursor,
+                    &mut mordal.scroll_to_slot,
+                )
+            })
+            .inner;
+        if let Some(action) = action {
+            apply_slot_action(
+                scenes,
+                *selected_scene_index,
+                action,
+                mordal,
+                context.history,
+            );
+        }
+        if let Some(revealed) = context.revealed.take() {
+            mordal.player_view.reveal(revealed);
+        }
+        if let Some(focused) = context.focused.take() {
+            mordal.focused_slot = Some(focused);
+        }
     });
 }
 
-/// シーン選択UI（左右ボタン + ComboBox）
+/// スロットのハンドルから選ばれた操作
+#[derive(Clone, Copy)]
+enum SlotAction {
+    /// 削除（確認モーダルを開く）
+    Delete(usize),
+    Duplicate(usize),
+    /// (移動元, 移動先)
+    Move(usize, usize),
+    /// 同じモードのまま別のシーンの末尾へ移動
+    MoveToScene(usize, Id),
+    /// 結果を伏せてコピーするかを切り替える
+    ToggleHidden(usize),
+}
+
+/// ドラッグ中のスロットの位置
+struct SlotDrag(usize);
+
+/// スロット操作を適用して履歴に記録する（レイアウトキャッシュは変更したシーンごとに無効化される）
+fn apply_slot_action(
+    scenes: &mut [Scene],
+    selected_index: usize,
+    action: SlotAction,
+    mordal: &mut Modal,
+    history: &mut History,
+) {
+    let Some(scene) = scenes.get_mut(selected_index) else {
+        return;
+    };
+    let (scene_id, mode_id) = (scene.id, scene.mode_id);
+    match action {
+        SlotAction::Delete(slot_index) => {
+            if let Some(slot) = scene.current_slots().get(slot_index) {
+                mordal.slot_delete = Some((scene.id, slot.id));
+            }
+        }
+        SlotAction::Duplicate(slot_index) => {
+            scene.duplicate_slot(slot_index);
+            if let Some(copy) = scene.current_slots().get(slot_index + 1) {
+                history.push(
+                    "スロットの複製",
+                    Change::RemoveSlot {
+                        scene: scene_id,
+                        slot: copy.id,
+                    },
+                );
+            }
+        }
+        SlotAction::Move(from, to) => {
+            let last = scene.current_slots().len().saturating_sub(1);
+            if from <= last && from != to {
+                scene.move_slot(from, to);
+                history.push(
+                    "スロットの並べ替え",
+                    Change::MoveSlot {
+                        scene: scene_id,
+                        mode: mode_id,
+                        from: to.min(last),
+                        to: from,
+                    },
+                );
+            }
+        }
+        SlotAction::ToggleHidden(slot_index) => {
+            if let Some(slot) = scene.current_slots_mut().get_mut(slot_index) {
+                let before = slot.clone();
+                slot.hidden = !slot.hidden;
+                history.push(
+                    "結果を伏せる設定の変更",
+                    Change::Slot {
+                        scene: scene_id,
+                        slot: Box::new(before),
+                    },
+                );
+            }
+        }
+        SlotAction::MoveToScene(slot_index, target_id) => {
+            let Some(slot) = scene.take_slot(slot_index) else {
+                return;
+            };
+            match scenes.iter_mut().find(|scene| scene.id == target_id) {
+                Some(target) => {
+                    // 移動先から取り除き、元のシーンの元の位置に戻す
+                    history.push(
+                        "スロットを別のシーンへ移動",
+                        Change::Batch(vec![
+                            Change::RemoveSlot {
+                                scene: target_id,
+                                slot: slot.id,
+                            },
+                            Change::InsertSlot {
+                                scene: scene_id,
+                                mode: mode_id,
+                                index: slot_index,
+                                slot: Box::new(slot.clone()),
+                            },
+                        ]),
+                    );
+                    target.slots_mut(mode_id).push(slot);
+                    target.layout_cache = None;
+                }
+                // 移動先が見つからなければ元に戻す
+                None => {
+                    if let Some(scene) = scenes.get_mut(selected_index) {
+                        let slots = scene.current_slots_mut();
+                        slots.insert(slot_index.min(slots.len()), slot);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// シーン選択UI（左右ボタン + `ComboBox`）
 fn show_scene_selector(ui: &mut egui::Ui, scenes: &[Scene], selected_index: &mut usize) {
     ui.label("Choose Scene");
     if ui.button("◀").clicked() {
@@ -58,7 +267,7 @@ fn show_scene_selector(ui: &mut egui::Ui, scenes: &[Scene], selected_index: &mut
                 }
             })
             .response;
-        enable_wheel(ui, selected_index, &scenes, &combo_response);
+        enable_wheel(ui, selected_index, scenes, &combo_response);
     }
 
     if ui.button("▶").clicked() {
@@ -71,31 +280,24 @@ fn show_mode_selector(
     ui: &mut egui::Ui,
     modes: &[Mode],
     scenes: &mut [Scene],
-    selected_index: &mut usize,
+    selected_index: &usize,
 ) {
-    // 借用エラー回避のため先にmode_indexを取得
-    let current_mode_index = scenes.get(*selected_index).map(|s| s.mode_index);
-    if let Some(mode_index) = current_mode_index {
-        if let Some(current_mode) = modes.get(mode_index) {
+    // 借用エラー回避のため先にmode_idを取得
+    let current_mode_id = scenes.get(*selected_index).map(|s| s.mode_id);
+    if let Some(mode_id) = current_mode_id {
+        if let Some(current_mode) = find_mode(modes, mode_id) {
             ui.label("Choose Mode:");
-            let combo_resp = egui::ComboBox::from_id_source("mode_combo")
+            let combo_resp = egui::ComboBox::from_id_salt("mode_combo")
                 .selected_text(&current_mode.name)
                 .show_ui(ui, |ui| {
-                    for (index, mode) in modes.iter().enumerate() {
+                    for mode in modes {
                         if ui
-                            .selectable_label(mode_index == index, &mode.name)
+                            .selectable_label(mode_id == mode.id, &mode.name)
                             .clicked()
                         {
                             if let Some(scene_mut) = scenes.get_mut(*selected_index) {
-                                scene_mut.mode_index = index;
                                 // モード変更時は各スロットの判定をリセット
-                                if let Some(mode_slots) = scene_mut.contents.get_mut(index) {
-                                    for slot in mode_slots.iter_mut() {
-                                        slot.selected_judge_index = 0;
-                                    }
-                                }
-                                // キャッシュを無効化
-                                scene_mut.layout_cache = None;
+                                scene_mut.set_mode(mode);
                             }
                         }
                     }
@@ -104,7 +306,11 @@ fn show_mode_selector(
 
             // ホイールでモード切り替え
             if let Some(scene) = scenes.get_mut(*selected_index) {
-                enable_wheel(ui, &mut scene.mode_index, modes, &combo_resp);
+                let mut position = mode_position(modes, scene.mode_id).unwrap_or(0);
+                enable_wheel(ui, &mut position, modes, &combo_resp);
+                if let Some(mode) = modes.get(position) {
+                    scene.mode_id = mode.id;
+                }
             }
         }
     }
@@ -123,14 +329,16 @@ fn show_scene_edit_button(ui: &mut egui::Ui, editing_scene_name_modal_open: &mut
 }
 
 /// シーン追加/削除ボタン
-#[allow(clippy::collapsible_if)]
+#[expect(clippy::too_many_arguments)]
 fn show_scene_buttons(
     ui: &mut egui::Ui,
     modes: &[Mode],
     scenes: &mut Vec<Scene>,
     selected_index: &mut usize,
     create_index: &mut usize,
+    new_scene_mode: Option<Id>,
     editing_scene_delete_modal_open: &mut bool,
+    history: &mut History,
 ) {
     if ui
         .add(
@@ -139,7 +347,9 @@ fn show_scene_buttons(
         )
         .clicked()
     {
-        scenes.push(Scene::new(*create_index, modes));
+        let scene = Scene::new(*create_index, modes, new_scene_mode);
+        history.push("シーンの追加", Change::RemoveScene { scene: scene.id });
+        scenes.push(scene);
         *create_index += 1;
         *selected_index = scenes.len() - 1; // 新規シーンを選択
     }
@@ -150,22 +360,29 @@ fn show_scene_buttons(
                 .fill(egui::Color32::DARK_RED),
         )
         .clicked()
+        && scenes.len() > 1
     {
-        if scenes.len() > 1 {
-            *editing_scene_delete_modal_open = true;
-        }
+        *editing_scene_delete_modal_open = true;
     }
 }
 
 /// 全スロット表示（縦に並べて表示 + 追加ボタン）
+/// 返り値: ハンドルのメニューやドラッグで選ばれたスロット操作
+#[expect(clippy::too_many_arguments)]
 fn show_all_slots(
     ui: &mut egui::Ui,
     modes: &[Mode],
-    scenes: &mut Vec<Scene>,
-    selected_scene_index: &mut usize,
+    scenes: &mut [Scene],
+    selected_scene_index: &usize,
     app_mode: &AppMode,
     toasts: &mut egui_notify::Toasts,
-) {
+    bindings: Bindings<'_>,
+    icons: &mut IconCache,
+    context: &mut SlotContext<'_>,
+    queue_cursor: Option<usize>,
+    scroll_to_slot: &mut Option<Id>,
+) -> Option<SlotAction> {
+    let mut action = None;
     ui.vertical(|ui| {
         let mut max_judge_width: f32 = 0.0;
         let mut max_icon_width: f32 = 0.0;
@@ -174,7 +391,7 @@ fn show_all_slots(
         // キャッシュのチェック
         if let Some(scene) = scenes.get(*selected_scene_index) {
             if let Some(cache) = &scene.layout_cache {
-                if cache.mode_index == scene.mode_index {
+                if cache.mode_id == scene.mode_id {
                     // キャッシュが有効
                     max_judge_width = cache.judge_width;
                     max_icon_width = cache.icon_width;
@@ -183,85 +400,226 @@ fn show_all_slots(
             }
         }
 
-        // キャッシュが無効な場合は再計算
-        if !cache_valid {
-            if let Some(scene) = scenes.get(*selected_scene_index) {
-                let mode_index = scene.mode_index;
-                if let Some(mode_slots) = scene.contents.get(mode_index) {
-                    let slot_count = mode_slots.len();
-
-                    // 各スロットを表示し、最大幅を記録
-                    for slot_index in 0..slot_count {
-                        let (judge_width, icon_width) = show_slot(
-                            ui,
-                            modes,
-                            scenes,
-                            selected_scene_index,
-                            slot_index,
-                            app_mode,
-                            toasts,
-                        );
-                        max_judge_width = max_judge_width.max(judge_width);
-                        max_icon_width = max_icon_width.max(icon_width);
-                        ui.add_space(SLOT_SPACING);
-                    }
+        let slot_count = scenes
+            .get(*selected_scene_index)
+            .map_or(0, |scene| scene.current_slots().len());
 
-                    // キャッシュを更新
-                    if let Some(scene_mut) = scenes.get_mut(*selected_scene_index) {
-                        scene_mut.layout_cache = Some(LayoutCache {
-                            judge_width: max_judge_width,
-                            icon_width: max_icon_width,
-                            mode_index: scene_mut.mode_index,
-                        });
-                    }
-                }
+        // 各スロットを表示し、キャッシュが無効な場合は最大幅を記録
+        for slot_index in 0..slot_count {
+            let row = ui.horizontal(|ui| {
+                show_slot_handle(
+                    ui,
+                    scenes,
+                    *selected_scene_index,
+                    slot_index,
+                    slot_count,
+                    app_mode,
+                    queue_cursor,
+                    &mut action,
+                );
+                show_slot(
+                    ui,
+                    modes,
+                    scenes,
+                    selected_scene_index,
+                    slot_index,
+                    app_mode,
+                    toasts,
+                    bindings,
+                    icons,
+                    context,
+                )
+            });
+            if !cache_valid {
+                let (judge_width, icon_width) = row.inner;
+                max_judge_width = max_judge_width.max(judge_width);
+                max_icon_width = max_icon_width.max(icon_width);
             }
-        } else {
-            // キャッシュが有効な場合でもスロットを描画
-            if let Some(scene) = scenes.get(*selected_scene_index) {
-                let mode_index = scene.mode_index;
-                if let Some(mode_slots) = scene.contents.get(mode_index) {
-                    let slot_count = mode_slots.len();
-
-                    for slot_index in 0..slot_count {
-                        show_slot(
-                            ui,
-                            modes,
-                            scenes,
-                            selected_scene_index,
-                            slot_index,
-                            app_mode,
-                            toasts,
-                        );
-                        ui.add_space(SLOT_SPACING);
-                    }
-                }
+            handle_slot_drop(ui, &row.response, slot_index, &mut action);
+
+            // 検索結果などから指定されたスロットまでスクロール
+            let slot_id = scenes
+                .get(*selected_scene_index)
+                .and_then(|scene| scene.current_slots().get(slot_index))
+                .map(|slot| slot.id);
+            if scroll_to_slot.is_some() && *scroll_to_slot == slot_id {
+                row.response.scroll_to_me(Some(egui::Align::Center));
+                *scroll_to_slot = None;
+            }
+            ui.add_space(SLOT_SPACING);
+        }
+
+        // キャッシュを更新
+        if !cache_valid {
+            if let Some(scene_mut) = scenes.get_mut(*selected_scene_index) {
+                scene_mut.layout_cache = Some(LayoutCache {
+                    judge_width: max_judge_width,
+                    icon_width: max_icon_width,
+                    mode_id: scene_mut.mode_id,
+                });
             }
         }
 
-        // +ボタン（左余白を判定ボタン幅+アイコン幅に合わせる）
+        // +ボタン（左余白をハンドル幅+判定ボタン幅+アイコン幅に合わせる）
         show_add_slot_button(
             ui,
             scenes,
             selected_scene_index,
             modes,
             app_mode,
-            max_judge_width,
-            max_icon_width,
+            (max_judge_width, max_icon_width),
+            context.history,
         );
     });
+    action
+}
+
+/// スロットのハンドル（ドラッグで並べ替え、クリックでメニュー）
+/// コピーモードではコピーキューのカーソルを表示する
+#[expect(clippy::too_many_arguments)]
+fn show_slot_handle(
+    ui: &mut egui::Ui,
+    scenes: &[Scene],
+    selected_index: usize,
+    slot_index: usize,
+    slot_count: usize,
+    app_mode: &AppMode,
+    queue_cursor: Option<usize>,
+    action: &mut Option<SlotAction>,
+) {
+    let size = egui::vec2(SLOT_HANDLE_WIDTH, ui.spacing().interact_size.y);
+    let hidden = scenes
+        .get(selected_index)
+        .and_then(|scene| scene.current_slots().get(slot_index))
+        .is_some_and(|slot| slot.hidden);
+    match app_mode {
+        AppMode::Edit => {
+            let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+            let color = if response.hovered() || response.dragged() {
+                ui.visuals().strong_text_color()
+            } else {
+                ui.visuals().weak_text_color()
+            };
+            ui.painter().text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "☰",
+                egui::TextStyle::Button.resolve(ui.style()),
+                color,
+            );
+            response.dnd_set_drag_payload(SlotDrag(slot_index));
+            if response.dragged() {
+                ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+            }
+            let response = response.on_hover_text("ドラッグで並べ替え / クリックでメニュー");
+
+            egui::Popup::menu(&response).show(|ui| {
+                if ui.button("複製").clicked() {
+                    *action = Some(SlotAction::Duplicate(slot_index));
+                }
+                if ui
+                    .add_enabled(slot_index > 0, egui::Button::new("上へ移動"))
+                    .clicked()
+                {
+                    *action = Some(SlotAction::Move(slot_index, slot_index - 1));
+                }
+                if ui
+                    .add_enabled(slot_index + 1 < slot_count, egui::Button::new("下へ移動"))
+                    .clicked()
+                {
+                    *action = Some(SlotAction::Move(slot_index, slot_index + 1));
+                }
+                ui.menu_button("シーンへ移動", |ui| {
+                    for (index, scene) in scenes.iter().enumerate() {
+                        if index != selected_index && ui.button(&scene.title).clicked() {
+                            *action = Some(SlotAction::MoveToScene(slot_index, scene.id));
+                        }
+                    }
+                });
+                if ui
+                    .selectable_label(hidden, "結果を伏せてコピー")
+                    .on_hover_text("Discordではスポイラー、ccfoliaではシークレットダイスにする")
+                    .clicked()
+                {
+                    *action = Some(SlotAction::ToggleHidden(slot_index));
+                }
+                ui.separator();
+                if ui.button("削除...").clicked() {
+                    *action = Some(SlotAction::Delete(slot_index));
+                }
+            });
+        }
+        AppMode::Copy => {
+            let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+            let mark = if queue_cursor == Some(slot_index) {
+                Some(("▶", ui.visuals().selection.stroke.color))
+            } else {
+                hidden.then(|| ("🔒", ui.visuals().weak_text_color()))
+            };
+            if let Some((text, color)) = mark {
+                ui.painter().text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    text,
+                    egui::TextStyle::Button.resolve(ui.style()),
+                    color,
+                );
+            }
+        }
+    }
+}
+
+/// ドラッグ中のスロットをこの行に落とした場合の並べ替え
+fn handle_slot_drop(
+    ui: &egui::Ui,
+    row: &egui::Response,
+    slot_index: usize,
+    action: &mut Option<SlotAction>,
+) {
+    let Some(dragged) = row.dnd_hover_payload::<SlotDrag>() else {
+        return;
+    };
+    let from = dragged.0;
+    // 行の上半分なら前、下半分なら後ろに挿入
+    let pointer_y = ui
+        .ctx()
+        .pointer_interact_pos()
+        .map_or(row.rect.center().y, |pos| pos.y);
+    let insert_below = pointer_y > row.rect.center().y;
+    let line_y = if insert_below {
+        row.rect.bottom() + SLOT_SPACING / 2.0
+    } else {
+        row.rect.top() - SLOT_SPACING / 2.0
+    };
+    ui.painter()
+        .hline(row.rect.x_range(), line_y, ui.visuals().selection.stroke);
+
+    if row.dnd_release_payload::<SlotDrag>().is_some() {
+        let insert_at = slot_index + usize::from(insert_below);
+        // 取り出した後の位置に変換
+        let to = if insert_at > from {
+            insert_at - 1
+        } else {
+            insert_at
+        };
+        *action = Some(SlotAction::Move(from, to));
+    }
 }
 
 /// 1つのスロットを表示（判定ボタン + アイコン + テキストエディタ）
 /// 返り値: (判定ボタン幅, アイコンエリア幅)
+#[expect(clippy::too_many_arguments)]
 fn show_slot(
     ui: &mut egui::Ui,
     modes: &[Mode],
     scenes: &mut [Scene],
-    selected_index: &mut usize,
+    selected_index: &usize,
     slot_index: usize,
     app_mode: &AppMode,
     toasts: &mut egui_notify::Toasts,
+    bindings: Bindings<'_>,
+    icons: &mut IconCache,
+    context: &mut SlotContext<'_>,
 ) -> (f32, f32) {
     let mut judge_width = 0.0;
     let mut icon_width = 0.0;
@@ -270,21 +628,51 @@ fn show_slot(
         let text_height = calc_height_from_buttons(ui, modes, scenes, *selected_index);
 
         // このスロット用の判定ボタン
-        let judge_response =
-            show_judge_buttons_for_slot(ui, modes, scenes, selected_index, slot_index, text_height);
+        let judge_response = show_judge_buttons_for_slot(
+            ui,
+            modes,
+            scenes,
+            selected_index,
+            slot_index,
+            text_height,
+            app_mode,
+            bindings,
+            toasts,
+            context,
+        );
         judge_width = judge_response.rect.width();
 
         // アイコン/名前のエリア
         let icon_response = ui.allocate_ui_with_layout(
-            egui::vec2(0.0, text_height),
-            egui::Layout::top_down(egui::Align::Min),
+            egui::vec2(ICON_AREA_WIDTH, text_height),
+            egui::Layout::top_down(egui::Align::Center),
             |ui| {
-                show_player_icon(ui, scenes);
-                show_player_name(ui, scenes);
+                ui.set_width(ICON_AREA_WIDTH);
+                let Some(scene) = scenes.get_mut(*selected_index) else {
+                    return;
+                };
+                let scene_id = scene.id;
+                if let Some(slot) = scene.current_slots_mut().get_mut(slot_index) {
+                    if let Some(before) =
+                        show_player_icon(ui, slot, bindings.players, icons, app_mode)
+                    {
+                        context.history.push(
+                            "話者の変更",
+                            Change::Slot {
+                                scene: scene_id,
+                                slot: Box::new(before),
+                            },
+                        );
+                    }
+                    show_player_name(ui, slot, bindings.players);
+                }
             },
         );
         icon_width = icon_response.response.rect.width();
 
+        let mode = scenes
+            .get(*selected_index)
+            .and_then(|scene| find_mode(modes, scene.mode_id));
         show_text_editor_for_slot(
             ui,
             scenes,
@@ -293,6 +681,9 @@ fn show_slot(
             text_height,
             app_mode,
             toasts,
+            bindings,
+            mode,
+            context,
         );
     });
 
@@ -300,16 +691,21 @@ fn show_slot(
 }
 
 /// +ボタン（スロット追加）
+/// `widths`は左に空ける（判定ボタン幅, アイコンエリア幅）
 fn show_add_slot_button(
     ui: &mut egui::Ui,
-    scenes: &mut Vec<Scene>,
-    selected_scene_index: &mut usize,
+    scenes: &mut [Scene],
+    selected_scene_index: &usize,
     modes: &[Mode],
     app_mode: &AppMode,
-    judge_width: f32,
-    icon_width: f32,
+    widths: (f32, f32),
+    history: &mut History,
 ) {
+    let (judge_width, icon_width) = widths;
     ui.horizontal(|ui| {
+        // ハンドルと同じ幅を確保
+        ui.allocate_space(egui::vec2(SLOT_HANDLE_WIDTH, 0.0));
+
         // 判定ボタンと同じ幅を確保
         ui.allocate_space(egui::vec2(judge_width, 0.0));
 
@@ -317,12 +713,13 @@ fn show_add_slot_button(
         ui.allocate_space(egui::vec2(icon_width, 0.0));
 
         if let Some(scene) = scenes.get_mut(*selected_scene_index) {
-            show_add_textbox_button(ui, scene, modes, app_mode);
+            show_add_textbox_button(ui, scene, modes, app_mode, history);
         }
     });
 }
 
-/// 判定ボタン群（大成功/成功/失敗/ファンブルなど） - 特定スロット用
+/// 判定ボタン群（大成功/成功/失敗/ファンブルなど） + 技能判定 - 特定スロット用
+#[expect(clippy::too_many_arguments)]
 fn show_judge_buttons_for_slot(
     ui: &mut egui::Ui,
     modes: &[Mode],
@@ -330,65 +727,314 @@ fn show_judge_buttons_for_slot(
     selected_index: &usize,
     slot_index: usize,
     text_height: f32,
+    app_mode: &AppMode,
+    bindings: Bindings<'_>,
+    toasts: &mut egui_notify::Toasts,
+    context: &mut SlotContext<'_>,
 ) -> egui::Response {
     ui.allocate_ui_with_layout(
         egui::vec2(JUDGE_BUTTON_WIDTH, text_height),
         egui::Layout::top_down(egui::Align::Min),
         |ui| {
             // 借用エラー回避のため先に必要な値を取得
-            let (mode_index, selected_judge_index) =
-                if let Some(scene) = scenes.get(*selected_index) {
-                    let judge_idx = scene
-                        .contents
-                        .get(scene.mode_index)
-                        .and_then(|slots| slots.get(slot_index))
-                        .map(|slot| slot.selected_judge_index)
-                        .unwrap_or_else(|| {
-                            eprintln!("Warning: slot not found, defaulting to 0");
-                            0
-                        });
-                    (scene.mode_index, judge_idx)
-                } else {
-                    return;
-                };
+            let (mode_id, selected_judge) = if let Some(scene) = scenes.get(*selected_index) {
+                let judge_id = scene
+                    .current_slots()
+                    .get(slot_index)
+                    .map(|slot| slot.selected_judge)
+                    .unwrap_or_else(|| {
+                        log::warn!("スロットが見つかりません（判定なしとして扱います）");
+                        Id::default()
+                    });
+                (scene.mode_id, judge_id)
+            } else {
+                return;
+            };
 
-            if let Some(mode) = modes.get(mode_index) {
-                for (index, judge) in mode.judges.iter().enumerate() {
-                    let is_selected = selected_judge_index == index;
+            if let Some(mode) = find_mode(modes, mode_id) {
+                for judge in &mode.judges {
+                    let is_selected = selected_judge == judge.id;
                     // 選択中のボタンは青色でハイライト
                     let button = if is_selected {
-                        egui::Button::new(judge).fill(egui::Color32::from_rgb(70, 130, 180))
+                        egui::Button::new(&judge.name).fill(egui::Color32::from_rgb(70, 130, 180))
                     } else {
-                        egui::Button::new(judge)
+                        egui::Button::new(&judge.name)
                     };
 
                     if ui.add(button).clicked() {
                         if let Some(scene_mut) = scenes.get_mut(*selected_index) {
-                            if let Some(slot) = scene_mut
-                                .contents
-                                .get_mut(mode_index)
-                                .and_then(|slots| slots.get_mut(slot_index))
-                            {
-                                slot.selected_judge_index = index;
+                            if let Some(slot) = scene_mut.slots_mut(mode_id).get_mut(slot_index) {
+                                slot.selected_judge = judge.id;
                             }
                         }
                     }
                 }
+
+                if let Some(scene_mut) = scenes.get_mut(*selected_index) {
+                    let scene_id = scene_mut.id;
+                    let before =
+                        scene_mut
+                            .slots_mut(mode_id)
+                            .get_mut(slot_index)
+                            .and_then(|slot| {
+                                show_skill_check(
+                                    ui, mode, slot, app_mode, bindings, toasts, context,
+                                )
+                            });
+                    // ボタンの表示が変わるので幅を測り直す
+                    if let Some(before) = before {
+                        scene_mut.layout_cache = None;
+                        context.history.push(
+                            "技能判定の変更",
+                            Change::Slot {
+                                scene: scene_id,
+                                slot: Box::new(before),
+                            },
+                        );
+                    }
+                }
             }
         },
     )
     .response
 }
 
-fn show_player_icon(ui: &mut egui::Ui, scenes: &[Scene]) {
-    //todo!()
+/// スロットの技能判定（編集モードでは設定、コピーモードではロールまたは出目の入力）
+/// 返り値: 技能判定の設定を変えた場合は変える前のスロット
+fn show_skill_check(
+    ui: &mut egui::Ui,
+    mode: &Mode,
+    slot: &mut scene::TextSlot,
+    app_mode: &AppMode,
+    bindings: Bindings<'_>,
+    toasts: &mut egui_notify::Toasts,
+    context: &mut SlotContext<'_>,
+) -> Option<scene::TextSlot> {
+    match app_mode {
+        AppMode::Edit => {
+            let label = slot.check.as_ref().map_or_else(
+                || String::from("🎲"),
+                |skill| format!("🎲 {}", skill.label()),
+            );
+            let response = ui.small_button(label).on_hover_text("技能判定を設定");
+            egui::Popup::menu(&response)
+                .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside)
+                .show(|ui| {
+                    let before = slot.clone();
+                    show_skill_check_menu(ui, slot, context.system);
+                    (slot.check != before.check).then_some(before)
+                })?
+                .inner
+        }
+        AppMode::Copy => {
+            let skill = slot.check.clone()?;
+            let command = Command::skill_check(context.system, &skill);
+            let rolled = ui
+                .button(format!("🎲 {}", skill.label()))
+                .on_hover_text(format!("{command}を振る"))
+                .clicked()
+                .then(|| {
+                    let roll = command.roll(context.system, context.rng);
+                    toasts
+                        .info(
+                            format!("{} {}", skill.skill, roll.text())
+                                .trim_start()
+                                .to_owned(),
+                        )
+                        .duration(Some(std::time::Duration::from_secs(4)));
+                    roll.verdict
+                })
+                .flatten();
+            let entered = show_result_input(ui, slot.id, &command, context.system, toasts);
+
+            if let Some(verdict) = rolled.or(entered) {
+                apply_verdict(ui, mode, slot, verdict, bindings, toasts, context);
+            }
+            None
+        }
+    }
+}
+
+/// 手元で振った出目の入力欄（Enterで判定）
+fn show_result_input(
+    ui: &mut egui::Ui,
+    slot_id: Id,
+    command: &Command,
+    system: GameSystem,
+    toasts: &mut egui_notify::Toasts,
+) -> Option<Verdict> {
+    let id = ui.id().with(("skill_check_result", slot_id));
+    let mut result: String = ui.data_mut(|data| data.get_temp(id)).unwrap_or_default();
+    // エモクロアTRPGは出目ではなく成功数を入力する
+    let hint = if matches!(command, Command::Pool { .. }) {
+        "成功数"
+    } else {
+        "出目"
+    };
+    let response = ui.add(
+        egui::TextEdit::singleline(&mut result)
+            .hint_text(hint)
+            .desired_width(JUDGE_BUTTON_WIDTH),
+    );
+    let mut verdict = None;
+    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+        let folded: String = result.trim().chars().map(fold_width).collect();
+        match folded.parse::<i64>() {
+            Ok(total) => verdict = command.verdict_for(total, system),
+            Err(_) => {
+                toasts.error("出目は数値で入力してください");
+            }
+        }
+        result.clear();
+    }
+    ui.data_mut(|data| data.insert_temp(id, result));
+    verdict
+}
+
+/// 技能判定の設定メニュー（技能名 + 目標値、エモクロアTRPGはダイス数も）
+fn show_skill_check_menu(ui: &mut egui::Ui, slot: &mut scene::TextSlot, system: GameSystem) {
+    // メニューを開いただけでは技能判定を追加しない
+    let mut skill = slot.check.clone().unwrap_or_default();
+    let mut changed = false;
+    egui::Grid::new("skill_check_grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("技能名");
+            changed |= ui
+                .add(
+                    egui::TextEdit::singleline(&mut skill.skill)
+                        .hint_text("目星")
+                        .desired_width(100.0),
+                )
+                .changed();
+            ui.end_row();
+            ui.label("目標値");
+            changed |= ui
+                .add(egui::DragValue::new(&mut skill.target).range(0..=999))
+                .changed();
+            ui.end_row();
+            if system == GameSystem::Emoklore {
+                ui.label("ダイス数");
+                changed |= ui
+                    .add(egui::DragValue::new(&mut skill.dice).range(1..=10))
+                    .changed();
+                ui.end_row();
+            }
+        });
+    if changed {
+        slot.check = Some(skill);
+    }
+    ui.separator();
+    if ui
+        .add_enabled(slot.check.is_some(), egui::Button::new("技能判定を外す"))
+        .clicked()
+    {
+        slot.check = None;
+        ui.close();
+    }
 }
 
-fn show_player_name(ui: &mut egui::Ui, scenes: &[Scene]) {
-    //todo!()
+/// 判定結果に対応する判定を選び、設定によってはそのテキストをコピーする
+fn apply_verdict(
+    ui: &egui::Ui,
+    mode: &Mode,
+    slot: &mut scene::TextSlot,
+    verdict: Verdict,
+    bindings: Bindings<'_>,
+    toasts: &mut egui_notify::Toasts,
+    context: &mut SlotContext<'_>,
+) {
+    let copy = context.copy;
+    let Some(judge) = verdict.judge(mode) else {
+        toasts.warning(format!("「{}」に対応する判定がありません", verdict.name));
+        return;
+    };
+    slot.selected_judge = judge;
+    if copy.after_check {
+        let resolved = message::format_slot(copy.target, slot, judge, Some(mode), bindings);
+        ui.ctx().copy_text(resolved.text.into_owned());
+        if let Some(revealed) = Revealed::from_slot(slot, judge, bindings) {
+            context.revealed = Some(revealed);
+        }
+        toasts
+            .success(format!("{}のテキストをコピーしました", verdict.name))
+            .duration(Some(std::time::Duration::from_secs(2)));
+    }
+}
+
+/// 話者のアイコン（編集モードではクリックで話者を選択）
+/// 返り値: 話者を変えた場合は変える前のスロット
+fn show_player_icon(
+    ui: &mut egui::Ui,
+    slot: &mut scene::TextSlot,
+    players: &[Player],
+    icons: &mut IconCache,
+    app_mode: &AppMode,
+) -> Option<scene::TextSlot> {
+    let response = match slot.speaker(players) {
+        Some(speaker) => icon::show_icon(ui, icons, speaker.icon_path, speaker.name, ICON_SIZE),
+        None => icon::show_icon(ui, icons, None, "", ICON_SIZE),
+    };
+
+    match app_mode {
+        AppMode::Edit => {
+            let response = response.on_hover_text("クリックで話者を選択");
+            egui::Popup::menu(&response)
+                .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside)
+                .show(|ui| {
+                    let before = slot.clone();
+                    show_speaker_menu(ui, slot, players);
+                    (slot.speaker != before.speaker || slot.label != before.label).then_some(before)
+                })?
+                .inner
+        }
+        AppMode::Copy => None,
+    }
+}
+
+/// 話者の選択メニュー（プレイヤー一覧 + カスタムラベル）
+fn show_speaker_menu(ui: &mut egui::Ui, slot: &mut scene::TextSlot, players: &[Player]) {
+    if ui
+        .selectable_label(slot.speaker.is_none() && slot.label.is_none(), "なし")
+        .clicked()
+    {
+        slot.speaker = None;
+        slot.label = None;
+        ui.close();
+    }
+    for player in players {
+        if ui
+            .selectable_label(slot.speaker == Some(player.id), &player.name)
+            .clicked()
+        {
+            slot.speaker = Some(player.id);
+            ui.close();
+        }
+    }
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.label("カスタム:");
+        let mut label = slot.label.clone().unwrap_or_default();
+        if ui
+            .add(egui::TextEdit::singleline(&mut label).desired_width(100.0))
+            .changed()
+        {
+            // カスタムラベルを入力したらプレイヤーの割り当ては外す
+            slot.speaker = None;
+            slot.label = (!label.is_empty()).then_some(label);
+        }
+    });
+}
+
+/// 話者の名前（アイコンの下）
+fn show_player_name(ui: &mut egui::Ui, slot: &scene::TextSlot, players: &[Player]) {
+    if let Some(speaker) = slot.speaker(players) {
+        ui.add(egui::Label::new(egui::RichText::new(speaker.name).small()).truncate());
+    }
 }
 
 /// テキストエディタ（マルチライン） - 指定されたスロット用
+#[expect(clippy::too_many_arguments)]
 fn show_text_editor_for_slot(
     ui: &mut egui::Ui,
     scenes: &mut [Scene],
@@ -397,57 +1043,230 @@ fn show_text_editor_for_slot(
     text_height: f32,
     app_mode: &AppMode,
     toasts: &mut egui_notify::Toasts,
+    bindings: Bindings<'_>,
+    mode: Option<&Mode>,
+    context: &mut SlotContext<'_>,
 ) {
+    let copy = context.copy;
     if let Some(scene) = scenes.get_mut(*selected_index) {
-        let mode_index = scene.mode_index;
-
-        // このスロットのselected_judge_indexを取得
-        let judge_index = scene
-            .contents
-            .get(mode_index)
-            .and_then(|slots| slots.get(slot_index))
-            .map(|slot| slot.selected_judge_index)
-            .unwrap_or(0);
-
+        let scene_id = scene.id;
         // テキストを取得: contents[mode][slot].texts[judge]
-        if let Some(content) = scene
-            .contents
-            .get_mut(mode_index)
-            .and_then(|slots| slots.get_mut(slot_index))
-            .and_then(|slot| slot.texts.get_mut(judge_index))
-        {
-            match app_mode {
+        if let Some(slot) = scene.current_slots_mut().get_mut(slot_index) {
+            ui.vertical(|ui| match app_mode {
                 AppMode::Edit => {
-                    ui.add_sized(
+                    let slot_id = slot.id;
+                    let judge = slot.selected_judge;
+                    let content = slot.text_mut(judge);
+                    // 入力できるのはフォーカス中の欄だけなので、その欄の変更前の値だけを取っておく
+                    let id = egui::Id::new(("slot_text", slot_id, judge));
+                    let before = ui.memory(|m| m.has_focus(id)).then(|| content.clone());
+                    let response = ui.add_sized(
                         [ui.available_width(), text_height],
-                        egui::TextEdit::multiline(content),
+                        egui::TextEdit::multiline(content).id(id),
                     );
+                    if response.changed() {
+                        if let Some(text) = before {
+                            context.history.push(
+                                "テキストの編集",
+                                Change::Text {
+                                    scene: scene_id,
+                                    slot: slot_id,
+                                    judge,
+                                    text,
+                                },
+                            );
+                        }
+                    }
+                    if response.has_focus() || response.clicked() {
+                        context.focused = Some(slot_id);
+                    }
+                    // 入力中も置き換え後の文字列を確認できるようにする
+                    let resolved = placeholder::resolve(content, bindings);
+                    if resolved.text != content.as_str() {
+                        show_resolved_preview(ui, &resolved);
+                    }
+                    show_undefined_warning(ui, &resolved);
                 }
                 AppMode::Copy => {
-                    let mut dummy = content.clone();
+                    // コピー先に合わせて整形した文字列を表示してコピーする
+                    let resolved = message::format_slot(
+                        copy.target,
+                        slot,
+                        slot.selected_judge,
+                        mode,
+                        bindings,
+                    );
+                    let mut dummy = resolved.text.clone().into_owned();
                     let response = ui.add_sized(
                         [ui.available_width(), text_height],
                         egui::TextEdit::multiline(&mut dummy).desired_width(f32::INFINITY),
                     );
                     // dummyは捨てる（元のcontentは変更されない）
 
+                    if response.has_focus() || response.clicked() {
+                        context.focused = Some(slot.id);
+                    }
                     if response.clicked() {
-                        ui.ctx().copy_text(content.clone());
-                        toasts
-                            .success("コピーしました")
-                            .duration(Some(std::time::Duration::from_secs(2)));
+                        if let Some(revealed) =
+                            Revealed::from_slot(slot, slot.selected_judge, bindings)
+                        {
+                            context.revealed = Some(revealed);
+                        }
                     }
+
+                    // 分割する場合はクリックするたびに続きをコピーする
+                    let chunks = split_chunks(copy, slot, mode, bindings);
+                    if response.clicked() {
+                        copy_resolved(ui.ctx(), slot.id, chunks.as_deref(), &resolved, toasts);
+                    }
+                    if let Some(chunks) = &chunks {
+                        show_split_copy(ui, slot.id, chunks);
+                    }
+                    show_undefined_warning(ui, &resolved);
                 }
-            }
+            });
         }
     }
 }
 
+/// スロットをコピー先に合わせて整形してコピーする（スロットのコピーボタンやショートカットキーと同じ動作）
+///
+/// 分割する場合はコピーするたびに続きの部分をコピーする。
+pub fn copy_slot(
+    ctx: &egui::Context,
+    copy: CopySettings,
+    slot: &scene::TextSlot,
+    mode: Option<&Mode>,
+    bindings: Bindings<'_>,
+    toasts: &mut egui_notify::Toasts,
+) {
+    let resolved = message::format_slot(copy.target, slot, slot.selected_judge, mode, bindings);
+    let chunks = split_chunks(copy, slot, mode, bindings);
+    copy_resolved(ctx, slot.id, chunks.as_deref(), &resolved, toasts);
+}
+
+/// 分割コピーする部分（分割しない設定、または1つに収まる場合はNone）
+fn split_chunks(
+    copy: CopySettings,
+    slot: &scene::TextSlot,
+    mode: Option<&Mode>,
+    bindings: Bindings<'_>,
+) -> Option<Vec<String>> {
+    copy.split_limit()
+        .map(|limit| {
+            message::format_slot_split(
+                copy.target,
+                slot,
+                slot.selected_judge,
+                mode,
+                bindings,
+                limit,
+            )
+        })
+        .filter(|chunks| chunks.len() > 1)
+}
+
+/// 分割コピーで最後にコピーした部分の位置を記録するID（画面上の位置によらずスロットごと）
+fn split_copy_id(slot_id: Id) -> egui::Id {
+    egui::Id::new(("split_copy", slot_id))
+}
+
+/// 分割コピーで最後にコピーした部分の位置
+fn split_copied(ctx: &egui::Context, slot_id: Id, chunks: &[String]) -> Option<usize> {
+    ctx.data_mut(|data| data.get_temp(split_copy_id(slot_id)))
+        .filter(|&index| index < chunks.len())
+}
+
+/// 整形した文字列をコピーする（`chunks`があれば前回の続きの部分をコピーする）
+fn copy_resolved(
+    ctx: &egui::Context,
+    slot_id: Id,
+    chunks: Option<&[String]>,
+    resolved: &Resolved<'_>,
+    toasts: &mut egui_notify::Toasts,
+) {
+    let Some(chunks) = chunks else {
+        ctx.copy_text(resolved.text.clone().into_owned());
+        copied_toast(toasts, "コピーしました", resolved);
+        return;
+    };
+    let next = split_copied(ctx, slot_id, chunks).map_or(0, |index| (index + 1) % chunks.len());
+    if let Some(chunk) = chunks.get(next) {
+        ctx.copy_text(chunk.clone());
+        copied_toast(
+            toasts,
+            &format!("{}/{}をコピーしました", next + 1, chunks.len()),
+            resolved,
+        );
+        ctx.data_mut(|data| data.insert_temp(split_copy_id(slot_id), next));
+    }
+}
+
+/// 分割コピーの状態（何番目をコピーしたか）と、最初からに戻すボタン
+fn show_split_copy(ui: &mut egui::Ui, slot_id: Id, chunks: &[String]) {
+    let copied = split_copied(ui.ctx(), slot_id, chunks);
+    ui.horizontal(|ui| {
+        match copied {
+            Some(index) => ui.label(format!(
+                "✂ {}/{}をコピー済み（クリックで次）",
+                index + 1,
+                chunks.len()
+            )),
+            None => ui.weak(format!("✂ {}分割（クリックで1つ目をコピー）", chunks.len())),
+        };
+        if copied.is_some() && ui.small_button("最初から").clicked() {
+            ui.data_mut(|data| data.remove::<usize>(split_copy_id(slot_id)));
+        }
+    });
+}
+
+/// コピーした通知（未定義の変数があれば警告）
+fn copied_toast(toasts: &mut egui_notify::Toasts, message: &str, resolved: &Resolved<'_>) {
+    if resolved.undefined.is_empty() {
+        toasts.success(message)
+    } else {
+        toasts.warning(format!(
+            "{message}（未定義の変数: {}）",
+            resolved.undefined.join("、")
+        ))
+    }
+    .duration(Some(std::time::Duration::from_secs(2)));
+}
+
+/// 置き換え後の文字列のプレビュー（1行目のみ、全文はホバーで表示）
+fn show_resolved_preview(ui: &mut egui::Ui, resolved: &Resolved<'_>) {
+    let first_line = resolved.text.lines().next().unwrap_or_default();
+    let preview = if resolved.text.contains('\n') {
+        format!("→ {first_line}…")
+    } else {
+        format!("→ {first_line}")
+    };
+    ui.add(egui::Label::new(egui::RichText::new(preview).weak()).truncate())
+        .on_hover_text(resolved.text.as_ref());
+}
+
+/// 未定義の変数の警告
+fn show_undefined_warning(ui: &mut egui::Ui, resolved: &Resolved<'_>) {
+    if resolved.undefined.is_empty() {
+        return;
+    }
+    let names: Vec<String> = resolved
+        .undefined
+        .iter()
+        .map(|name| format!("{{{name}}}"))
+        .collect();
+    ui.colored_label(
+        ui.visuals().warn_fg_color,
+        format!("⚠ 未定義の変数: {}", names.join(" ")),
+    );
+}
+
 fn show_add_textbox_button(
     ui: &mut egui::Ui,
     scene: &mut Scene,
     modes: &[Mode],
     app_mode: &AppMode,
+    history: &mut History,
 ) {
     match app_mode {
         AppMode::Edit => {
@@ -460,16 +1279,16 @@ fn show_add_textbox_button(
                 .clicked()
             {
                 // 現在のモードにスロットを追加
-                let mode_index = scene.mode_index;
-                if let Some(mode) = modes.get(mode_index) {
-                    if let Some(mode_slots) = scene.contents.get_mut(mode_index) {
-                        // 新しいスロットを作成（各判定のテキストは空）
-                        let new_slot = scene::TextSlot::new_empty(mode.judges.len());
-                        mode_slots.push(new_slot);
-                    }
+                if let Some(mode) = find_mode(modes, scene.mode_id) {
+                    let slot = scene.push_slot(mode);
+                    history.push(
+                        "スロットの追加",
+                        Change::RemoveSlot {
+                            scene: scene.id,
+                            slot,
+                        },
+                    );
                 }
-                // スロット追加時はキャッシュを無効化（幅が変わる可能性がある）
-                scene.layout_cache = None;
             }
         }
 
@@ -485,7 +1304,7 @@ fn calc_height_from_buttons(
     selected_index: usize,
 ) -> f32 {
     if let Some(scene) = scenes.get(selected_index) {
-        if let Some(mode) = modes.get(scene.mode_index) {
+        if let Some(mode) = find_mode(modes, scene.mode_id) {
             let button_count = mode.judges.len() as f32;
             let button_height = ui.spacing().interact_size.y;
             let button_spacing = ui.spacing().item_spacing.y;
@@ -500,3 +1319,142 @@ fn calc_height_from_buttons(
         TEXTBOX_MIN_HEIGHT
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+
+    /// 各シーンの表示中のモードのスロットID
+    fn slot_ids(scenario: &Scenario) -> Vec<Vec<Id>> {
+        scenario
+            .scenes
+            .iter()
+            .map(|scene| scene.current_slots().iter().map(|slot| slot.id).collect())
+            .collect()
+    }
+
+    /// 履歴に記録された変更を確定する
+    fn commit(history: &mut History) {
+        let ctx = egui::Context::default();
+        let _output: egui::FullOutput = ctx.run(egui::RawInput::default(), |ctx| {
+            history.update(ctx);
+        });
+    }
+
+    #[test]
+    fn move_to_scene_appends_and_can_be_undone() {
+        let mut scenario = Scenario::default();
+        let mut modal = Modal::new();
+        let mut history = History::new();
+        let before = slot_ids(&scenario);
+        let target = scenario
+            .scenes
+            .get(2)
+            .map(|scene| scene.id)
+            .expect("3つ目のシーンがある");
+        let moved = before
+            .first()
+            .and_then(|slots| slots.first())
+            .copied()
+            .expect("先頭のスロットがある");
+
+        apply_slot_action(
+            &mut scenario.scenes,
+            0,
+            SlotAction::MoveToScene(0, target),
+            &mut modal,
+            &mut history,
+        );
+        let after = slot_ids(&scenario);
+        assert_eq!(
+            after.first().map(Vec::len),
+            before.first().map(|slots| slots.len() - 1),
+            "元のシーンから取り除く"
+        );
+        assert_eq!(
+            after.get(2).and_then(|slots| slots.last()),
+            Some(&moved),
+            "移動先の末尾に追加する"
+        );
+
+        commit(&mut history);
+        history.undo(&mut scenario);
+        assert_eq!(slot_ids(&scenario), before, "元に戻すと元の位置に戻る");
+    }
+
+    #[test]
+    fn move_to_missing_scene_keeps_slot() {
+        let mut scenario = Scenario::default();
+        let mut modal = Modal::new();
+        let mut history = History::new();
+        let before = slot_ids(&scenario);
+
+        apply_slot_action(
+            &mut scenario.scenes,
+            0,
+            SlotAction::MoveToScene(0, Id::new()),
+            &mut modal,
+            &mut history,
+        );
+        assert_eq!(
+            slot_ids(&scenario),
+            before,
+            "移動先がなければ元の位置のまま"
+        );
+        commit(&mut history);
+        assert!(history.undo_label().is_none(), "履歴にも記録しない");
+    }
+
+    #[test]
+    fn copy_slot_cycles_split_chunks() {
+        let mut scenario = Scenario::default();
+        let mode = scenario.modes.first().cloned().expect("モードがある");
+        let judge = mode
+            .judges
+            .first()
+            .map(|judge| judge.id)
+            .expect("判定がある");
+        let slot = scenario
+            .scenes
+            .first_mut()
+            .and_then(|scene| scene.slots_mut(mode.id).first_mut())
+            .expect("スロットがある");
+        *slot.text_mut(judge) = "一文目です。".repeat(10);
+        let copy = CopySettings {
+            split: true,
+            split_limit: 20,
+            ..CopySettings::default()
+        };
+        let bindings = Bindings {
+            players: &scenario.player,
+            variables: &scenario.variables,
+        };
+        let slot = scenario
+            .scenes
+            .first()
+            .and_then(|scene| scene.slots(mode.id).first())
+            .expect("スロットがある");
+        let chunks = split_chunks(copy, slot, Some(&mode), bindings).expect("分割される");
+        assert_eq!(chunks.len(), 4, "6文字の文を3つずつに分ける");
+
+        let ctx = egui::Context::default();
+        let mut toasts = egui_notify::Toasts::default();
+        let mut copied = Vec::new();
+        for _ in 0..5 {
+            copy_slot(&ctx, copy, slot, Some(&mode), bindings, &mut toasts);
+            copied.push(split_copied(&ctx, slot.id, &chunks));
+        }
+        assert_eq!(
+            copied,
+            [Some(0), Some(1), Some(2), Some(3), Some(0)],
+            "コピーするたびに続きの部分へ進み、最後の次は最初に戻る"
+        );
+
+        let unsplit = CopySettings::default();
+        assert!(
+            split_chunks(unsplit, slot, Some(&mode), bindings).is_none(),
+            "分割しない設定では分割しない"
+        );
+    }
+}
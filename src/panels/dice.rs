@@ -0,0 +1,95 @@
+use crate::parser::dice::{self, DiceRng};
+use crate::scene::Mode;
+use crate::system::GameSystem;
+use eframe::egui;
+
+/// 保持するロール履歴の最大数
+const MAX_LOG: usize = 50;
+
+/// ダイスロールウィンドウの状態
+#[derive(Default)]
+pub struct DiceState {
+    pub open: bool,
+    pub command: String,
+    /// 直前の入力の解釈エラー
+    error: Option<String>,
+    /// ロール履歴（新しい順）
+    log: Vec<LogEntry>,
+}
+
+/// ロール履歴の1件
+struct LogEntry {
+    text: String,
+    /// 振った時点のモードで対応した判定名
+    judge: Option<String>,
+}
+
+/// ダイスロールウィンドウ
+///
+/// 結果は現在のシーンのモード（`mode`）の判定に対応付けて表示する。
+pub fn show(
+    ctx: &egui::Context,
+    system: GameSystem,
+    mode: Option<&Mode>,
+    rng: &mut DiceRng,
+    state: &mut DiceState,
+) {
+    let mut open = state.open;
+    egui::Window::new("ダイスロール")
+        .open(&mut open)
+        .default_width(360.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut state.command)
+                        .hint_text("1D100<=65、CC(1)<=50、CCB<=40、2DM<=6、2D6+3")
+                        .desired_width(240.0),
+                );
+                let submitted =
+                    response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.button("振る").clicked() || submitted {
+                    roll(system, mode, rng, state);
+                    response.request_focus();
+                }
+            });
+            ui.weak(format!("システム: {}", system.name()));
+            if let Some(error) = &state.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            ui.separator();
+
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    for entry in &state.log {
+                        ui.label(&entry.text);
+                        if let Some(judge) = &entry.judge {
+                            ui.weak(format!("→ 判定: {judge}"));
+                        }
+                        ui.add_space(4.0);
+                    }
+                });
+        });
+    state.open = open;
+}
+
+fn roll(system: GameSystem, mode: Option<&Mode>, rng: &mut DiceRng, state: &mut DiceState) {
+    match dice::roll(&state.command, system, rng) {
+        Ok(roll) => {
+            let judge = mode.and_then(|mode| {
+                let judge_id = roll.verdict?.judge(mode)?;
+                mode.judge(judge_id).map(|judge| judge.name.clone())
+            });
+            state.log.insert(
+                0,
+                LogEntry {
+                    text: roll.text(),
+                    judge,
+                },
+            );
+            state.log.truncate(MAX_LOG);
+            state.error = None;
+        }
+        Err(err) => state.error = Some(err.to_string()),
+    }
+}
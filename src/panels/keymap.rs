@@ -0,0 +1,171 @@
+use crate::keymap::{self, Action, Keymap};
+use eframe::egui;
+
+/// ショートカットキー設定ウィンドウの状態
+#[derive(Default)]
+pub struct KeymapState {
+    pub open: bool,
+    /// キー入力を待っている操作
+    capturing: Option<Action>,
+}
+
+impl KeymapState {
+    /// キー入力を待っているか（その間はショートカットキーを実行しない）
+    pub fn is_capturing(&self) -> bool {
+        self.open && self.capturing.is_some()
+    }
+}
+
+/// ショートカットキー設定ウィンドウ
+/// 返り値: 割り当てを変更したか
+pub fn show(
+    ctx: &egui::Context,
+    keymap: &mut Keymap,
+    state: &mut KeymapState,
+    toasts: &mut egui_notify::Toasts,
+) -> bool {
+    let mut changed = false;
+    if let Some(action) = state.capturing {
+        changed |= capture(ctx, keymap, state, action, toasts);
+    }
+
+    let mut open = state.open;
+    egui::Window::new("ショートカットキー")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            egui::Grid::new("keymap_grid")
+                .num_columns(4)
+                .striped(true)
+                .show(ui, |ui| {
+                    for action in Action::ALL {
+                        changed |= show_row(ui, keymap, state, action);
+                        ui.end_row();
+                    }
+                });
+
+            ui.separator();
+            if ui.button("すべて既定に戻す").clicked() {
+                *keymap = Keymap::default();
+                state.capturing = None;
+                changed = true;
+            }
+            if let Some(path) = Keymap::path() {
+                ui.weak(format!("保存先: {}", path.display()));
+            }
+            ui.collapsing("変更できないキー", |ui| {
+                egui::Grid::new("fixed_keymap_grid")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (label, shortcut) in keymap::FIXED_SHORTCUTS {
+                            ui.label(label);
+                            ui.label(ui.ctx().format_shortcut(&shortcut));
+                            ui.end_row();
+                        }
+                    });
+            });
+        });
+    state.open = open;
+    if !state.open {
+        state.capturing = None;
+    }
+    changed
+}
+
+/// 操作1つ分の行（名前、キー、解除、既定）
+fn show_row(
+    ui: &mut egui::Ui,
+    keymap: &mut Keymap,
+    state: &mut KeymapState,
+    action: Action,
+) -> bool {
+    let mut changed = false;
+    ui.label(action.label());
+
+    let text = if state.capturing == Some(action) {
+        String::from("キーを押してください（Escで取り消し）")
+    } else {
+        keymap.shortcut(action).map_or_else(
+            || String::from("なし"),
+            |shortcut| ui.ctx().format_shortcut(&shortcut),
+        )
+    };
+    if ui
+        .add(egui::Button::new(text).min_size(egui::vec2(160.0, 0.0)))
+        .on_hover_text("クリックしてから割り当てるキーを押す")
+        .clicked()
+    {
+        state.capturing = Some(action);
+    }
+
+    if ui
+        .add_enabled(keymap.shortcut(action).is_some(), egui::Button::new("解除"))
+        .clicked()
+    {
+        keymap.set(action, None);
+        changed = true;
+    }
+    if ui
+        .add_enabled(
+            keymap.shortcut(action) != action.default_shortcut(),
+            egui::Button::new("既定"),
+        )
+        .clicked()
+    {
+        keymap.set(action, action.default_shortcut());
+        changed = true;
+    }
+    changed
+}
+
+/// 押されたキーを操作に割り当てる（修飾キーなしのEscは取り消し）
+fn capture(
+    ctx: &egui::Context,
+    keymap: &mut Keymap,
+    state: &mut KeymapState,
+    action: Action,
+    toasts: &mut egui_notify::Toasts,
+) -> bool {
+    let pressed = ctx.input_mut(|i| {
+        let pressed = i.events.iter().find_map(|event| match event {
+            egui::Event::Key {
+                key,
+                pressed: true,
+                repeat: false,
+                modifiers,
+                ..
+            } => Some((*key, *modifiers)),
+            _ => None,
+        });
+        if let Some((key, modifiers)) = pressed {
+            // 割り当てたキーでほかの操作が動かないよう入力を消費する
+            i.consume_key(modifiers, key);
+        }
+        pressed
+    });
+    let Some((key, modifiers)) = pressed else {
+        return false;
+    };
+    state.capturing = None;
+    if key == egui::Key::Escape && modifiers.is_none() {
+        return false;
+    }
+
+    let shortcut = keymap::shortcut_from(modifiers, key);
+    if let Some(fixed) = keymap::fixed_action(shortcut) {
+        toasts.error(format!(
+            "{}は「{fixed}」に使われているため割り当てられません",
+            ctx.format_shortcut(&shortcut)
+        ));
+        return false;
+    }
+    if let Some(displaced) = keymap.set(action, Some(shortcut)) {
+        toasts.warning(format!(
+            "{}は「{}」から外しました",
+            ctx.format_shortcut(&shortcut),
+            displaced.label()
+        ));
+    }
+    true
+}
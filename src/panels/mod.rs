@@ -1,3 +1,12 @@
 pub mod central;
+pub mod dice;
+pub mod keymap;
+pub mod mode_editor;
+pub mod palette;
+pub mod player_view;
+pub mod queue;
+pub mod roster;
+pub mod search;
 pub mod side;
 pub mod top;
+pub mod variables;
@@ -0,0 +1,286 @@
+use crate::history::{Change, History};
+use crate::scenario::Scenario;
+use crate::scene::Id;
+use eframe::egui;
+
+/// モード/判定編集ウィンドウの状態
+#[derive(Default)]
+pub struct ModeEditorState {
+    pub open: bool,
+    /// 判定を編集中のモード
+    pub selected_mode: Id,
+    /// 削除確認中の項目
+    pub pending_delete: Option<DeleteTarget>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DeleteTarget {
+    Mode(Id),
+    Judge(Id, Id),
+}
+
+/// ウィンドウ内で押された操作（描画後にまとめて適用する）
+enum Edit {
+    AddMode,
+    SwapModes(usize, usize),
+    AddJudge(Id),
+    SwapJudges(Id, usize, usize),
+    Remove(DeleteTarget),
+}
+
+/// モード/判定編集ウィンドウ（変更は`history`に記録する）
+pub fn show(
+    ctx: &egui::Context,
+    scenario: &mut Scenario,
+    state: &mut ModeEditorState,
+    history: &mut History,
+) {
+    let mut open = state.open;
+    let mut edit = None;
+    // 名前などの入力欄の変更を記録するため、表示前のモード一覧を取っておく
+    let before = scenario.modes.clone();
+
+    egui::Window::new("モードと判定の編集")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.horizontal_top(|ui| {
+                ui.vertical(|ui| {
+                    ui.strong("モード");
+                    show_mode_list(ui, scenario, state, &mut edit);
+                });
+                ui.separator();
+                ui.vertical(|ui| {
+                    show_judge_list(ui, scenario, state, &mut edit);
+                });
+            });
+
+            if let Some(target) = state.pending_delete {
+                ui.separator();
+                show_delete_confirm(ui, scenario, state, target, &mut edit);
+            }
+        });
+    state.open = open;
+
+    if scenario.modes != before {
+        history.push(
+            "モードの編集",
+            Change::Modes {
+                system: scenario.system,
+                modes: before,
+                contents: Vec::new(),
+            },
+        );
+    }
+
+    let Some(edit) = edit else {
+        return;
+    };
+    // 判定が変わるモードのスロットは形が変わるので、戻せるよう取っておく
+    let changed_mode = match edit {
+        Edit::AddJudge(mode_id)
+        | Edit::Remove(DeleteTarget::Mode(mode_id) | DeleteTarget::Judge(mode_id, _)) => {
+            vec![mode_id]
+        }
+        Edit::AddMode | Edit::SwapModes(..) | Edit::SwapJudges(..) => Vec::new(),
+    };
+    let undo = Change::modes(scenario, &changed_mode);
+    let label = match edit {
+        Edit::AddMode => {
+            state.selected_mode = scenario.add_mode(format!("モード{}", scenario.modes.len() + 1));
+            "モードの追加"
+        }
+        Edit::SwapModes(a, b) => {
+            scenario.swap_modes(a, b);
+            "モードの並べ替え"
+        }
+        Edit::AddJudge(mode_id) => {
+            let judge_count = scenario.mode(mode_id).map_or(0, |mode| mode.judges.len());
+            scenario.add_judge(mode_id, format!("判定{}", judge_count + 1));
+            "判定の追加"
+        }
+        Edit::SwapJudges(mode_id, a, b) => {
+            scenario.swap_judges(mode_id, a, b);
+            "判定の並べ替え"
+        }
+        Edit::Remove(DeleteTarget::Mode(mode_id)) => {
+            scenario.remove_mode(mode_id);
+            "モードの削除"
+        }
+        Edit::Remove(DeleteTarget::Judge(mode_id, judge_id)) => {
+            scenario.remove_judge(mode_id, judge_id);
+            "判定の削除"
+        }
+    };
+    history.push(label, undo);
+}
+
+/// モード一覧（名前の編集、並べ替え、削除）
+fn show_mode_list(
+    ui: &mut egui::Ui,
+    scenario: &mut Scenario,
+    state: &mut ModeEditorState,
+    edit: &mut Option<Edit>,
+) {
+    let mode_count = scenario.modes.len();
+    for (index, mode) in scenario.modes.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            if ui
+                .radio(state.selected_mode == mode.id, "")
+                .on_hover_text("判定を編集")
+                .clicked()
+            {
+                state.selected_mode = mode.id;
+            }
+            ui.add(egui::TextEdit::singleline(&mut mode.name).desired_width(120.0));
+            if ui.add_enabled(index > 0, egui::Button::new("⏶")).clicked() {
+                *edit = Some(Edit::SwapModes(index, index - 1));
+            }
+            if ui
+                .add_enabled(index + 1 < mode_count, egui::Button::new("⏷"))
+                .clicked()
+            {
+                *edit = Some(Edit::SwapModes(index, index + 1));
+            }
+            if ui
+                .add_enabled(mode_count > 1, egui::Button::new("🗑"))
+                .clicked()
+            {
+                state.pending_delete = Some(DeleteTarget::Mode(mode.id));
+            }
+        });
+    }
+    if ui.button("+ モード追加").clicked() {
+        *edit = Some(Edit::AddMode);
+    }
+}
+
+/// 選択中のモードの判定一覧（名前の編集、並べ替え、削除）
+fn show_judge_list(
+    ui: &mut egui::Ui,
+    scenario: &mut Scenario,
+    state: &mut ModeEditorState,
+    edit: &mut Option<Edit>,
+) {
+    let Some(mode) = scenario
+        .modes
+        .iter_mut()
+        .find(|mode| mode.id == state.selected_mode)
+    else {
+        // 未選択または削除済みなら先頭のモードを選択する
+        if let Some(first) = scenario.modes.first() {
+            state.selected_mode = first.id;
+        }
+        return;
+    };
+    let mode_id = mode.id;
+
+    ui.strong(format!("「{}」の判定", mode.name));
+    let judge_count = mode.judges.len();
+    for (index, judge) in mode.judges.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut judge.name).desired_width(120.0));
+            if ui.add_enabled(index > 0, egui::Button::new("⏶")).clicked() {
+                *edit = Some(Edit::SwapJudges(mode_id, index, index - 1));
+            }
+            if ui
+                .add_enabled(index + 1 < judge_count, egui::Button::new("⏷"))
+                .clicked()
+            {
+                *edit = Some(Edit::SwapJudges(mode_id, index, index + 1));
+            }
+            if ui
+                .add_enabled(judge_count > 1, egui::Button::new("🗑"))
+                .clicked()
+            {
+                state.pending_delete = Some(DeleteTarget::Judge(mode_id, judge.id));
+            }
+        });
+    }
+    if ui.button("+ 判定追加").clicked() {
+        *edit = Some(Edit::AddJudge(mode_id));
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("新規シーンのスロット数:");
+        ui.add(egui::DragValue::new(&mut mode.default_text_num).range(0..=20));
+    });
+
+    ui.separator();
+    ui.strong("コピー時に前後に付ける文字列");
+    ui.weak("{話者}、{判定}（判定名）、{モード}（モード名）と変数が使えます");
+    egui::Grid::new("copy_template_grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("前:");
+            ui.add(
+                egui::TextEdit::multiline(&mut mode.copy_prefix)
+                    .hint_text("【{モード}】")
+                    .desired_rows(1)
+                    .desired_width(200.0),
+            );
+            ui.end_row();
+            ui.label("後:");
+            ui.add(
+                egui::TextEdit::multiline(&mut mode.copy_suffix)
+                    .desired_rows(1)
+                    .desired_width(200.0),
+            );
+            ui.end_row();
+        });
+}
+
+/// 削除確認（消えるテキストの数を表示）
+fn show_delete_confirm(
+    ui: &mut egui::Ui,
+    scenario: &Scenario,
+    state: &mut ModeEditorState,
+    target: DeleteTarget,
+    edit: &mut Option<Edit>,
+) {
+    let (name, lost_texts) = match target {
+        DeleteTarget::Mode(mode_id) => (
+            scenario.mode(mode_id).map(|mode| mode.name.clone()),
+            scenario
+                .scenes
+                .iter()
+                .flat_map(|scene| scene.slots(mode_id))
+                .flat_map(|slot| slot.texts.values())
+                .filter(|text| !text.trim().is_empty())
+                .count(),
+        ),
+        DeleteTarget::Judge(mode_id, judge_id) => (
+            scenario
+                .mode(mode_id)
+                .and_then(|mode| mode.judge(judge_id))
+                .map(|judge| judge.name.clone()),
+            scenario
+                .scenes
+                .iter()
+                .flat_map(|scene| scene.slots(mode_id))
+                .filter(|slot| !slot.text(judge_id).trim().is_empty())
+                .count(),
+        ),
+    };
+    let Some(name) = name else {
+        state.pending_delete = None;
+        return;
+    };
+
+    ui.label(format!("「{name}」を削除しますか？"));
+    if lost_texts > 0 {
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            format!("{lost_texts}件のテキストも削除されます"),
+        );
+    }
+    ui.horizontal(|ui| {
+        if ui.button("削除").clicked() {
+            *edit = Some(Edit::Remove(target));
+            state.pending_delete = None;
+        }
+        if ui.button("キャンセル").clicked() {
+            state.pending_delete = None;
+        }
+    });
+}
@@ -0,0 +1,95 @@
+use crate::export::ccfolia::{self, ChatPalette};
+use crate::scenario::Scenario;
+use eframe::egui;
+
+/// チャットパレットウィンドウの状態
+#[derive(Default)]
+pub struct PaletteState {
+    pub open: bool,
+    /// 選択中のキャラクターの位置
+    selected: usize,
+}
+
+/// チャットパレットウィンドウで選ばれた操作
+pub enum PaletteAction {
+    /// 1人分をクリップボードへコピー
+    Copy(ChatPalette),
+    /// 1人分をファイルに保存
+    Save(ChatPalette),
+    /// 全員分をフォルダに保存
+    SaveAll(Vec<ChatPalette>),
+}
+
+/// ccfoliaのチャットパレットの一覧（キャラクターごとにコピー/保存）
+pub fn show(
+    ctx: &egui::Context,
+    scenario: &Scenario,
+    state: &mut PaletteState,
+) -> Option<PaletteAction> {
+    let mut open = state.open;
+    let mut action = None;
+
+    egui::Window::new("ccfoliaチャットパレット")
+        .open(&mut open)
+        .default_width(520.0)
+        .show(ctx, |ui| {
+            let palettes = ccfolia::chat_palettes(scenario);
+            if palettes.is_empty() {
+                ui.weak("話者を設定したスロットがありません");
+                return;
+            }
+            state.selected = state.selected.min(palettes.len() - 1);
+
+            ui.horizontal_top(|ui| {
+                ui.vertical(|ui| {
+                    ui.set_width(140.0);
+                    egui::ScrollArea::vertical()
+                        .id_salt("palette_list")
+                        .max_height(360.0)
+                        .show(ui, |ui| {
+                            for (index, palette) in palettes.iter().enumerate() {
+                                let count = palette.commands.len() + palette.lines.len();
+                                ui.selectable_value(
+                                    &mut state.selected,
+                                    index,
+                                    format!("{}（{count}）", palette.name),
+                                );
+                            }
+                        });
+                });
+                ui.separator();
+                ui.vertical(|ui| {
+                    let Some(palette) = palettes.get(state.selected) else {
+                        return;
+                    };
+                    ui.horizontal(|ui| {
+                        if ui.button("📋 コピー").clicked() {
+                            action = Some(PaletteAction::Copy(palette.clone()));
+                        }
+                        if ui.button("保存...").clicked() {
+                            action = Some(PaletteAction::Save(palette.clone()));
+                        }
+                    });
+                    let mut text = palette.text();
+                    egui::ScrollArea::vertical()
+                        .id_salt("palette_text")
+                        .max_height(330.0)
+                        .show(ui, |ui| {
+                            // 表示のみ（編集しても元のシナリオは変わらない）
+                            ui.add(
+                                egui::TextEdit::multiline(&mut text).desired_width(f32::INFINITY),
+                            );
+                        });
+                });
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.weak("ccfoliaのキャラクターの「チャットパレット」欄に貼り付けます");
+                if ui.button("全員分を保存...").clicked() {
+                    action = Some(PaletteAction::SaveAll(palettes.clone()));
+                }
+            });
+        });
+    state.open = open;
+    action
+}
@@ -0,0 +1,186 @@
+use crate::constants::constants::{PLAYER_VIEW_FONT_SIZE, PLAYER_VIEW_ICON_SIZE};
+use crate::placeholder::{self, Bindings};
+use crate::scene::{Id, TextSlot};
+use crate::widgets::icon::{self, IconCache};
+use eframe::egui;
+use std::path::PathBuf;
+
+/// プレイヤー用ウィンドウの背景色
+const BACKGROUND: egui::Color32 = egui::Color32::from_gray(16);
+
+/// プレイヤー用ウィンドウの文字色
+const FOREGROUND: egui::Color32 = egui::Color32::from_gray(235);
+
+/// プレイヤー用ウィンドウ（配信や画面共有で見せる、公開したテキストだけの別ウィンドウ）の状態
+#[derive(Default)]
+pub struct PlayerViewState {
+    pub open: bool,
+    /// 表示中のテキスト（何も公開していなければNone）
+    revealed: Option<Revealed>,
+}
+
+impl PlayerViewState {
+    /// 表示するテキストを差し替える
+    pub fn reveal(&mut self, revealed: Revealed) {
+        self.revealed = Some(revealed);
+    }
+
+    /// 何も表示しない状態に戻す
+    pub fn clear(&mut self) {
+        self.revealed = None;
+    }
+}
+
+/// 公開したスロットの内容（コピーした時点の内容を保持し、後から編集しても変わらない）
+pub struct Revealed {
+    speaker: Option<String>,
+    icon_path: Option<PathBuf>,
+    /// プレースホルダーを置き換えた本文（コピー先の書式は付けない）
+    text: String,
+}
+
+impl Revealed {
+    /// スロットの判定のテキストを公開する内容
+    /// 結果を伏せるスロットと空のテキストは公開しない
+    pub fn from_slot(slot: &TextSlot, judge: Id, bindings: Bindings<'_>) -> Option<Self> {
+        if slot.hidden {
+            return None;
+        }
+        let resolved = placeholder::resolve(slot.text(judge), bindings);
+        let text = resolved.text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        let speaker = slot.speaker(bindings.players);
+        Some(Self {
+            speaker: speaker.as_ref().map(|speaker| speaker.name.to_owned()),
+            icon_path: speaker
+                .and_then(|speaker| speaker.icon_path)
+                .map(std::path::Path::to_path_buf),
+            text: text.to_owned(),
+        })
+    }
+}
+
+/// プレイヤー用ウィンドウ（別のOSウィンドウ、対応していない環境ではメインウィンドウ内）
+pub fn show(ctx: &egui::Context, state: &mut PlayerViewState, icons: &mut IconCache) {
+    ctx.show_viewport_immediate(
+        egui::ViewportId::from_hash_of("player_view"),
+        egui::ViewportBuilder::default()
+            .with_title("プレイヤー表示")
+            .with_inner_size([960.0, 540.0]),
+        |ctx, class| {
+            let frame = egui::Frame::NONE
+                .fill(BACKGROUND)
+                .inner_margin(PLAYER_VIEW_FONT_SIZE);
+            if class == egui::ViewportClass::Embedded {
+                let mut open = state.open;
+                egui::Window::new("プレイヤー表示")
+                    .open(&mut open)
+                    .frame(frame)
+                    .default_size([640.0, 360.0])
+                    .show(ctx, |ui| show_revealed(ui, state.revealed.as_ref(), icons));
+                state.open = open;
+            } else {
+                egui::CentralPanel::default()
+                    .frame(frame)
+                    .show(ctx, |ui| show_revealed(ui, state.revealed.as_ref(), icons));
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    state.open = false;
+                }
+            }
+        },
+    );
+}
+
+/// 公開中のテキスト（話者のアイコンと名前 + 本文）
+fn show_revealed(ui: &mut egui::Ui, revealed: Option<&Revealed>, icons: &mut IconCache) {
+    let Some(revealed) = revealed else {
+        return;
+    };
+    ui.horizontal_top(|ui| {
+        if let Some(speaker) = &revealed.speaker {
+            icon::show_icon(
+                ui,
+                icons,
+                revealed.icon_path.as_deref(),
+                speaker,
+                PLAYER_VIEW_ICON_SIZE,
+            );
+            ui.add_space(PLAYER_VIEW_FONT_SIZE * 0.5);
+        }
+        ui.vertical(|ui| {
+            if let Some(speaker) = &revealed.speaker {
+                ui.label(
+                    egui::RichText::new(speaker)
+                        .size(PLAYER_VIEW_FONT_SIZE * 0.8)
+                        .strong()
+                        .color(FOREGROUND),
+                );
+            }
+            egui::ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .show(ui, |ui| {
+                    ui.add(
+                        egui::Label::new(
+                            egui::RichText::new(&revealed.text)
+                                .size(PLAYER_VIEW_FONT_SIZE)
+                                .color(FOREGROUND),
+                        )
+                        .wrap(),
+                    );
+                });
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Judge;
+
+    fn slot(text: &str) -> (TextSlot, Id) {
+        let judges = [Judge::new(String::from("本文"))];
+        let mut slot = TextSlot::new_empty(&judges);
+        let judge = slot.selected_judge;
+        *slot.text_mut(judge) = String::from(text);
+        (slot, judge)
+    }
+
+    #[test]
+    fn hidden_or_empty_slots_are_not_revealed() {
+        let bindings = Bindings {
+            players: &[],
+            variables: &[],
+        };
+        let (mut hidden, judge) = slot("秘密");
+        hidden.hidden = true;
+        assert!(
+            Revealed::from_slot(&hidden, judge, bindings).is_none(),
+            "結果を伏せるスロット"
+        );
+        let (blank, judge) = slot("  \n ");
+        assert!(
+            Revealed::from_slot(&blank, judge, bindings).is_none(),
+            "空白だけのテキスト"
+        );
+    }
+
+    #[test]
+    fn revealed_text_resolves_placeholders_and_speaker() {
+        let players = crate::scenario::default_players();
+        let bindings = Bindings {
+            players: &players,
+            variables: &[],
+        };
+        let (mut slot, judge) = slot(" {PC1}が扉を開けた。\n");
+        slot.speaker = players.first().map(|player| player.id);
+        let revealed = Revealed::from_slot(&slot, judge, bindings).expect("公開できる");
+        assert_eq!(
+            revealed.text, "探偵が扉を開けた。",
+            "置き換えて前後の空白を除く"
+        );
+        assert_eq!(revealed.speaker.as_deref(), Some("地の文"), "話者の名前");
+        assert_eq!(revealed.icon_path, None, "アイコンのない話者");
+    }
+}
@@ -0,0 +1,230 @@
+use crate::scene::{Id, Scene, TextSlot};
+use eframe::egui;
+
+/// 次のスロットをコピーして進む
+pub const QUEUE_NEXT_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F9);
+
+/// 1つ前のスロットに戻る
+pub const QUEUE_BACK_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F8);
+
+/// コピーせずに次のスロットへ進む
+pub const QUEUE_SKIP_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::SHIFT, egui::Key::F9);
+
+/// コピーキュー（現在のシーンのスロットを上から順にコピーする）の状態
+#[derive(Default)]
+pub struct CopyQueue {
+    pub active: bool,
+    /// 次にコピーするスロットの位置
+    pub cursor: usize,
+    /// カーソルを合わせたシーンとモード（変わったら先頭に戻す）
+    origin: Option<(Id, Id)>,
+}
+
+/// キューの操作
+#[derive(Clone, Copy)]
+pub enum QueueStep {
+    /// カーソルのスロットをコピーして進む
+    Next,
+    /// 1つ戻る
+    Back,
+    /// コピーせずに進む
+    Skip,
+}
+
+impl CopyQueue {
+    /// 表示中のシーン/モードが変わっていればカーソルを先頭に戻す
+    pub fn sync(&mut self, scene: &Scene) {
+        let origin = Some((scene.id, scene.mode_id));
+        if self.origin != origin {
+            self.origin = origin;
+            self.cursor = 0;
+        }
+    }
+
+    /// カーソルの位置（キューが無効ならNone）
+    pub fn cursor(&self) -> Option<usize> {
+        self.active.then_some(self.cursor)
+    }
+
+    /// 操作を適用する（`format`はスロットからコピーする文字列を作る）
+    /// 返り値: 移動後のカーソルのスロット（スクロール用）
+    pub fn step(
+        &mut self,
+        step: QueueStep,
+        ctx: &egui::Context,
+        scene: &Scene,
+        format: &mut dyn FnMut(&TextSlot) -> String,
+        toasts: &mut egui_notify::Toasts,
+    ) -> Option<Id> {
+        let slots = scene.current_slots();
+        let (cursor, copied) = advance(step, self.cursor, slots);
+        self.cursor = cursor;
+        match copied.and_then(|index| Some((index, slots.get(index)?))) {
+            Some((index, slot)) => {
+                ctx.copy_text(format(slot));
+                toasts
+                    .success(format!("コピーしました（{}/{}）", index + 1, slots.len()))
+                    .duration(Some(std::time::Duration::from_secs(2)));
+            }
+            None if matches!(step, QueueStep::Next) => {
+                toasts.info("キューの最後です");
+                return None;
+            }
+            None => {}
+        }
+        slots.get(self.cursor).map(|slot| slot.id)
+    }
+}
+
+/// 操作後のカーソルの位置を求める
+/// 返り値: (移動後のカーソル, コピーするスロットの位置)
+fn advance(step: QueueStep, cursor: usize, slots: &[TextSlot]) -> (usize, Option<usize>) {
+    match step {
+        QueueStep::Next => {
+            // 空のスロットはコピーしても意味がないので飛ばす
+            slots
+                .iter()
+                .enumerate()
+                .skip(cursor)
+                .find(|(_, slot)| !slot.text(slot.selected_judge).trim().is_empty())
+                .map_or((slots.len(), None), |(index, _)| (index + 1, Some(index)))
+        }
+        QueueStep::Back => (cursor.saturating_sub(1), None),
+        QueueStep::Skip => ((cursor + 1).min(slots.len()), None),
+    }
+}
+
+/// ショートカットキーで選ばれた操作
+pub fn shortcut_step(ctx: &egui::Context) -> Option<QueueStep> {
+    ctx.input_mut(|i| {
+        // Shift+F9はF9にも一致するため先に判定する
+        if i.consume_shortcut(&QUEUE_SKIP_SHORTCUT) {
+            Some(QueueStep::Skip)
+        } else if i.consume_shortcut(&QUEUE_NEXT_SHORTCUT) {
+            Some(QueueStep::Next)
+        } else if i.consume_shortcut(&QUEUE_BACK_SHORTCUT) {
+            Some(QueueStep::Back)
+        } else {
+            None
+        }
+    })
+}
+
+/// キューの操作バー（オン/オフ、進み具合、戻る/コピー/スキップ）
+/// 返り値: 押された操作
+pub fn show_bar(ui: &mut egui::Ui, queue: &mut CopyQueue, slot_count: usize) -> Option<QueueStep> {
+    let mut step = None;
+    ui.horizontal(|ui| {
+        ui.toggle_value(&mut queue.active, "コピーキュー")
+            .on_hover_text("シーンのスロットを上から順にコピーする");
+        if !queue.active {
+            return;
+        }
+        ui.label(format!("{}/{}", queue.cursor.min(slot_count), slot_count));
+        let [back, next, skip] = [
+            QUEUE_BACK_SHORTCUT,
+            QUEUE_NEXT_SHORTCUT,
+            QUEUE_SKIP_SHORTCUT,
+        ]
+        .map(|shortcut| ui.ctx().format_shortcut(&shortcut));
+        if ui
+            .add_enabled(queue.cursor > 0, egui::Button::new("⏮ 戻る"))
+            .on_hover_text(back)
+            .clicked()
+        {
+            step = Some(QueueStep::Back);
+        }
+        let remaining = queue.cursor < slot_count;
+        if ui
+            .add_enabled(remaining, egui::Button::new("📋 コピーして次へ"))
+            .on_hover_text(next)
+            .clicked()
+        {
+            step = Some(QueueStep::Next);
+        }
+        if ui
+            .add_enabled(remaining, egui::Button::new("⏭ スキップ"))
+            .on_hover_text(skip)
+            .clicked()
+        {
+            step = Some(QueueStep::Skip);
+        }
+        if ui.button("先頭へ").clicked() {
+            queue.cursor = 0;
+        }
+    });
+    step
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Judge;
+
+    /// テキストを入れたスロット（空文字列は空のスロット）
+    fn slots(texts: &[&str]) -> Vec<TextSlot> {
+        let judges = [Judge::new(String::from("本文"))];
+        texts
+            .iter()
+            .map(|text| {
+                let mut slot = TextSlot::new_empty(&judges);
+                *slot.text_mut(slot.selected_judge) = String::from(*text);
+                slot
+            })
+            .collect()
+    }
+
+    #[test]
+    fn next_skips_empty_slots() {
+        let slots = slots(&["A", "", " \n", "D"]);
+        assert_eq!(
+            advance(QueueStep::Next, 0, &slots),
+            (1, Some(0)),
+            "カーソルのスロットをコピーして進む"
+        );
+        assert_eq!(
+            advance(QueueStep::Next, 1, &slots),
+            (4, Some(3)),
+            "空白だけのスロットも飛ばす"
+        );
+    }
+
+    #[test]
+    fn next_stops_at_the_end() {
+        let slots = slots(&["A", ""]);
+        assert_eq!(
+            advance(QueueStep::Next, 1, &slots),
+            (2, None),
+            "残りが空なら末尾で止まる"
+        );
+        assert_eq!(
+            advance(QueueStep::Next, 2, &slots),
+            (2, None),
+            "末尾からは進まない"
+        );
+        assert_eq!(
+            advance(QueueStep::Skip, 2, &slots),
+            (2, None),
+            "スキップも末尾で止まる"
+        );
+    }
+
+    #[test]
+    fn back_and_skip_move_one_slot() {
+        let slots = slots(&["A", "", "C"]);
+        assert_eq!(
+            advance(QueueStep::Back, 0, &slots),
+            (0, None),
+            "先頭からは戻らない"
+        );
+        assert_eq!(advance(QueueStep::Back, 2, &slots), (1, None), "1つ戻る");
+        assert_eq!(
+            advance(QueueStep::Skip, 0, &slots),
+            (1, None),
+            "空でなくてもコピーせずに進む"
+        );
+    }
+}
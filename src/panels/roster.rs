@@ -0,0 +1,160 @@
+use crate::app::Player;
+use crate::history::{Change, History};
+use crate::scene::Id;
+use eframe::egui;
+
+/// プレイヤー一覧の編集ウィンドウの状態
+#[derive(Default)]
+pub struct RosterState {
+    pub open: bool,
+    /// 削除確認中のプレイヤー
+    pub pending_delete: Option<Id>,
+}
+
+/// ウィンドウ内で押された操作（描画後にまとめて適用する）
+enum Edit {
+    Add,
+    Swap(usize, usize),
+    ClearIcon(Id),
+}
+
+/// プレイヤー一覧の編集ウィンドウ（変更は`history`に記録する）
+/// 返り値: アイコン画像の選択を求められたプレイヤーのID
+pub fn show(
+    ctx: &egui::Context,
+    players: &mut Vec<Player>,
+    state: &mut RosterState,
+    history: &mut History,
+) -> Option<Id> {
+    let before = players.clone();
+    let mut open = state.open;
+    let mut edit = None;
+    let mut icon_request = None;
+
+    egui::Window::new("プレイヤー一覧")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            show_player_list(ui, players, state, &mut edit, &mut icon_request);
+            if ui.button("+ プレイヤー追加").clicked() {
+                edit = Some(Edit::Add);
+            }
+
+            if let Some(player_id) = state.pending_delete {
+                ui.separator();
+                show_delete_confirm(ui, players, state, player_id);
+            }
+        });
+    state.open = open;
+
+    match edit {
+        Some(Edit::Add) => players.push(Player {
+            name: format!("プレイヤー{}", players.len() + 1),
+            ..Default::default()
+        }),
+        Some(Edit::Swap(a, b)) => {
+            if a < players.len() && b < players.len() {
+                players.swap(a, b);
+            }
+        }
+        Some(Edit::ClearIcon(player_id)) => {
+            if let Some(player) = players.iter_mut().find(|player| player.id == player_id) {
+                player.icon_path = None;
+            }
+        }
+        None => {}
+    }
+    if *players != before {
+        history.push("プレイヤーの編集", Change::Players(before));
+    }
+    icon_request
+}
+
+/// プレイヤー一覧（名前、キャラクター名、アイコンの編集、並べ替え、削除）
+fn show_player_list(
+    ui: &mut egui::Ui,
+    players: &mut [Player],
+    state: &mut RosterState,
+    edit: &mut Option<Edit>,
+    icon_request: &mut Option<Id>,
+) {
+    let player_count = players.len();
+    egui::Grid::new("roster").show(ui, |ui| {
+        for (index, player) in players.iter_mut().enumerate() {
+            ui.add(egui::TextEdit::singleline(&mut player.name).desired_width(120.0));
+            ui.add(
+                egui::TextEdit::singleline(&mut player.character)
+                    .hint_text("キャラクター名")
+                    .desired_width(120.0),
+            )
+            .on_hover_text("コピー時に{プレイヤー名}をこの名前に置き換えます");
+
+            // アイコンはファイル名だけ表示し、フルパスはホバーで表示
+            match &player.icon_path {
+                Some(path) => {
+                    let file_name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    ui.label(file_name)
+                        .on_hover_text(path.display().to_string());
+                }
+                None => {
+                    ui.weak("アイコンなし");
+                }
+            }
+            ui.horizontal(|ui| {
+                if ui.button("画像...").clicked() {
+                    *icon_request = Some(player.id);
+                }
+                if ui
+                    .add_enabled(player.icon_path.is_some(), egui::Button::new("✖"))
+                    .on_hover_text("アイコンを外す")
+                    .clicked()
+                {
+                    *edit = Some(Edit::ClearIcon(player.id));
+                }
+            });
+
+            ui.horizontal(|ui| {
+                if ui.add_enabled(index > 0, egui::Button::new("⏶")).clicked() {
+                    *edit = Some(Edit::Swap(index, index - 1));
+                }
+                if ui
+                    .add_enabled(index + 1 < player_count, egui::Button::new("⏷"))
+                    .clicked()
+                {
+                    *edit = Some(Edit::Swap(index, index + 1));
+                }
+                if ui.button("🗑").clicked() {
+                    state.pending_delete = Some(player.id);
+                }
+            });
+            ui.end_row();
+        }
+    });
+}
+
+/// 削除確認
+fn show_delete_confirm(
+    ui: &mut egui::Ui,
+    players: &mut Vec<Player>,
+    state: &mut RosterState,
+    player_id: Id,
+) {
+    let Some(player) = players.iter().find(|player| player.id == player_id) else {
+        state.pending_delete = None;
+        return;
+    };
+
+    ui.label(format!("「{}」を削除しますか？", player.name));
+    ui.horizontal(|ui| {
+        if ui.button("削除").clicked() {
+            players.retain(|player| player.id != player_id);
+            state.pending_delete = None;
+        }
+        if ui.button("キャンセル").clicked() {
+            state.pending_delete = None;
+        }
+    });
+}
@@ -0,0 +1,478 @@
+use crate::scenario::Scenario;
+use crate::scene::Id;
+use crate::search::{self, Hit, Location, MatchOptions, Matcher, Replacement};
+use eframe::egui;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// 検索ウィンドウを開く
+pub const SEARCH_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::F);
+
+/// 表示する検索結果の最大件数
+const MAX_RESULTS: usize = 500;
+
+/// プレビューで一致箇所の前後に表示する文字数
+const PREVIEW_CONTEXT_CHARS: usize = 20;
+
+/// 検索結果を検索し直しても同じ箇所を指すキー（場所, 一致の開始位置）
+type HitKey = (Id, Location, usize);
+
+/// 検索ウィンドウの状態
+#[derive(Default)]
+pub struct SearchState {
+    pub open: bool,
+    pub query: String,
+    /// 次のフレームで検索欄にフォーカスする
+    pub request_focus: bool,
+    pub options: MatchOptions,
+    /// 置換欄を表示する
+    pub replace_open: bool,
+    pub replacement: String,
+    /// 置換しない（スキップした）箇所
+    skipped: HashSet<HitKey>,
+    /// 前回の検索結果
+    results: SearchResults,
+}
+
+/// 検索結果（検索の条件とシナリオが変わるまで使い回す）
+#[derive(Default)]
+struct SearchResults {
+    /// 検索したときの条件（検索語, オプション, 置換後の文字列, シナリオのリビジョン）
+    key: Option<(String, MatchOptions, Option<String>, u64)>,
+    /// 検索語が正規表現として不正な場合のエラー
+    error: Option<String>,
+    hits: Vec<Hit>,
+    /// 各一致箇所の置換後の文字列（置換欄を閉じていればNone）
+    replacements: Vec<Option<String>>,
+}
+
+impl SearchState {
+    /// 条件かシナリオが前回の検索から変わっていれば検索し直す
+    fn refresh(&mut self, scenario: &Scenario, revision: u64) {
+        let replacement = self.replace_open.then_some(self.replacement.as_str());
+        let unchanged = matches!(
+            &self.results.key,
+            Some((query, options, last_replacement, last_revision))
+                if *query == self.query
+                    && *options == self.options
+                    && last_replacement.as_deref() == replacement
+                    && *last_revision == revision
+        );
+        if unchanged {
+            return;
+        }
+
+        let key = Some((
+            self.query.clone(),
+            self.options,
+            replacement.map(str::to_owned),
+            revision,
+        ));
+        self.results = match Matcher::new(&self.query, self.options) {
+            Ok(Some(matcher)) => {
+                let hits = search::search(scenario, &matcher);
+                let replacements = hits
+                    .iter()
+                    .map(|hit| {
+                        replacement.map(|template| {
+                            let text = search::field_text(scenario, hit.scene_id, hit.location)
+                                .unwrap_or_default();
+                            matcher.expand(template, text, &hit.groups)
+                        })
+                    })
+                    .collect();
+                SearchResults {
+                    key,
+                    error: None,
+                    hits,
+                    replacements,
+                }
+            }
+            Ok(None) => SearchResults {
+                key,
+                ..SearchResults::default()
+            },
+            Err(err) => SearchResults {
+                key,
+                error: Some(err.to_string()),
+                ..SearchResults::default()
+            },
+        };
+    }
+}
+
+/// 検索ウィンドウで選ばれた操作
+pub enum SearchAction {
+    /// 検索結果の場所へ移動
+    Jump(Hit),
+    /// チェックした箇所をまとめて置換
+    Replace(Vec<Replacement>),
+}
+
+/// 検索/置換ウィンドウ
+///
+/// `revision`はシナリオの編集履歴のリビジョン（変わったときだけ検索し直す）。
+pub fn show(
+    ctx: &egui::Context,
+    scenario: &Scenario,
+    revision: u64,
+    state: &mut SearchState,
+) -> Option<SearchAction> {
+    let mut open = state.open;
+    let mut action = None;
+
+    egui::Window::new("検索と置換")
+        .open(&mut open)
+        .default_width(420.0)
+        .show(ctx, |ui| {
+            if show_inputs(ui, state) {
+                state.skipped.clear();
+            }
+
+            state.refresh(scenario, revision);
+            if let Some(err) = &state.results.error {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+                return;
+            }
+            if state.query.is_empty() {
+                return;
+            }
+            let SearchResults {
+                hits, replacements, ..
+            } = &state.results;
+
+            ui.horizontal(|ui| {
+                ui.label(format!("{}件", hits.len()));
+                if state.replace_open {
+                    let accepted = hits
+                        .iter()
+                        .filter(|hit| !state.skipped.contains(&hit_key(hit)))
+                        .count();
+                    if ui
+                        .add_enabled(
+                            accepted > 0,
+                            egui::Button::new(format!("{accepted}件を置換")),
+                        )
+                        .clicked()
+                    {
+                        action = Some(SearchAction::Replace(
+                            hits.iter()
+                                .zip(replacements)
+                                .filter(|(hit, _)| !state.skipped.contains(&hit_key(hit)))
+                                .map(|(hit, text)| Replacement {
+                                    hit: hit.clone(),
+                                    text: text.clone().unwrap_or_default(),
+                                })
+                                .collect(),
+                        ));
+                        // 置換後は位置が変わるのでスキップの記録を捨てる
+                        state.skipped.clear();
+                    }
+                    if ui.button("全て選択").clicked() {
+                        state.skipped.clear();
+                    }
+                    if ui.button("全て解除").clicked() {
+                        state.skipped = hits.iter().map(hit_key).collect();
+                    }
+                }
+            });
+            ui.separator();
+
+            egui::ScrollArea::vertical()
+                .max_height(400.0)
+                .show(ui, |ui| {
+                    for (hit, replacement) in hits.iter().zip(replacements).take(MAX_RESULTS) {
+                        if show_hit(
+                            ui,
+                            scenario,
+                            hit,
+                            replacement.as_deref(),
+                            &mut state.skipped,
+                        )
+                        .clicked()
+                        {
+                            action = Some(SearchAction::Jump(hit.clone()));
+                        }
+                    }
+                });
+            if hits.len() > MAX_RESULTS {
+                ui.weak(format!("先頭の{MAX_RESULTS}件を表示しています"));
+            }
+        });
+    state.open = open;
+    action
+}
+
+/// 検索語、オプション、置換後の文字列の入力欄
+/// 返り値: 検索の条件が変わったか
+fn show_inputs(ui: &mut egui::Ui, state: &mut SearchState) -> bool {
+    let response = ui.add(
+        egui::TextEdit::singleline(&mut state.query)
+            .hint_text("シーン名、ラベル、テキストを検索")
+            .desired_width(f32::INFINITY),
+    );
+    if state.request_focus {
+        response.request_focus();
+        state.request_focus = false;
+    }
+    let mut changed = response.changed();
+
+    ui.horizontal(|ui| {
+        let options = &mut state.options;
+        changed |= ui
+            .toggle_value(&mut options.case_sensitive, "Aa")
+            .on_hover_text("大文字/小文字を区別")
+            .changed();
+        changed |= ui
+            .toggle_value(&mut options.width_sensitive, "全半")
+            .on_hover_text("全角/半角を区別")
+            .changed();
+        changed |= ui
+            .toggle_value(&mut options.regex, ".*")
+            .on_hover_text("正規表現")
+            .changed();
+        ui.separator();
+        ui.toggle_value(&mut state.replace_open, "置換");
+    });
+
+    if state.replace_open {
+        let hint = if state.options.regex {
+            "置換後の文字列（$1、${name}でグループを参照）"
+        } else {
+            "置換後の文字列"
+        };
+        changed |= ui
+            .add(
+                egui::TextEdit::singleline(&mut state.replacement)
+                    .hint_text(hint)
+                    .desired_width(f32::INFINITY),
+            )
+            .changed();
+    }
+    changed
+}
+
+fn hit_key(hit: &Hit) -> HitKey {
+    (hit.scene_id, hit.location, hit.range.start)
+}
+
+/// 検索結果の1件（場所 + 一致箇所のプレビュー、置換時は置換するかのチェック）
+/// 返り値: プレビューのレスポンス（クリックで移動）
+fn show_hit(
+    ui: &mut egui::Ui,
+    scenario: &Scenario,
+    hit: &Hit,
+    replacement: Option<&str>,
+    skipped: &mut HashSet<HitKey>,
+) -> egui::Response {
+    let text = search::field_text(scenario, hit.scene_id, hit.location).unwrap_or_default();
+    ui.weak(context_label(scenario, hit));
+    let response = ui
+        .horizontal(|ui| {
+            if replacement.is_some() {
+                let key = hit_key(hit);
+                let mut accepted = !skipped.contains(&key);
+                if ui.checkbox(&mut accepted, "").changed() {
+                    if accepted {
+                        skipped.remove(&key);
+                    } else {
+                        skipped.insert(key);
+                    }
+                }
+            }
+            let preview = preview_job(ui, text, &hit.range, replacement);
+            ui.add(egui::Button::new(preview).frame(false))
+        })
+        .inner;
+    ui.add_space(4.0);
+    response
+}
+
+/// 一致した場所の説明（シーン › モード › スロット › 判定）
+pub fn context_label(scenario: &Scenario, hit: &Hit) -> String {
+    let Some(scene) = scenario
+        .scenes
+        .iter()
+        .find(|scene| scene.id == hit.scene_id)
+    else {
+        return String::new();
+    };
+    let mode_name = |mode_id| scenario.mode(mode_id).map_or("", |mode| mode.name.as_str());
+    let slot_name = |mode_id, slot_id| {
+        scene
+            .slots(mode_id)
+            .iter()
+            .enumerate()
+            .find(|(_, slot)| slot.id == slot_id)
+            .map(|(index, slot)| {
+                slot.label
+                    .clone()
+                    .unwrap_or_else(|| format!("スロット{}", index + 1))
+            })
+            .unwrap_or_default()
+    };
+
+    match hit.location {
+        Location::SceneTitle => format!("{} ・ シーン名", scene.title),
+        Location::SlotLabel { mode_id, .. } => {
+            format!("{} › {} ・ ラベル", scene.title, mode_name(mode_id))
+        }
+        Location::Text {
+            mode_id,
+            slot_id,
+            judge_id,
+        } => {
+            let judge_name = scenario
+                .mode(mode_id)
+                .and_then(|mode| mode.judge(judge_id))
+                .map_or("", |judge| judge.name.as_str());
+            format!(
+                "{} › {} › {} › {}",
+                scene.title,
+                mode_name(mode_id),
+                slot_name(mode_id, slot_id),
+                judge_name
+            )
+        }
+    }
+}
+
+/// 一致箇所を強調した1行のプレビュー（置換後の文字列があれば一致箇所を消して並べる）
+pub fn preview_job(
+    ui: &egui::Ui,
+    text: &str,
+    range: &Range<usize>,
+    replacement: Option<&str>,
+) -> egui::text::LayoutJob {
+    let before = text.get(..range.start).unwrap_or_default();
+    let matched = text.get(range.clone()).unwrap_or_default();
+    let after = text.get(range.end..).unwrap_or_default();
+
+    let mut before: Vec<char> = before
+        .chars()
+        .rev()
+        .take(PREVIEW_CONTEXT_CHARS + 1)
+        .collect();
+    let before_truncated = before.len() > PREVIEW_CONTEXT_CHARS;
+    before.truncate(PREVIEW_CONTEXT_CHARS);
+    let before: String = before.into_iter().rev().collect();
+    let mut after: Vec<char> = after.chars().take(PREVIEW_CONTEXT_CHARS + 1).collect();
+    let after_truncated = after.len() > PREVIEW_CONTEXT_CHARS;
+    after.truncate(PREVIEW_CONTEXT_CHARS);
+    let after: String = after.into_iter().collect();
+
+    let single_line = |s: &str| s.replace('\n', " ");
+    let font = egui::TextStyle::Body.resolve(ui.style());
+    let normal = egui::TextFormat::simple(font.clone(), ui.visuals().text_color());
+    let highlight = egui::TextFormat {
+        background: ui.visuals().selection.bg_fill,
+        ..egui::TextFormat::simple(font, ui.visuals().strong_text_color())
+    };
+
+    let mut job = egui::text::LayoutJob::default();
+    if before_truncated {
+        job.append("…", 0.0, normal.clone());
+    }
+    job.append(&single_line(&before), 0.0, normal.clone());
+    match replacement {
+        Some(replacement) => {
+            let removed = egui::TextFormat {
+                strikethrough: egui::Stroke::new(1.0, ui.visuals().weak_text_color()),
+                color: ui.visuals().weak_text_color(),
+                ..normal.clone()
+            };
+            job.append(&single_line(matched), 0.0, removed);
+            job.append(&single_line(replacement), 0.0, highlight);
+        }
+        None => job.append(&single_line(matched), 0.0, highlight),
+    }
+    job.append(&single_line(&after), 0.0, normal.clone());
+    if after_truncated {
+        job.append("…", 0.0, normal);
+    }
+    job
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_title(scenario: &mut Scenario, title: &str) {
+        scenario
+            .scenes
+            .first_mut()
+            .expect("サンプルのシーンがある")
+            .title = String::from(title);
+    }
+
+    #[test]
+    fn results_are_reused_until_scenario_or_query_changes() {
+        let mut scenario = Scenario::default();
+        set_title(&mut scenario, "合言葉");
+        let mut state = SearchState {
+            query: String::from("合言葉"),
+            ..SearchState::default()
+        };
+        state.refresh(&scenario, 1);
+        assert_eq!(state.results.hits.len(), 1, "タイトルに一致する");
+
+        // リビジョンが同じ間は検索し直さない
+        set_title(&mut scenario, "なし");
+        state.refresh(&scenario, 1);
+        assert_eq!(state.results.hits.len(), 1, "前回の結果を使う");
+
+        state.refresh(&scenario, 2);
+        assert!(
+            state.results.hits.is_empty(),
+            "リビジョンが変われば検索し直す"
+        );
+
+        state.query = String::from("なし");
+        state.refresh(&scenario, 2);
+        assert_eq!(state.results.hits.len(), 1, "検索語が変われば検索し直す");
+    }
+
+    #[test]
+    fn replacement_and_error_are_part_of_the_key() {
+        let mut scenario = Scenario::default();
+        set_title(&mut scenario, "目星");
+        let mut state = SearchState {
+            query: String::from("目星"),
+            replace_open: true,
+            replacement: String::from("図書館"),
+            ..SearchState::default()
+        };
+        state.refresh(&scenario, 1);
+        assert_eq!(
+            state
+                .results
+                .replacements
+                .first()
+                .cloned()
+                .flatten()
+                .as_deref(),
+            Some("図書館"),
+            "置換後の文字列"
+        );
+
+        state.replacement = String::from("聞き耳");
+        state.refresh(&scenario, 1);
+        assert_eq!(
+            state
+                .results
+                .replacements
+                .first()
+                .cloned()
+                .flatten()
+                .as_deref(),
+            Some("聞き耳"),
+            "置換後の文字列が変われば作り直す"
+        );
+
+        state.options.regex = true;
+        state.query = String::from("(");
+        state.refresh(&scenario, 1);
+        assert!(state.results.error.is_some(), "不正な正規表現はエラー");
+    }
+}
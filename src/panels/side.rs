@@ -1,17 +1,205 @@
-use crate::scene::Scene;
+use crate::scene::{Id, Scene};
 use eframe::egui;
+use std::collections::BTreeSet;
 
-pub fn show(ctx: &egui::Context, scenes: &[Scene], selected_index: &mut usize) {
+/// シーン一覧の状態
+#[derive(Default)]
+pub struct SceneListState {
+    /// Ctrl/Shiftで複数選択されたシーン
+    pub selected: BTreeSet<Id>,
+    /// Shift選択の起点
+    pub anchor: Option<Id>,
+}
+
+impl SceneListState {
+    /// 操作対象のシーン（複数選択に現在のシーンが含まれなければ現在のシーンのみ）
+    pub fn targets(&self, scenes: &[Scene], current: usize) -> Vec<Id> {
+        let Some(current) = scenes.get(current) else {
+            return Vec::new();
+        };
+        if self.selected.contains(&current.id) {
+            scenes
+                .iter()
+                .filter(|scene| self.selected.contains(&scene.id))
+                .map(|scene| scene.id)
+                .collect()
+        } else {
+            vec![current.id]
+        }
+    }
+}
+
+/// シーン一覧で選ばれた操作
+pub enum SceneAction {
+    /// 選択中のシーンの名前を変更
+    Rename,
+    Duplicate(Vec<Id>),
+    /// 削除（確認モーダルを開く）
+    Delete(Vec<Id>),
+    /// 指定位置に新規シーンを挿入
+    Insert(usize),
+    /// (移動するシーン, 移動先の位置)
+    Move(Vec<Id>, usize),
+}
+
+/// ドラッグ中のシーン
+struct SceneDrag(Vec<Id>);
+
+/// `SidePanel`のメイン表示関数
+/// 返り値: 右クリックメニューやドラッグで選ばれた操作
+pub fn show(
+    ctx: &egui::Context,
+    scenes: &[Scene],
+    selected_index: &mut usize,
+    state: &mut SceneListState,
+) -> Option<SceneAction> {
+    let mut action = None;
     egui::SidePanel::left("scene_list").show(ctx, |ui| {
         egui::ScrollArea::vertical().show(ui, |ui| {
             for (i, scene) in scenes.iter().enumerate() {
-                if ui
-                    .selectable_label(*selected_index == i, &scene.title)
-                    .clicked()
-                {
+                let is_selected = *selected_index == i || state.selected.contains(&scene.id);
+                let response = ui.add(
+                    egui::Button::selectable(is_selected, &scene.title)
+                        .sense(egui::Sense::click_and_drag()),
+                );
+
+                if response.clicked() {
+                    select(ui, scenes, i, selected_index, state);
+                }
+                // 選択されていないシーンを右クリック/ドラッグした場合はそのシーンだけを選択
+                if (response.secondary_clicked() || response.drag_started()) && !is_selected {
+                    state.selected.clear();
+                    state.anchor = Some(scene.id);
                     *selected_index = i;
                 }
+                if response.drag_started() {
+                    response
+                        .dnd_set_drag_payload(SceneDrag(state.targets(scenes, *selected_index)));
+                }
+                handle_scene_drop(ui, &response, i, &mut action);
+
+                response.context_menu(|ui| {
+                    show_context_menu(ui, scenes, i, *selected_index, state, &mut action);
+                });
             }
         });
     });
+    action
+}
+
+/// クリックで選択（Shiftで範囲選択、Ctrlで追加/解除）
+fn select(
+    ui: &egui::Ui,
+    scenes: &[Scene],
+    index: usize,
+    selected_index: &mut usize,
+    state: &mut SceneListState,
+) {
+    let Some(scene) = scenes.get(index) else {
+        return;
+    };
+    let modifiers = ui.input(|i| i.modifiers);
+    if modifiers.shift {
+        let anchor = state
+            .anchor
+            .and_then(|id| scenes.iter().position(|scene| scene.id == id))
+            .unwrap_or(*selected_index);
+        let (start, end) = (anchor.min(index), anchor.max(index));
+        state.selected = scenes
+            .iter()
+            .skip(start)
+            .take(end - start + 1)
+            .map(|scene| scene.id)
+            .collect();
+    } else if modifiers.command {
+        // 最初のCtrlクリックでは現在のシーンも複数選択に含める
+        if state.selected.is_empty() {
+            if let Some(current) = scenes.get(*selected_index) {
+                state.selected.insert(current.id);
+            }
+        }
+        if !state.selected.remove(&scene.id) {
+            state.selected.insert(scene.id);
+        }
+        state.anchor = Some(scene.id);
+    } else {
+        state.selected.clear();
+        state.anchor = Some(scene.id);
+    }
+    *selected_index = index;
+}
+
+/// 右クリックメニュー（名前の変更、複製、挿入、削除）
+fn show_context_menu(
+    ui: &mut egui::Ui,
+    scenes: &[Scene],
+    index: usize,
+    selected_index: usize,
+    state: &SceneListState,
+    action: &mut Option<SceneAction>,
+) {
+    let targets = state.targets(scenes, selected_index);
+    let count = if targets.len() > 1 {
+        format!("（{}件）", targets.len())
+    } else {
+        String::new()
+    };
+
+    if ui
+        .add_enabled(targets.len() == 1, egui::Button::new("名前を変更..."))
+        .clicked()
+    {
+        *action = Some(SceneAction::Rename);
+    }
+    if ui.button(format!("複製{count}")).clicked() {
+        *action = Some(SceneAction::Duplicate(targets.clone()));
+    }
+    ui.separator();
+    if ui.button("前に新規シーンを挿入").clicked() {
+        *action = Some(SceneAction::Insert(index));
+    }
+    if ui.button("後に新規シーンを挿入").clicked() {
+        *action = Some(SceneAction::Insert(index + 1));
+    }
+    ui.separator();
+    // 全てのシーンは削除できない
+    if ui
+        .add_enabled(
+            targets.len() < scenes.len(),
+            egui::Button::new(format!("削除{count}...")),
+        )
+        .clicked()
+    {
+        *action = Some(SceneAction::Delete(targets));
+    }
+}
+
+/// ドラッグ中のシーンをこの行に落とした場合の並べ替え
+fn handle_scene_drop(
+    ui: &egui::Ui,
+    row: &egui::Response,
+    index: usize,
+    action: &mut Option<SceneAction>,
+) {
+    if row.dnd_hover_payload::<SceneDrag>().is_none() {
+        return;
+    }
+    // 行の上半分なら前、下半分なら後ろに挿入
+    let pointer_y = ui
+        .ctx()
+        .pointer_interact_pos()
+        .map_or(row.rect.center().y, |pos| pos.y);
+    let insert_below = pointer_y > row.rect.center().y;
+    let line_y = if insert_below {
+        row.rect.bottom()
+    } else {
+        row.rect.top()
+    };
+    ui.painter()
+        .hline(row.rect.x_range(), line_y, ui.visuals().selection.stroke);
+
+    if let Some(dragged) = row.dnd_release_payload::<SceneDrag>() {
+        let to = index + usize::from(insert_below);
+        *action = Some(SceneAction::Move(dragged.0.clone(), to));
+    }
 }
@@ -1,31 +1,204 @@
 use eframe::egui;
 
-use crate::app::{self, AppMode};
+use crate::app::{AppMode, CopySettings, MenuCommand};
+use crate::export::ExportFormat;
+use crate::history::{History, REDO_SHORTCUT, UNDO_SHORTCUT};
+use crate::message::CopyTarget;
+use crate::panels::search::SEARCH_SHORTCUT;
+use crate::system::GameSystem;
 
-/// TopPanelのメイン表示関数
-pub fn show(ctx: &egui::Context, app_mode: &mut AppMode) {
+/// `TopPanel`のメイン表示関数
+/// 返り値: Fileメニューで選択されたコマンド
+pub fn show(
+    ctx: &egui::Context,
+    app_mode: &mut AppMode,
+    copy: &mut CopySettings,
+    system: GameSystem,
+    history: &History,
+) -> Option<MenuCommand> {
+    let mut command = None;
     egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
         egui::MenuBar::new().ui(ui, |ui| {
-            show_file_menu(ctx, ui);
+            show_file_menu(ui, &mut command);
+            show_edit_menu(ui, history, &mut command);
+            show_system_menu(ui, system, &mut command);
             show_appmode_buttons(ui, app_mode);
+            if *app_mode == AppMode::Copy {
+                show_copy_settings(ui, copy);
+            }
             show_theme_buttons(ui);
         });
     });
+    command
+}
+
+/// コピーモードの設定（判定後にコピー、分割コピー）
+fn show_copy_settings(ui: &mut egui::Ui, copy: &mut CopySettings) {
+    egui::ComboBox::from_id_salt("copy_target")
+        .selected_text(copy.target.label())
+        .show_ui(ui, |ui| {
+            for target in CopyTarget::ALL {
+                ui.selectable_value(&mut copy.target, target, target.label());
+            }
+        })
+        .response
+        .on_hover_text("貼り付け先に合わせてコピーする文字列を整形する");
+    ui.checkbox(&mut copy.after_check, "判定後にコピー")
+        .on_hover_text("技能判定で選ばれた判定のテキストをそのままコピーする");
+    ui.checkbox(&mut copy.split, "分割コピー")
+        .on_hover_text("長いテキストを文の区切りで分け、クリックするたびに続きをコピーする");
+    if copy.split {
+        ui.add(
+            egui::DragValue::new(&mut copy.split_limit)
+                .range(20..=2000)
+                .suffix("文字"),
+        )
+        .on_hover_text("1回にコピーする最大文字数");
+    }
 }
 
-/// Fileメニュー（Quitボタン）
-fn show_file_menu(ctx: &egui::Context, ui: &mut egui::Ui) {
+/// Fileメニュー（新規/開く/保存/インポート/エクスポート/Quit）
+fn show_file_menu(ui: &mut egui::Ui, command: &mut Option<MenuCommand>) {
     let is_web = cfg!(target_arch = "wasm32");
     if !is_web {
         ui.menu_button("File", |ui| {
+            if ui.button("新規").clicked() {
+                *command = Some(MenuCommand::New);
+            }
+            if ui.button("開く...").clicked() {
+                *command = Some(MenuCommand::Open);
+            }
+            ui.separator();
+            if ui.button("保存").clicked() {
+                *command = Some(MenuCommand::Save);
+            }
+            if ui.button("名前を付けて保存...").clicked() {
+                *command = Some(MenuCommand::SaveAs);
+            }
+            ui.separator();
+            if ui.button("Markdownをインポート...").clicked() {
+                *command = Some(MenuCommand::ImportMarkdown);
+            }
+            ui.menu_button("エクスポート", |ui| {
+                for format in ExportFormat::ALL {
+                    if ui
+                        .button(format!("{}...", format.exporter().name()))
+                        .clicked()
+                    {
+                        *command = Some(MenuCommand::Export(format));
+                    }
+                }
+                ui.separator();
+                if ui.button("ccfoliaチャットパレット...").clicked() {
+                    *command = Some(MenuCommand::OpenChatPalette);
+                }
+            });
+            ui.separator();
             if ui.button("Quit").clicked() {
-                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                *command = Some(MenuCommand::Quit);
             }
         });
         ui.add_space(16.0);
     }
 }
 
+/// Editメニュー（元に戻す/やり直し、検索）
+fn show_edit_menu(ui: &mut egui::Ui, history: &History, command: &mut Option<MenuCommand>) {
+    ui.menu_button("Edit", |ui| {
+        let undo_text = history.undo_label().map_or_else(
+            || String::from("元に戻す"),
+            |label| format!("元に戻す（{label}）"),
+        );
+        if ui
+            .add_enabled(
+                history.undo_label().is_some(),
+                egui::Button::new(undo_text)
+                    .shortcut_text(ui.ctx().format_shortcut(&UNDO_SHORTCUT)),
+            )
+            .clicked()
+        {
+            *command = Some(MenuCommand::Undo);
+        }
+
+        let redo_text = history.redo_label().map_or_else(
+            || String::from("やり直し"),
+            |label| format!("やり直し（{label}）"),
+        );
+        if ui
+            .add_enabled(
+                history.redo_label().is_some(),
+                egui::Button::new(redo_text)
+                    .shortcut_text(ui.ctx().format_shortcut(&REDO_SHORTCUT)),
+            )
+            .clicked()
+        {
+            *command = Some(MenuCommand::Redo);
+        }
+        ui.separator();
+        if ui
+            .add(
+                egui::Button::new("検索と置換...")
+                    .shortcut_text(ui.ctx().format_shortcut(&SEARCH_SHORTCUT)),
+            )
+            .clicked()
+        {
+            *command = Some(MenuCommand::OpenSearch);
+        }
+    });
+    ui.add_space(16.0);
+}
+
+/// システムメニュー（ゲームシステムの切り替え、モード/判定/プレイヤーの編集）
+fn show_system_menu(ui: &mut egui::Ui, current: GameSystem, command: &mut Option<MenuCommand>) {
+    ui.menu_button("システム", |ui| {
+        for system in GameSystem::ALL {
+            if ui
+                .selectable_label(current == system, system.name())
+                .clicked()
+            {
+                *command = Some(MenuCommand::SwitchSystem(system));
+            }
+        }
+        ui.separator();
+        if ui.button("モードと判定を編集...").clicked() {
+            *command = Some(MenuCommand::OpenModeEditor);
+        }
+        if ui.button("プレイヤー一覧を編集...").clicked() {
+            *command = Some(MenuCommand::OpenRoster);
+        }
+        if ui.button("変数一覧を編集...").clicked() {
+            *command = Some(MenuCommand::OpenVariables);
+        }
+        ui.separator();
+        if ui.button("ダイスロール...").clicked() {
+            *command = Some(MenuCommand::OpenDice);
+        }
+        ui.separator();
+        if ui
+            .button("プレイヤー用ウィンドウ...")
+            .on_hover_text("コピーしたテキストだけを大きく表示する別ウィンドウ（画面共有用）")
+            .clicked()
+        {
+            *command = Some(MenuCommand::OpenPlayerView);
+        }
+        if ui.button("プレイヤー用ウィンドウの表示を消す").clicked() {
+            *command = Some(MenuCommand::ClearPlayerView);
+        }
+        ui.separator();
+        if ui.button("ショートカットキー...").clicked() {
+            *command = Some(MenuCommand::OpenKeymap);
+        }
+        if ui
+            .button("設定ファイルの場所をコピー")
+            .on_hover_text("設定ファイルがなければ雛形を作ります（保存すると自動で読み直します）")
+            .clicked()
+        {
+            *command = Some(MenuCommand::CopyConfigPath);
+        }
+    });
+    ui.add_space(16.0);
+}
+
 /// AppMode切り替えボタン（編集/コピー）
 fn show_appmode_buttons(ui: &mut egui::Ui, app_mode: &mut AppMode) {
     if ui
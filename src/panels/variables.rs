@@ -0,0 +1,122 @@
+use crate::history::{Change, History};
+use crate::placeholder::{self, Variable};
+use crate::scenario::Scenario;
+use eframe::egui;
+
+/// 変数一覧の編集ウィンドウの状態
+#[derive(Default)]
+pub struct VariablesState {
+    pub open: bool,
+}
+
+/// ウィンドウ内で押された操作（描画後にまとめて適用する）
+enum Edit {
+    Add(String),
+    Swap(usize, usize),
+    Remove(usize),
+}
+
+/// 変数一覧の編集ウィンドウ（変更は`history`に記録する）
+pub fn show(
+    ctx: &egui::Context,
+    scenario: &mut Scenario,
+    state: &mut VariablesState,
+    history: &mut History,
+) {
+    let before = scenario.variables.clone();
+    let mut open = state.open;
+    let mut edit = None;
+
+    egui::Window::new("変数一覧")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.weak("本文中の{名前}をコピー時に値に置き換えます。");
+            ui.weak("{PC1}は地の文を除いたプレイヤー一覧の1番目、{探偵}は同じ名前のプレイヤーのキャラクター名になります。");
+            ui.weak("{{、}}と書くと{、}をそのまま出力します。");
+            ui.separator();
+
+            show_variable_list(ui, &mut scenario.variables, &mut edit);
+            if ui.button("+ 変数追加").clicked() {
+                edit = Some(Edit::Add(format!(
+                    "変数{}",
+                    scenario.variables.len() + 1
+                )));
+            }
+
+            // 本文で使われているが定義されていない名前
+            let undefined = placeholder::undefined_names(scenario);
+            if !undefined.is_empty() {
+                ui.separator();
+                ui.colored_label(ui.visuals().warn_fg_color, "未定義の変数:");
+                ui.horizontal_wrapped(|ui| {
+                    for name in undefined {
+                        if ui
+                            .button(format!("+ {{{name}}}"))
+                            .on_hover_text("変数として追加")
+                            .clicked()
+                        {
+                            edit = Some(Edit::Add(name));
+                        }
+                    }
+                });
+            }
+        });
+    state.open = open;
+
+    let variables = &mut scenario.variables;
+    match edit {
+        Some(Edit::Add(name)) => variables.push(Variable {
+            name,
+            value: String::new(),
+        }),
+        Some(Edit::Swap(a, b)) => {
+            if a < variables.len() && b < variables.len() {
+                variables.swap(a, b);
+            }
+        }
+        Some(Edit::Remove(index)) => {
+            if index < variables.len() {
+                variables.remove(index);
+            }
+        }
+        None => {}
+    }
+    if *variables != before {
+        history.push("変数の編集", Change::Variables(before));
+    }
+}
+
+/// 変数一覧（名前と値の編集、並べ替え、削除）
+fn show_variable_list(ui: &mut egui::Ui, variables: &mut [Variable], edit: &mut Option<Edit>) {
+    let variable_count = variables.len();
+    egui::Grid::new("variables").show(ui, |ui| {
+        for (index, variable) in variables.iter_mut().enumerate() {
+            ui.add(
+                egui::TextEdit::singleline(&mut variable.name)
+                    .hint_text("名前")
+                    .desired_width(100.0),
+            );
+            ui.add(
+                egui::TextEdit::singleline(&mut variable.value)
+                    .hint_text("値")
+                    .desired_width(180.0),
+            );
+            ui.horizontal(|ui| {
+                if ui.add_enabled(index > 0, egui::Button::new("⏶")).clicked() {
+                    *edit = Some(Edit::Swap(index, index - 1));
+                }
+                if ui
+                    .add_enabled(index + 1 < variable_count, egui::Button::new("⏷"))
+                    .clicked()
+                {
+                    *edit = Some(Edit::Swap(index, index + 1));
+                }
+                if ui.button("🗑").clicked() {
+                    *edit = Some(Edit::Remove(index));
+                }
+            });
+            ui.end_row();
+        }
+    });
+}
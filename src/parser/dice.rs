@@ -0,0 +1,1065 @@
+use crate::scene::{Id, Mode, SkillCheck};
+use crate::search::fold_width;
+use crate::system::{GameSystem, Outcome, classify};
+use std::fmt;
+use std::hash::{BuildHasher as _, RandomState};
+
+/// 1つの項で振れるダイスの最大個数
+const MAX_DICE: u32 = 100;
+
+/// ダイスの最大面数
+const MAX_SIDES: u32 = 10000;
+
+/// 7版のボーナス/ペナルティダイスの最大数
+const MAX_BONUS_DICE: i32 = 2;
+
+/// エモクロアTRPGの判定で2成功になる出目
+const POOL_CRITICAL: u32 = 1;
+
+/// エモクロアTRPGの判定で目標値を超えたら-1成功になる出目
+const POOL_FUMBLE: u32 = 10;
+
+/// 数値として読む最大の桁数（オーバーフロー対策）
+const MAX_DIGITS: usize = 9;
+
+/// ダイス用の乱数（SplitMix64）
+///
+/// シードを指定すれば同じ出目を再現できる。
+pub struct DiceRng {
+    state: u64,
+}
+
+impl DiceRng {
+    pub fn seeded(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// 1〜`sides`の出目
+    pub fn roll(&mut self, sides: u32) -> u32 {
+        // 上位ビットを使って偏りを抑える
+        let value = (u128::from(self.next_u64()) * u128::from(sides)) >> 64;
+        u32::try_from(value).unwrap_or(0) + 1
+    }
+}
+
+impl Default for DiceRng {
+    /// 起動ごとに異なるシード
+    fn default() -> Self {
+        Self::seeded(RandomState::new().hash_one(0_u64))
+    }
+}
+
+/// ダイスコマンドの解釈エラー
+#[derive(Debug)]
+pub enum DiceError {
+    /// コマンドが空
+    Empty,
+    /// 解釈できない文字（位置は文字単位、Noneは途中で終わった）
+    Unexpected {
+        position: usize,
+        found: Option<char>,
+    },
+    /// ダイスの個数/面数、ボーナスダイスの数などが範囲外
+    OutOfRange,
+    /// 目標値にダイスが含まれている
+    DiceInTarget,
+}
+
+impl fmt::Display for DiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "コマンドを入力してください"),
+            Self::Unexpected {
+                position,
+                found: Some(c),
+            } => write!(f, "{}文字目の「{c}」を解釈できません", position + 1),
+            Self::Unexpected { found: None, .. } => write!(f, "コマンドが途中で終わっています"),
+            Self::OutOfRange => write!(
+                f,
+                "ダイスは{MAX_DICE}個、{MAX_SIDES}面まで、ボーナス/ペナルティダイスは{MAX_BONUS_DICE}個までです"
+            ),
+            Self::DiceInTarget => write!(f, "目標値にダイスは使えません"),
+        }
+    }
+}
+
+impl std::error::Error for DiceError {}
+
+/// 目標値との比較
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Le,
+    Ge,
+    Lt,
+    Gt,
+    Eq,
+    Ne,
+}
+
+impl Comparison {
+    /// 長い記号から順に照合する
+    const ALL: [(&'static str, Self); 7] = [
+        ("<=", Self::Le),
+        (">=", Self::Ge),
+        ("<>", Self::Ne),
+        ("!=", Self::Ne),
+        ("<", Self::Lt),
+        (">", Self::Gt),
+        ("=", Self::Eq),
+    ];
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Le => "<=",
+            Self::Ge => ">=",
+            Self::Lt => "<",
+            Self::Gt => ">",
+            Self::Eq => "=",
+            Self::Ne => "<>",
+        }
+    }
+
+    fn test(self, value: i64, target: i64) -> bool {
+        match self {
+            Self::Le => value <= target,
+            Self::Ge => value >= target,
+            Self::Lt => value < target,
+            Self::Gt => value > target,
+            Self::Eq => value == target,
+            Self::Ne => value != target,
+        }
+    }
+}
+
+/// 加算ロールの項
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Term {
+    /// `2D6`
+    Dice {
+        count: u32,
+        sides: u32,
+    },
+    Number(i64),
+}
+
+/// ダイスコマンド
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// 加算ロール（`2D6+3`、`1D100<=65`）
+    /// terms: (負の項か, 項)
+    Sum {
+        terms: Vec<(bool, Term)>,
+        target: Option<(Comparison, i64)>,
+    },
+    /// クトゥルフ神話TRPGの技能ロール
+    ///
+    /// * `CC<=50`: 7版ならレギュラー/ハード/イクストリーム、それ以外は1クリティカル/100ファンブル
+    /// * `CC(1)<=50`、`CC(-1)<=50`: ボーナス/ペナルティダイス
+    /// * `CCB<=40`: 6版の1〜5決定的成功/96〜100致命的失敗
+    Skill {
+        target: Option<i64>,
+        bonus: i32,
+        wide: bool,
+    },
+    /// エモクロアTRPGの判定（`2DM<=6`）
+    ///
+    /// D10を`count`個振り、目標値以下の出目を1成功（1は2成功、目標値を超えた10は-1成功）として数える。
+    Pool { count: u32, target: i64 },
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sum { terms, target } => {
+                for (index, (negative, term)) in terms.iter().enumerate() {
+                    if *negative {
+                        write!(f, "-")?;
+                    } else if index > 0 {
+                        write!(f, "+")?;
+                    }
+                    match term {
+                        Term::Dice { count, sides } => write!(f, "{count}D{sides}")?,
+                        Term::Number(number) => write!(f, "{number}")?,
+                    }
+                }
+                if let Some((comparison, target)) = target {
+                    write!(f, "{}{target}", comparison.symbol())?;
+                }
+                Ok(())
+            }
+            Self::Skill {
+                target,
+                bonus,
+                wide,
+            } => {
+                write!(f, "{}", if *wide { "CCB" } else { "CC" })?;
+                if *bonus != 0 {
+                    write!(f, "({bonus})")?;
+                }
+                if let Some(target) = target {
+                    write!(f, "<={target}")?;
+                }
+                Ok(())
+            }
+            Self::Pool { count, target } => write!(f, "{count}DM<={target}"),
+        }
+    }
+}
+
+/// 判定結果
+#[derive(Clone, Copy, Debug)]
+pub struct Verdict {
+    /// 判定名（ゲームシステムの判定名と同じ表記）
+    pub name: &'static str,
+    pub outcome: Outcome,
+}
+
+impl Verdict {
+    fn new(name: &'static str, outcome: Outcome) -> Self {
+        Self { name, outcome }
+    }
+
+    /// モードの判定のうち結果に対応するもの
+    ///
+    /// 同じ名前の判定、同じ分類の判定、近い分類の判定の順に探す。
+    pub fn judge(&self, mode: &Mode) -> Option<Id> {
+        mode.judges
+            .iter()
+            .find(|judge| judge.name.trim() == self.name)
+            .or_else(|| {
+                std::iter::once(self.outcome)
+                    .chain(self.outcome.fallbacks().iter().copied())
+                    .find_map(|outcome| {
+                        mode.judges
+                            .iter()
+                            .find(|judge| classify(&judge.name) == Some(outcome))
+                    })
+            })
+            .map(|judge| judge.id)
+    }
+}
+
+/// ダイスを振った結果
+pub struct Roll {
+    pub command: Command,
+    pub total: i64,
+    /// 途中経過（出目の内訳など）
+    pub steps: Vec<String>,
+    /// 目標値がない場合はNone
+    pub verdict: Option<Verdict>,
+}
+
+impl Roll {
+    /// `BCDice`風の結果表示（`(2D6+3) ＞ 7[3,4]+3 ＞ 10`）
+    pub fn text(&self) -> String {
+        let mut text = format!("({})", self.command);
+        for step in &self.steps {
+            text.push_str(" ＞ ");
+            text.push_str(step);
+        }
+        text.push_str(&format!(" ＞ {}", self.total));
+        if let Some(verdict) = self.verdict {
+            text.push_str(&format!(" ＞ {}", verdict.name));
+        }
+        text
+    }
+}
+
+/// コマンドを解釈する
+///
+/// 大文字/小文字、全角/半角は区別しない。空白の後ろはコメントとして無視する（`1D100<=65 目星`）。
+///
+/// # Errors
+/// 解釈できない場合
+pub fn parse(input: &str) -> Result<Command, DiceError> {
+    let folded: String = input.chars().map(fold_width).collect();
+    let chars: Vec<char> = folded
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .flat_map(char::to_uppercase)
+        .collect();
+    if chars.is_empty() {
+        return Err(DiceError::Empty);
+    }
+
+    let mut cursor = Cursor { chars, position: 0 };
+    let command = if cursor.eat_str("CCB") {
+        cursor.skill(true)?
+    } else if cursor.eat_str("CC") {
+        cursor.skill(false)?
+    } else if let Some(pool) = cursor.pool()? {
+        pool
+    } else {
+        cursor.sum()?
+    };
+    if !cursor.is_end() {
+        return Err(cursor.unexpected());
+    }
+    Ok(command)
+}
+
+/// ダイスを振るコマンドだけの行か（`1D100<=50 アイデア`、`CC<=65`など）
+pub fn is_command(line: &str) -> bool {
+    match parse(line) {
+        Ok(Command::Skill { .. } | Command::Pool { .. }) => true,
+        // 数字だけの行はコマンドとみなさない
+        Ok(Command::Sum { terms, .. }) => terms
+            .iter()
+            .any(|(_, term)| matches!(term, Term::Dice { .. })),
+        Err(_) => false,
+    }
+}
+
+/// コマンドを解釈して振る
+///
+/// # Errors
+/// 解釈できない場合
+pub fn roll(input: &str, system: GameSystem, rng: &mut DiceRng) -> Result<Roll, DiceError> {
+    Ok(parse(input)?.roll(system, rng))
+}
+
+struct Cursor {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Cursor {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn is_end(&self) -> bool {
+        self.position >= self.chars.len()
+    }
+
+    fn unexpected(&self) -> DiceError {
+        DiceError::Unexpected {
+            position: self.position,
+            found: self.peek(),
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let matched = self.peek() == Some(c);
+        if matched {
+            self.position += 1;
+        }
+        matched
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        let len = s.chars().count();
+        let matched = self
+            .chars
+            .get(self.position..self.position + len)
+            .is_some_and(|window| window.iter().copied().eq(s.chars()));
+        if matched {
+            self.position += len;
+        }
+        matched
+    }
+
+    /// 符号なしの整数（数字がなければNone）
+    fn number(&mut self) -> Result<Option<u32>, DiceError> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        let digits: String = self
+            .chars
+            .get(start..self.position)
+            .unwrap_or_default()
+            .iter()
+            .collect();
+        if digits.is_empty() {
+            return Ok(None);
+        }
+        if digits.len() > MAX_DIGITS {
+            return Err(DiceError::OutOfRange);
+        }
+        digits
+            .parse()
+            .map(Some)
+            .map_err(|_err| DiceError::OutOfRange)
+    }
+
+    /// `(1)`、`(-1)`、`1`、`-1`
+    fn bonus(&mut self) -> Result<i32, DiceError> {
+        let parenthesized = self.eat('(');
+        let negative = self.eat('-');
+        if !negative {
+            self.eat('+');
+        }
+        let bonus = match self.number()? {
+            Some(number) => i32::try_from(number).map_err(|_err| DiceError::OutOfRange)?,
+            None if parenthesized || negative => return Err(self.unexpected()),
+            None => 0,
+        };
+        if parenthesized && !self.eat(')') {
+            return Err(self.unexpected());
+        }
+        if bonus > MAX_BONUS_DICE {
+            return Err(DiceError::OutOfRange);
+        }
+        Ok(if negative { -bonus } else { bonus })
+    }
+
+    fn skill(&mut self, wide: bool) -> Result<Command, DiceError> {
+        let bonus = if wide { 0 } else { self.bonus()? };
+        let target = if self.eat_str("<=") {
+            Some(self.constant()?)
+        } else {
+            None
+        };
+        Ok(Command::Skill {
+            target,
+            bonus,
+            wide,
+        })
+    }
+
+    /// `2DM<=6`、`DM<=6`（`DM`で始まらなければ読み進めずにNone）
+    fn pool(&mut self) -> Result<Option<Command>, DiceError> {
+        let start = self.position;
+        let count = self.number()?;
+        if !self.eat_str("DM") {
+            self.position = start;
+            return Ok(None);
+        }
+        let count = count.unwrap_or(1);
+        if !(1..=MAX_DICE).contains(&count) {
+            return Err(DiceError::OutOfRange);
+        }
+        if !self.eat_str("<=") {
+            return Err(self.unexpected());
+        }
+        let target = self.constant()?;
+        Ok(Some(Command::Pool { count, target }))
+    }
+
+    fn sum(&mut self) -> Result<Command, DiceError> {
+        let terms = self.expression()?;
+        let comparison = Comparison::ALL
+            .iter()
+            .find(|(symbol, _)| self.eat_str(symbol))
+            .map(|(_, comparison)| *comparison);
+        let target = match comparison {
+            Some(comparison) => Some((comparison, self.constant()?)),
+            None => None,
+        };
+        Ok(Command::Sum { terms, target })
+    }
+
+    /// ダイスを含まない式（目標値）
+    fn constant(&mut self) -> Result<i64, DiceError> {
+        self.expression()?
+            .into_iter()
+            .map(|(negative, term)| match term {
+                Term::Number(number) if negative => Ok(-number),
+                Term::Number(number) => Ok(number),
+                Term::Dice { .. } => Err(DiceError::DiceInTarget),
+            })
+            .sum()
+    }
+
+    /// 項を`+`/`-`でつないだ式
+    fn expression(&mut self) -> Result<Vec<(bool, Term)>, DiceError> {
+        let mut terms = Vec::new();
+        let mut negative = self.eat('-');
+        loop {
+            terms.push((negative, self.term()?));
+            if self.eat('+') {
+                negative = false;
+            } else if self.eat('-') {
+                negative = true;
+            } else {
+                return Ok(terms);
+            }
+        }
+    }
+
+    /// `2D6`、`D6`、`3`
+    fn term(&mut self) -> Result<Term, DiceError> {
+        let number = self.number()?;
+        if !self.eat('D') {
+            return number
+                .map(|number| Term::Number(i64::from(number)))
+                .ok_or_else(|| self.unexpected());
+        }
+        let count = number.unwrap_or(1);
+        let sides = self.number()?.ok_or_else(|| self.unexpected())?;
+        if !(1..=MAX_DICE).contains(&count) || !(1..=MAX_SIDES).contains(&sides) {
+            return Err(DiceError::OutOfRange);
+        }
+        Ok(Term::Dice { count, sides })
+    }
+}
+
+impl Command {
+    /// ダイスを振る（`system`で技能ロールや特殊な出目の扱いが変わる）
+    pub fn roll(&self, system: GameSystem, rng: &mut DiceRng) -> Roll {
+        match self {
+            Self::Sum { terms, target } => self.roll_sum(terms, *target, system, rng),
+            Self::Skill {
+                target,
+                bonus,
+                wide,
+            } => self.roll_skill(*target, *bonus, *wide, system, rng),
+            Self::Pool { count, target } => self.roll_pool(*count, *target, rng),
+        }
+    }
+
+    fn roll_sum(
+        &self,
+        terms: &[(bool, Term)],
+        target: Option<(Comparison, i64)>,
+        system: GameSystem,
+        rng: &mut DiceRng,
+    ) -> Roll {
+        let mut total = 0;
+        let mut detail = String::new();
+        // ダイスの出目の合計（修正値を除く）
+        let mut natural = 0;
+        for (index, (negative, term)) in terms.iter().enumerate() {
+            let (value, part) = match term {
+                Term::Dice { count, sides } => {
+                    let rolls: Vec<i64> =
+                        (0..*count).map(|_| i64::from(rng.roll(*sides))).collect();
+                    let sum = rolls.iter().sum();
+                    let rolls: Vec<String> = rolls.iter().map(i64::to_string).collect();
+                    natural += sum;
+                    (sum, format!("{sum}[{}]", rolls.join(",")))
+                }
+                Term::Number(number) => (*number, number.to_string()),
+            };
+            if *negative {
+                detail.push('-');
+                total -= value;
+            } else {
+                if index > 0 {
+                    detail.push('+');
+                }
+                total += value;
+            }
+            detail.push_str(&part);
+        }
+
+        // 1個のダイスだけなら内訳は合計と同じなので省く
+        let single_die = matches!(terms, [(false, Term::Dice { count: 1, .. })]);
+        let steps = if single_die { Vec::new() } else { vec![detail] };
+
+        let verdict = target.map(|(comparison, target)| {
+            sum_verdict(terms, comparison, target, total, natural, system)
+        });
+
+        Roll {
+            command: self.clone(),
+            total,
+            steps,
+            verdict,
+        }
+    }
+
+    fn roll_skill(
+        &self,
+        target: Option<i64>,
+        bonus: i32,
+        wide: bool,
+        system: GameSystem,
+        rng: &mut DiceRng,
+    ) -> Roll {
+        // 1の位を1つ、10の位を(1 + ボーナス/ペナルティの数)個振る
+        let units = i64::from(rng.roll(10)) - 1;
+        let candidates: Vec<i64> = (0..=bonus.unsigned_abs())
+            .map(|_| {
+                let value = (i64::from(rng.roll(10)) - 1) * 10 + units;
+                if value == 0 { 100 } else { value }
+            })
+            .collect();
+        let total = if bonus >= 0 {
+            candidates.iter().copied().min()
+        } else {
+            candidates.iter().copied().max()
+        }
+        .unwrap_or(100);
+
+        let steps = if candidates.len() > 1 {
+            let candidates: Vec<String> = candidates.iter().map(i64::to_string).collect();
+            vec![candidates.join(", ")]
+        } else {
+            Vec::new()
+        };
+
+        let verdict = target.map(|target| skill_verdict(target, wide, total, system));
+
+        Roll {
+            command: self.clone(),
+            total,
+            steps,
+            verdict,
+        }
+    }
+
+    /// 合計は成功数
+    fn roll_pool(&self, count: u32, target: i64, rng: &mut DiceRng) -> Roll {
+        let rolls: Vec<u32> = (0..count).map(|_| rng.roll(10)).collect();
+        let total = rolls
+            .iter()
+            .map(|value| pool_successes(*value, target))
+            .sum();
+        let rolls: Vec<String> = rolls.iter().map(u32::to_string).collect();
+        Roll {
+            command: self.clone(),
+            total,
+            steps: vec![format!("[{}]", rolls.join(","))],
+            verdict: Some(pool_verdict(total)),
+        }
+    }
+}
+
+impl Command {
+    /// スロットの技能判定に使うシステムごとの標準のコマンド
+    ///
+    /// 7版は`CC`、6版は`CCB`、ソード・ワールド2.5は`2D6>=目標値`、エモクロアTRPGは`ダイス数DM<=目標値`、
+    /// それ以外は`1D100<=目標値`。
+    pub fn skill_check(system: GameSystem, check: &SkillCheck) -> Self {
+        let target = check.target;
+        match system {
+            GameSystem::Coc7 | GameSystem::Coc6 => Self::Skill {
+                target: Some(target),
+                bonus: 0,
+                wide: system == GameSystem::Coc6,
+            },
+            GameSystem::SwordWorld25 => Self::Sum {
+                terms: vec![(false, Term::Dice { count: 2, sides: 6 })],
+                target: Some((Comparison::Ge, target)),
+            },
+            GameSystem::Emoklore => Self::Pool {
+                count: check.dice.clamp(1, MAX_DICE),
+                target,
+            },
+            GameSystem::Generic => Self::Sum {
+                terms: vec![(
+                    false,
+                    Term::Dice {
+                        count: 1,
+                        sides: 100,
+                    },
+                )],
+                target: Some((Comparison::Le, target)),
+            },
+        }
+    }
+
+    /// 振った結果の値（修正値を含む合計、エモクロアTRPGは成功数）から判定する（手元で振った出目を入力する場合）
+    pub fn verdict_for(&self, total: i64, system: GameSystem) -> Option<Verdict> {
+        match self {
+            Self::Sum { terms, target } => {
+                let (comparison, target) = (*target)?;
+                // 修正値を除いた出目
+                let modifier: i64 = terms
+                    .iter()
+                    .map(|(negative, term)| match term {
+                        Term::Number(number) if *negative => -number,
+                        Term::Number(number) => *number,
+                        Term::Dice { .. } => 0,
+                    })
+                    .sum();
+                Some(sum_verdict(
+                    terms,
+                    comparison,
+                    target,
+                    total,
+                    total - modifier,
+                    system,
+                ))
+            }
+            Self::Skill { target, wide, .. } => {
+                Some(skill_verdict((*target)?, *wide, total, system))
+            }
+            Self::Pool { .. } => Some(pool_verdict(total)),
+        }
+    }
+}
+
+/// 加算ロールの判定（`natural`は修正値を除いた出目）
+fn sum_verdict(
+    terms: &[(bool, Term)],
+    comparison: Comparison,
+    target: i64,
+    total: i64,
+    natural: i64,
+    system: GameSystem,
+) -> Verdict {
+    // ソード・ワールド2.5の2D6は6ゾロで自動成功、1ゾロで自動失敗
+    let dice: Vec<&(bool, Term)> = terms
+        .iter()
+        .filter(|(_, term)| matches!(term, Term::Dice { .. }))
+        .collect();
+    let is_2d6 = matches!(
+        dice.as_slice(),
+        [(false, Term::Dice { count: 2, sides: 6 })]
+    );
+    if system == GameSystem::SwordWorld25 && is_2d6 && natural == 12 {
+        Verdict::new("自動成功", Outcome::Critical)
+    } else if system == GameSystem::SwordWorld25 && is_2d6 && natural == 2 {
+        Verdict::new("自動失敗", Outcome::Fumble)
+    } else if comparison.test(total, target) {
+        Verdict::new("成功", Outcome::Success)
+    } else {
+        Verdict::new("失敗", Outcome::Failure)
+    }
+}
+
+/// 技能ロールの判定（7版の`CC`以外は6版の判定）
+fn skill_verdict(target: i64, wide: bool, value: i64, system: GameSystem) -> Verdict {
+    if system == GameSystem::Coc7 && !wide {
+        coc7_verdict(value, target)
+    } else {
+        coc6_verdict(value, target, wide)
+    }
+}
+
+/// エモクロアTRPGの出目1つ分の成功数
+fn pool_successes(value: u32, target: i64) -> i64 {
+    if i64::from(value) > target {
+        if value >= POOL_FUMBLE { -1 } else { 0 }
+    } else if value <= POOL_CRITICAL {
+        2
+    } else {
+        1
+    }
+}
+
+/// エモクロアTRPGの成功数による判定
+fn pool_verdict(successes: i64) -> Verdict {
+    match successes {
+        ..0 => Verdict::new("ファンブル", Outcome::Fumble),
+        0 => Verdict::new("失敗", Outcome::Failure),
+        1 => Verdict::new("シングル", Outcome::Success),
+        2 => Verdict::new("ダブル", Outcome::Special),
+        3 => Verdict::new("トリプル", Outcome::Special),
+        4..10 => Verdict::new("ミラクル", Outcome::Critical),
+        _ => Verdict::new("カタストロフ", Outcome::Critical),
+    }
+}
+
+/// 新クトゥルフ神話TRPG（7版）の成功度
+fn coc7_verdict(value: i64, target: i64) -> Verdict {
+    let fumble = if target < 50 {
+        value >= 96
+    } else {
+        value == 100
+    };
+    if value == 1 {
+        Verdict::new("クリティカル", Outcome::Critical)
+    } else if fumble {
+        Verdict::new("ファンブル", Outcome::Fumble)
+    } else if value <= target / 5 {
+        Verdict::new("イクストリーム", Outcome::Special)
+    } else if value <= target / 2 {
+        Verdict::new("ハード", Outcome::Special)
+    } else if value <= target {
+        Verdict::new("レギュラー", Outcome::Success)
+    } else {
+        Verdict::new("失敗", Outcome::Failure)
+    }
+}
+
+/// クトゥルフ神話TRPG（6版）の判定（`wide`なら1〜5/96〜100、それ以外は1/100）
+fn coc6_verdict(value: i64, target: i64, wide: bool) -> Verdict {
+    let (critical, fumble) = if wide { (5, 96) } else { (1, 100) };
+    if value <= target {
+        if value <= critical {
+            Verdict::new("決定的成功", Outcome::Critical)
+        } else if value <= target / 5 {
+            Verdict::new("スペシャル", Outcome::Special)
+        } else {
+            Verdict::new("成功", Outcome::Success)
+        }
+    } else if value >= fumble {
+        Verdict::new("致命的失敗", Outcome::Fumble)
+    } else {
+        Verdict::new("失敗", Outcome::Failure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dice(count: u32, sides: u32) -> (bool, Term) {
+        (false, Term::Dice { count, sides })
+    }
+
+    /// 判定名（目標値のないコマンドはNone）
+    fn verdict_name(input: &str, total: i64, system: GameSystem) -> Option<&'static str> {
+        parse(input)
+            .expect("command should parse")
+            .verdict_for(total, system)
+            .map(|verdict| verdict.name)
+    }
+
+    #[test]
+    fn parses_sum_with_target() {
+        assert_eq!(
+            parse("1d100<=65").expect("1d100<=65"),
+            Command::Sum {
+                terms: vec![dice(1, 100)],
+                target: Some((Comparison::Le, 65)),
+            }
+        );
+        assert_eq!(
+            parse("2d6+3>=7").expect("2d6+3>=7"),
+            Command::Sum {
+                terms: vec![dice(2, 6), (false, Term::Number(3))],
+                target: Some((Comparison::Ge, 7)),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_skill_rolls() {
+        assert_eq!(
+            parse("CC<=50").expect("CC<=50"),
+            Command::Skill {
+                target: Some(50),
+                bonus: 0,
+                wide: false,
+            }
+        );
+        assert_eq!(
+            parse("CCB<=40").expect("CCB<=40"),
+            Command::Skill {
+                target: Some(40),
+                bonus: 0,
+                wide: true,
+            }
+        );
+        assert_eq!(
+            parse("CC(-1)").expect("CC(-1)"),
+            Command::Skill {
+                target: None,
+                bonus: -1,
+                wide: false,
+            }
+        );
+    }
+
+    #[test]
+    fn parses_full_width_lower_case_and_comment() {
+        assert_eq!(
+            parse("ｃｃｂ＜＝４０ 目星").expect("full width"),
+            parse("CCB<=40").expect("half width"),
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(matches!(parse(""), Err(DiceError::Empty)), "empty");
+        assert!(matches!(parse("   "), Err(DiceError::Empty)), "blank");
+        assert!(
+            matches!(
+                parse("2d6+"),
+                Err(DiceError::Unexpected { found: None, .. })
+            ),
+            "dangling operator"
+        );
+        assert!(
+            matches!(
+                parse("1d100<=6x"),
+                Err(DiceError::Unexpected {
+                    position: 8,
+                    found: Some('X')
+                })
+            ),
+            "trailing garbage"
+        );
+        assert!(
+            matches!(parse("1d100<=1d6"), Err(DiceError::DiceInTarget)),
+            "dice in target"
+        );
+        assert!(
+            matches!(parse("101d6"), Err(DiceError::OutOfRange)),
+            "too many dice"
+        );
+        assert!(
+            matches!(parse("CC(3)<=50"), Err(DiceError::OutOfRange)),
+            "too many bonus dice"
+        );
+        assert!(
+            matches!(parse("CC(1<=50"), Err(DiceError::Unexpected { .. })),
+            "unclosed bonus"
+        );
+    }
+
+    #[test]
+    fn coc7_fumble_depends_on_target() {
+        let system = GameSystem::Coc7;
+        assert_eq!(verdict_name("CC<=49", 95, system), Some("失敗"));
+        assert_eq!(verdict_name("CC<=49", 96, system), Some("ファンブル"));
+        assert_eq!(verdict_name("CC<=50", 96, system), Some("失敗"));
+        assert_eq!(verdict_name("CC<=50", 99, system), Some("失敗"));
+        assert_eq!(verdict_name("CC<=50", 100, system), Some("ファンブル"));
+    }
+
+    #[test]
+    fn coc7_success_levels() {
+        let system = GameSystem::Coc7;
+        assert_eq!(verdict_name("CC<=50", 1, system), Some("クリティカル"));
+        assert_eq!(verdict_name("CC<=50", 10, system), Some("イクストリーム"));
+        assert_eq!(verdict_name("CC<=50", 11, system), Some("ハード"));
+        assert_eq!(verdict_name("CC<=50", 25, system), Some("ハード"));
+        assert_eq!(verdict_name("CC<=50", 26, system), Some("レギュラー"));
+        assert_eq!(verdict_name("CC<=50", 50, system), Some("レギュラー"));
+        assert_eq!(verdict_name("CC<=50", 51, system), Some("失敗"));
+        assert_eq!(verdict_name("CC", 1, system), None, "no target");
+    }
+
+    #[test]
+    fn coc6_ccb_boundaries() {
+        let system = GameSystem::Coc6;
+        assert_eq!(verdict_name("CCB<=40", 5, system), Some("決定的成功"));
+        assert_eq!(verdict_name("CCB<=40", 6, system), Some("スペシャル"));
+        assert_eq!(verdict_name("CCB<=40", 8, system), Some("スペシャル"));
+        assert_eq!(verdict_name("CCB<=40", 9, system), Some("成功"));
+        assert_eq!(verdict_name("CCB<=40", 95, system), Some("失敗"));
+        assert_eq!(verdict_name("CCB<=40", 96, system), Some("致命的失敗"));
+        assert_eq!(verdict_name("CCB<=40", 100, system), Some("致命的失敗"));
+    }
+
+    #[test]
+    fn coc6_cc_boundaries() {
+        let system = GameSystem::Coc6;
+        assert_eq!(verdict_name("CC<=40", 1, system), Some("決定的成功"));
+        assert_eq!(verdict_name("CC<=40", 2, system), Some("スペシャル"));
+        assert_eq!(verdict_name("CC<=40", 99, system), Some("失敗"));
+        assert_eq!(verdict_name("CC<=40", 100, system), Some("致命的失敗"));
+    }
+
+    #[test]
+    fn sword_world_automatic_success_and_failure() {
+        let system = GameSystem::SwordWorld25;
+        assert_eq!(verdict_name("2D6>=20", 12, system), Some("自動成功"));
+        assert_eq!(verdict_name("2D6>=0", 2, system), Some("自動失敗"));
+        // 修正値を除いた出目で判定する
+        assert_eq!(verdict_name("2D6+5>=30", 17, system), Some("自動成功"));
+        assert_eq!(verdict_name("2D6+5>=0", 7, system), Some("自動失敗"));
+        assert_eq!(verdict_name("2D6>=7", 7, system), Some("成功"));
+        assert_eq!(verdict_name("2D6>=7", 6, system), Some("失敗"));
+        // ほかのシステムでは通常の判定
+        assert_eq!(
+            verdict_name("2D6>=20", 12, GameSystem::Generic),
+            Some("失敗")
+        );
+    }
+
+    #[test]
+    fn parses_emoklore_pool() {
+        assert_eq!(
+            parse("2dm<=6").expect("2dm<=6"),
+            Command::Pool {
+                count: 2,
+                target: 6
+            }
+        );
+        assert_eq!(
+            parse("DM<=4").expect("DM<=4"),
+            Command::Pool {
+                count: 1,
+                target: 4
+            }
+        );
+        assert!(
+            matches!(parse("2DM"), Err(DiceError::Unexpected { found: None, .. })),
+            "pool without target"
+        );
+        assert!(is_command("3DM<=7 調査"), "pool is a command line");
+    }
+
+    #[test]
+    fn emoklore_pool_counts_successes() {
+        assert_eq!(pool_successes(1, 6), 2, "1 is a double success");
+        assert_eq!(pool_successes(6, 6), 1, "at target");
+        assert_eq!(pool_successes(7, 6), 0, "over target");
+        assert_eq!(pool_successes(10, 6), -1, "10 over target");
+        assert_eq!(pool_successes(10, 10), 1, "10 within target");
+
+        let system = GameSystem::Emoklore;
+        assert_eq!(verdict_name("2DM<=6", -1, system), Some("ファンブル"));
+        assert_eq!(verdict_name("2DM<=6", 0, system), Some("失敗"));
+        assert_eq!(verdict_name("2DM<=6", 1, system), Some("シングル"));
+        assert_eq!(verdict_name("2DM<=6", 2, system), Some("ダブル"));
+        assert_eq!(verdict_name("2DM<=6", 3, system), Some("トリプル"));
+        assert_eq!(verdict_name("2DM<=6", 4, system), Some("ミラクル"));
+        assert_eq!(verdict_name("2DM<=6", 10, system), Some("カタストロフ"));
+    }
+
+    #[test]
+    fn skill_check_commands_per_system() {
+        let check = SkillCheck {
+            skill: String::from("調査"),
+            target: 6,
+            dice: 3,
+        };
+        let command = |system| Command::skill_check(system, &check).to_string();
+        assert_eq!(command(GameSystem::Coc6), "CCB<=6");
+        assert_eq!(command(GameSystem::Coc7), "CC<=6");
+        assert_eq!(command(GameSystem::SwordWorld25), "2D6>=6");
+        assert_eq!(command(GameSystem::Emoklore), "3DM<=6");
+        assert_eq!(command(GameSystem::Generic), "1D100<=6");
+    }
+
+    #[test]
+    fn emoklore_roll_total_matches_dice() {
+        let roll =
+            roll("5DM<=5", GameSystem::Emoklore, &mut DiceRng::seeded(3)).expect("pool roll");
+        let expected: i64 = roll
+            .steps
+            .first()
+            .expect("dice step")
+            .trim_matches(['[', ']'])
+            .split(',')
+            .map(|value| pool_successes(value.parse().expect("die value"), 5))
+            .sum();
+        assert_eq!(roll.total, expected, "{}", roll.text());
+        assert!(roll.verdict.is_some(), "pool always has a verdict");
+    }
+
+    #[test]
+    fn seeded_rolls_are_reproducible() {
+        let first = roll("2D6+3>=7", GameSystem::Generic, &mut DiceRng::seeded(42))
+            .expect("roll should parse");
+        let second = roll("2D6+3>=7", GameSystem::Generic, &mut DiceRng::seeded(42))
+            .expect("roll should parse");
+        assert_eq!(first.text(), second.text());
+        assert!((5..=15).contains(&first.total), "total {}", first.total);
+    }
+
+    #[test]
+    fn rolls_stay_in_range() {
+        let mut rng = DiceRng::seeded(7);
+        for _ in 0..1000 {
+            let value = rng.roll(6);
+            assert!((1..=6).contains(&value), "d6 rolled {value}");
+        }
+        for _ in 0..200 {
+            let skill = roll("CC(-2)<=50", GameSystem::Coc7, &mut rng).expect("skill roll");
+            assert!(
+                (1..=100).contains(&skill.total),
+                "CC rolled {}",
+                skill.total
+            );
+        }
+    }
+}
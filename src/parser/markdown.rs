@@ -0,0 +1,522 @@
+use crate::app::Player;
+use crate::scene::{Id, Mode, Scene, TextSlot};
+use std::borrow::Cow;
+
+/// インポート時の警告（行番号は1始まり）
+#[derive(Clone, Debug)]
+pub struct ImportWarning {
+    pub line: usize,
+    pub message: String,
+}
+
+/// スロット見出しの先頭に付けて、話者がプレイヤーであることを表す記号（`### @プレイヤー名`）
+pub const SPEAKER_MARKER: char = '@';
+
+/// Markdownインポートの結果
+pub struct ImportResult {
+    pub scenes: Vec<Scene>,
+    pub warnings: Vec<ImportWarning>,
+}
+
+/// Markdownのシナリオをシーン一覧に変換する
+///
+/// * `# 見出し` → シーン
+/// * `## 見出し` → モード（`modes`の名前と照合）
+/// * `### 見出し` → ラベル付きの新しいスロット
+/// * `### @プレイヤー名` → `players`のそのプレイヤーが話者の新しいスロット
+///   （一致するプレイヤーがいなければ`@`を含めた見出しをラベルにする）
+/// * `---` → ラベルなしの新しいスロット
+/// * `【判定名】` → 以降の行をその判定のテキストにする（`Mode.judges`と照合）
+/// * `\`で始まる行 → 先頭の`\`を除いた本文（見出しや区切りと同じ形の本文を書くため）
+///
+/// 判定ラベルのない本文は先頭の判定に入る。同じスロット内で既に書かれた
+/// 判定ラベルが再び現れた場合は新しいスロットとして扱う。
+/// 照合できなかった見出しやラベル、およびその配下の本文は警告として報告する。
+pub fn parse(source: &str, modes: &[Mode], players: &[Player]) -> ImportResult {
+    let mut parser = Parser::new(modes, players);
+    for (index, line) in source.lines().enumerate() {
+        parser.line(index + 1, line);
+    }
+    parser.finish()
+}
+
+/// 現在の書き込み先
+enum Target {
+    /// 最初のシーン見出しより前
+    BeforeScene,
+    /// 照合できなかったモード/判定の配下（本文は破棄して警告する）
+    Skipped,
+    /// `modes[mode_index]`の最後のスロットの`judges[judge_index]`のテキスト
+    Judge {
+        mode_index: usize,
+        judge_index: usize,
+    },
+}
+
+struct Parser<'a> {
+    modes: &'a [Mode],
+    players: &'a [Player],
+    scenes: Vec<Scene>,
+    warnings: Vec<ImportWarning>,
+    target: Target,
+    /// 現在のモード（シーン見出し直後や不明なモードの場合はNone）
+    mode_index: Option<usize>,
+    /// 次の本文で新しいスロットを作るか
+    new_slot: bool,
+    /// 次に作るスロットのラベル
+    pending_label: Option<String>,
+    /// 次に作るスロットの話者
+    pending_speaker: Option<Id>,
+    /// 破棄した本文の先頭行（まとめて1回だけ警告する）
+    skipped_from: Option<usize>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(modes: &'a [Mode], players: &'a [Player]) -> Self {
+        Self {
+            modes,
+            players,
+            scenes: Vec::new(),
+            warnings: Vec::new(),
+            target: Target::BeforeScene,
+            mode_index: None,
+            new_slot: true,
+            pending_label: None,
+            pending_speaker: None,
+            skipped_from: None,
+        }
+    }
+
+    fn warn(&mut self, line: usize, message: String) {
+        self.warnings.push(ImportWarning { line, message });
+    }
+
+    fn line(&mut self, line_no: usize, line: &str) {
+        if let Some(escaped) = line.strip_prefix('\\') {
+            self.text(line_no, escaped);
+            return;
+        }
+        let trimmed = line.trim();
+
+        if let Some(title) = heading(trimmed, 1) {
+            self.start_scene(line_no, title);
+        } else if let Some(name) = heading(trimmed, 2) {
+            self.start_mode(line_no, name);
+        } else if let Some(label) = heading(trimmed, 3) {
+            self.start_slot(line_no, Some(label.to_owned()));
+        } else if is_slot_separator(trimmed) {
+            self.start_slot(line_no, None);
+        } else if let Some(judge) = judge_label(trimmed) {
+            self.start_judge(line_no, judge);
+        } else {
+            self.text(line_no, line);
+        }
+    }
+
+    fn start_scene(&mut self, line_no: usize, title: &str) {
+        self.flush_skipped();
+        self.scenes.push(Scene {
+            id: Id::new(),
+            title: title.to_owned(),
+            mode_id: Id::default(),
+            contents: Default::default(),
+            layout_cache: None,
+        });
+        if title.is_empty() {
+            self.warn(line_no, String::from("シーン名が空です"));
+        }
+        self.mode_index = None;
+        self.target = Target::Skipped;
+        self.new_slot = true;
+        self.pending_label = None;
+    }
+
+    fn start_mode(&mut self, line_no: usize, name: &str) {
+        self.flush_skipped();
+        if self.scenes.is_empty() {
+            self.warn(
+                line_no,
+                format!("モード「{name}」がシーン見出し(#)より前にあります"),
+            );
+            self.target = Target::BeforeScene;
+            return;
+        }
+
+        self.new_slot = true;
+        self.pending_label = None;
+        if let Some(mode_index) = self.modes.iter().position(|mode| mode.name == name) {
+            self.mode_index = Some(mode_index);
+            self.target = Target::Judge {
+                mode_index,
+                judge_index: 0,
+            };
+        } else {
+            self.warn(line_no, format!("不明なモード「{name}」"));
+            self.mode_index = None;
+            self.target = Target::Skipped;
+        }
+    }
+
+    fn start_slot(&mut self, line_no: usize, label: Option<String>) {
+        self.flush_skipped();
+        let Some(mode_index) = self.mode_index else {
+            self.warn(
+                line_no,
+                String::from("モード見出し(##)の外にあるスロットです"),
+            );
+            self.target = Target::Skipped;
+            return;
+        };
+        self.new_slot = true;
+        self.pending_label = label.filter(|label| !label.is_empty());
+        self.pending_speaker = self
+            .pending_label
+            .as_deref()
+            .and_then(|label| label.strip_prefix(SPEAKER_MARKER))
+            .and_then(|name| {
+                self.players
+                    .iter()
+                    .find(|player| player.name == name.trim())
+            })
+            .map(|player| player.id);
+        if self.pending_speaker.is_some() {
+            self.pending_label = None;
+        }
+        self.target = Target::Judge {
+            mode_index,
+            judge_index: 0,
+        };
+    }
+
+    fn start_judge(&mut self, line_no: usize, judge: &str) {
+        self.flush_skipped();
+        let Some(mode_index) = self.mode_index else {
+            self.warn(
+                line_no,
+                format!("判定ラベル「{judge}」がモード見出し(##)の外にあります"),
+            );
+            self.target = Target::Skipped;
+            return;
+        };
+
+        let Some(mode) = self.modes.get(mode_index) else {
+            return;
+        };
+        let Some(judge_index) = mode.judges.iter().position(|j| j.name == judge) else {
+            self.warn(
+                line_no,
+                format!(
+                    "判定ラベル「{judge}」はモード「{}」にありません（{}）",
+                    mode.name,
+                    mode.judges
+                        .iter()
+                        .map(|judge| judge.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join("/")
+                ),
+            );
+            self.target = Target::Skipped;
+            return;
+        };
+
+        // 同じスロットで既に書かれた判定なら次のスロットに移る
+        let already_written = self
+            .current_slot(mode_index)
+            .zip(mode.judges.get(judge_index))
+            .is_some_and(|(slot, judge)| !slot.text(judge.id).is_empty());
+        if already_written {
+            self.new_slot = true;
+        }
+
+        self.target = Target::Judge {
+            mode_index,
+            judge_index,
+        };
+    }
+
+    fn text(&mut self, line_no: usize, line: &str) {
+        match self.target {
+            Target::BeforeScene | Target::Skipped => {
+                if !line.trim().is_empty() && self.skipped_from.is_none() {
+                    self.skipped_from = Some(line_no);
+                }
+            }
+            Target::Judge {
+                mode_index,
+                judge_index,
+            } => {
+                // スロットの先頭の空行は無視する
+                if self.new_slot && line.trim().is_empty() {
+                    return;
+                }
+                if self.new_slot {
+                    self.push_slot(mode_index);
+                }
+                let Some(judge_id) = self
+                    .modes
+                    .get(mode_index)
+                    .and_then(|mode| mode.judges.get(judge_index))
+                    .map(|judge| judge.id)
+                else {
+                    return;
+                };
+                let Some(slot) = self.current_slot_mut(mode_index) else {
+                    return;
+                };
+                let text = slot.text_mut(judge_id);
+                if !text.is_empty() {
+                    text.push('\n');
+                }
+                text.push_str(line);
+            }
+        }
+    }
+
+    /// 現在のシーンのモードにスロットを追加
+    fn push_slot(&mut self, mode_index: usize) {
+        let Some(mode) = self.modes.get(mode_index) else {
+            return;
+        };
+        let mut slot = TextSlot::new_empty(&mode.judges);
+        if let Some(label) = self.pending_label.take() {
+            slot = slot.with_label(label);
+        }
+        slot.speaker = self.pending_speaker.take();
+        if let Some(scene) = self.scenes.last_mut() {
+            scene.slots_mut(mode.id).push(slot);
+        }
+        self.new_slot = false;
+    }
+
+    fn current_slot(&self, mode_index: usize) -> Option<&TextSlot> {
+        if self.new_slot {
+            return None;
+        }
+        let mode = self.modes.get(mode_index)?;
+        self.scenes.last()?.slots(mode.id).last()
+    }
+
+    fn current_slot_mut(&mut self, mode_index: usize) -> Option<&mut TextSlot> {
+        let mode = self.modes.get(mode_index)?;
+        self.scenes.last_mut()?.slots_mut(mode.id).last_mut()
+    }
+
+    /// 破棄した本文があれば警告を追加
+    fn flush_skipped(&mut self) {
+        if let Some(line) = self.skipped_from.take() {
+            self.warn(line, String::from("取り込み先のない本文を無視しました"));
+        }
+    }
+
+    fn finish(mut self) -> ImportResult {
+        self.flush_skipped();
+        if self.scenes.is_empty() {
+            self.warn(1, String::from("シーン見出し(#)が見つかりません"));
+        }
+
+        for scene in &mut self.scenes {
+            for slot in scene.contents.values_mut().flatten() {
+                for text in slot.texts.values_mut() {
+                    *text = text.trim_end().to_owned();
+                }
+            }
+            // 最初に本文のあるモードを選択状態にする
+            scene.mode_id = self
+                .modes
+                .iter()
+                .find(|mode| !scene.slots(mode.id).is_empty())
+                .or(self.modes.first())
+                .map(|mode| mode.id)
+                .unwrap_or_default();
+        }
+
+        self.warnings.sort_by_key(|warning| warning.line);
+        ImportResult {
+            scenes: self.scenes,
+            warnings: self.warnings,
+        }
+    }
+}
+
+/// 本文の行を、見出しや区切りとして読まれないよう必要なら`\`で始まる形にする
+pub fn escape_line(line: &str) -> Cow<'_, str> {
+    let trimmed = line.trim();
+    let structural = (1..=3).any(|level| heading(trimmed, level).is_some())
+        || is_slot_separator(trimmed)
+        || judge_label(trimmed).is_some();
+    if structural || line.starts_with('\\') {
+        Cow::Owned(format!("\\{line}"))
+    } else {
+        Cow::Borrowed(line)
+    }
+}
+
+/// `level`個の`#`で始まる見出しなら見出し文字列を返す
+fn heading(line: &str, level: usize) -> Option<&str> {
+    let rest = line.strip_prefix(&"#".repeat(level))?;
+    if rest.starts_with('#') {
+        return None;
+    }
+    if rest.is_empty() {
+        return Some(rest);
+    }
+    rest.strip_prefix([' ', '\t', '　']).map(str::trim)
+}
+
+/// スロット区切り（`---`、`***`など3文字以上の水平線）か
+fn is_slot_separator(line: &str) -> bool {
+    line.len() >= 3 && (line.chars().all(|c| c == '-') || line.chars().all(|c| c == '*'))
+}
+
+/// `【判定名】`だけの行なら判定名を返す
+fn judge_label(line: &str) -> Option<&str> {
+    let name = line.strip_prefix('【')?.strip_suffix('】')?.trim();
+    (!name.is_empty() && !name.contains(['【', '】'])).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::GameSystem;
+
+    /// モード名と判定名からスロットのテキストを取り出す
+    fn text_of<'a>(
+        scene: &'a Scene,
+        modes: &[Mode],
+        mode_name: &str,
+        slot_index: usize,
+        judge_name: &str,
+    ) -> &'a str {
+        let mode = modes
+            .iter()
+            .find(|mode| mode.name == mode_name)
+            .expect("モードがある");
+        let judge = mode
+            .judges
+            .iter()
+            .find(|judge| judge.name == judge_name)
+            .expect("判定がある");
+        scene
+            .slots(mode.id)
+            .get(slot_index)
+            .map_or("", |slot| slot.text(judge.id))
+    }
+
+    #[test]
+    fn parses_scenes_modes_slots_and_judges() {
+        let modes = GameSystem::Generic.modes();
+        let source = "# 導入\n\n## 地の文\n\n本文1\n\n---\n\n本文2\n\n## 探索\n\n### 図書館\n【成功】\n見つけた\n【失敗】\n見つからない\n";
+        let result = parse(source, &modes, &[]);
+        assert!(
+            result.warnings.is_empty(),
+            "警告はない: {:?}",
+            result.warnings
+        );
+        let scene = result.scenes.first().expect("シーンが1つある");
+        assert_eq!(scene.title, "導入", "シーン名");
+        assert_eq!(
+            text_of(scene, &modes, "地の文", 0, "本文"),
+            "本文1",
+            "1つ目のスロット"
+        );
+        assert_eq!(
+            text_of(scene, &modes, "地の文", 1, "本文"),
+            "本文2",
+            "区切りの後のスロット"
+        );
+        assert_eq!(
+            text_of(scene, &modes, "探索", 0, "成功"),
+            "見つけた",
+            "成功のテキスト"
+        );
+        assert_eq!(
+            text_of(scene, &modes, "探索", 0, "失敗"),
+            "見つからない",
+            "失敗のテキスト"
+        );
+    }
+
+    #[test]
+    fn escaped_lines_are_body_text() {
+        let modes = GameSystem::Generic.modes();
+        let source = "# シーン\n## 探索\n【成功】\n\\# 見出しではない\n\\---\n\\【失敗】\n\\\\先頭の円記号\n";
+        let result = parse(source, &modes, &[]);
+        assert!(
+            result.warnings.is_empty(),
+            "警告はない: {:?}",
+            result.warnings
+        );
+        assert_eq!(
+            result.scenes.len(),
+            1,
+            "エスケープした見出しでシーンは増えない"
+        );
+        let scene = result.scenes.first().expect("シーンが1つある");
+        let mode = modes
+            .iter()
+            .find(|mode| mode.name == "探索")
+            .expect("モードがある");
+        assert_eq!(
+            scene.slots(mode.id).len(),
+            1,
+            "エスケープした区切りでスロットは増えない"
+        );
+        assert_eq!(
+            text_of(scene, &modes, "探索", 0, "成功"),
+            "# 見出しではない\n---\n【失敗】\n\\先頭の円記号",
+            "先頭の`\\`だけを取り除く"
+        );
+        assert_eq!(
+            text_of(scene, &modes, "探索", 0, "失敗"),
+            "",
+            "判定ラベルとして扱わない"
+        );
+    }
+
+    #[test]
+    fn unknown_mode_is_reported() {
+        let modes = GameSystem::Generic.modes();
+        let result = parse("# シーン\n## 戦闘\n本文\n", &modes, &[]);
+        assert_eq!(
+            result.warnings.len(),
+            2,
+            "不明なモードと無視した本文: {:?}",
+            result.warnings
+        );
+    }
+
+    #[test]
+    fn speaker_headings_link_players() {
+        let modes = GameSystem::Generic.modes();
+        let players = [Player {
+            name: String::from("KP"),
+            ..Player::default()
+        }];
+        let source = "# シーン\n## 探索\n### @KP\n本文1\n### @不明\n本文2\n### 図書館\n本文3\n";
+        let result = parse(source, &modes, &players);
+        assert!(
+            result.warnings.is_empty(),
+            "警告はない: {:?}",
+            result.warnings
+        );
+        let mode = modes
+            .iter()
+            .find(|mode| mode.name == "探索")
+            .expect("モードがある");
+        let slots = result
+            .scenes
+            .first()
+            .map(|scene| scene.slots(mode.id))
+            .expect("シーンが1つある");
+        let speakers: Vec<(Option<Id>, Option<&str>)> = slots
+            .iter()
+            .map(|slot| (slot.speaker, slot.label.as_deref()))
+            .collect();
+        let kp = players.first().map(|player| player.id);
+        assert_eq!(
+            speakers,
+            [(kp, None), (None, Some("@不明")), (None, Some("図書館"))],
+            "一致するプレイヤーだけ話者にし、ほかはラベルにする"
+        );
+    }
+}
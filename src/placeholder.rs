@@ -0,0 +1,236 @@
+use crate::app::Player;
+use crate::scenario::{NARRATION_PLAYER, Scenario};
+use std::borrow::Cow;
+
+/// 変数の値に含まれるプレースホルダーを展開する深さの上限（循環参照対策）
+const MAX_DEPTH: usize = 8;
+
+/// シナリオで定義する変数（本文中の`{名前}`をコピー時に値に置き換える）
+#[derive(serde::Deserialize, serde::Serialize, Clone, Default, PartialEq, Eq)]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+}
+
+/// プレースホルダーの置き換え先
+///
+/// 次の順に探す:
+/// 1. シナリオの変数
+/// 2. `{PC1}`、`{PC2}`...: プレイヤー一覧の順番（地の文のプレイヤーは数えない）
+/// 3. `{探偵}`など: 同じ名前のプレイヤー
+///
+/// プレイヤーはキャラクター名があればキャラクター名、なければプレイヤー名に置き換える。
+#[derive(Clone, Copy)]
+pub struct Bindings<'a> {
+    pub players: &'a [Player],
+    pub variables: &'a [Variable],
+}
+
+impl<'a> Bindings<'a> {
+    fn lookup(&self, name: &str) -> Option<&'a str> {
+        if let Some(variable) = self.variables.iter().find(|variable| variable.name == name) {
+            return Some(&variable.value);
+        }
+        let player = match name
+            .strip_prefix("PC")
+            .and_then(|n| n.parse::<usize>().ok())
+        {
+            Some(number) => self
+                .players
+                .iter()
+                .filter(|player| player.name != NARRATION_PLAYER)
+                .nth(number.checked_sub(1)?),
+            None => self.players.iter().find(|player| player.name == name),
+        }?;
+        Some(if player.character.is_empty() {
+            &player.name
+        } else {
+            &player.character
+        })
+    }
+}
+
+/// 置き換えの結果
+pub struct Resolved<'a> {
+    pub text: Cow<'a, str>,
+    /// 定義されていないプレースホルダー名（出現順、重複なし）
+    pub undefined: Vec<String>,
+}
+
+/// 文字列中の`{名前}`を置き換える
+///
+/// `{{`、`}}`はそれぞれ`{`、`}`になる。定義されていない名前は`{名前}`のまま残す。
+pub fn resolve<'a>(text: &'a str, bindings: Bindings<'_>) -> Resolved<'a> {
+    if !text.contains(['{', '}']) {
+        return Resolved {
+            text: Cow::Borrowed(text),
+            undefined: Vec::new(),
+        };
+    }
+    let mut resolved = String::with_capacity(text.len());
+    let mut undefined = Vec::new();
+    expand(text, bindings, 0, &mut resolved, &mut undefined);
+    Resolved {
+        text: Cow::Owned(resolved),
+        undefined,
+    }
+}
+
+fn expand(
+    text: &str,
+    bindings: Bindings<'_>,
+    depth: usize,
+    out: &mut String,
+    undefined: &mut Vec<String>,
+) {
+    let mut rest = text;
+    while let Some(start) = rest.find(['{', '}']) {
+        let (before, tail) = rest.split_at(start);
+        out.push_str(before);
+        if let Some(tail) = tail.strip_prefix("{{") {
+            out.push('{');
+            rest = tail;
+            continue;
+        }
+        if let Some(tail) = tail.strip_prefix('}') {
+            // `}}`は`}`、対応する`{`のない`}`はそのまま
+            out.push('}');
+            rest = tail.strip_prefix('}').unwrap_or(tail);
+            continue;
+        }
+        let inner = tail.strip_prefix('{').unwrap_or(tail);
+        // 閉じられていない/改行をまたぐ`{`はそのまま
+        let Some((name, after)) = inner
+            .split_once('}')
+            .filter(|(name, _)| !name.is_empty() && !name.contains(['{', '\n']))
+        else {
+            out.push('{');
+            rest = inner;
+            continue;
+        };
+        let name = name.trim();
+        match bindings.lookup(name) {
+            Some(value) if depth < MAX_DEPTH => expand(value, bindings, depth + 1, out, undefined),
+            Some(value) => out.push_str(value),
+            None => {
+                out.push('{');
+                out.push_str(name);
+                out.push('}');
+                if !undefined.iter().any(|undefined| undefined == name) {
+                    undefined.push(name.to_owned());
+                }
+            }
+        }
+        rest = after;
+    }
+    out.push_str(rest);
+}
+
+/// シナリオ全体で使われている未定義のプレースホルダー名（出現順、重複なし）
+pub fn undefined_names(scenario: &Scenario) -> Vec<String> {
+    let bindings = Bindings {
+        players: &scenario.player,
+        variables: &scenario.variables,
+    };
+    let mut names: Vec<String> = Vec::new();
+    let texts = scenario
+        .scenes
+        .iter()
+        .flat_map(|scene| scene.contents.values().flatten())
+        .flat_map(|slot| slot.texts.values());
+    for text in texts {
+        for name in resolve(text, bindings).undefined {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::default_players;
+
+    fn variable(name: &str, value: &str) -> Variable {
+        Variable {
+            name: String::from(name),
+            value: String::from(value),
+        }
+    }
+
+    #[test]
+    fn pc_numbers_skip_narration() {
+        let mut players = default_players();
+        if let Some(detective) = players.get_mut(1) {
+            detective.character = String::from("明智");
+        }
+        let bindings = Bindings {
+            players: &players,
+            variables: &[],
+        };
+        assert_eq!(
+            resolve("{PC1}と{PC2}", bindings).text,
+            "明智と助手",
+            "地の文を除いて数え、キャラクター名があればキャラクター名"
+        );
+        assert_eq!(resolve("{探偵}", bindings).text, "明智", "名前でも引ける");
+        let resolved = resolve("{PC0}{PC5}", bindings);
+        assert_eq!(resolved.text, "{PC0}{PC5}", "範囲外の番号は残す");
+        assert_eq!(resolved.undefined, ["PC0", "PC5"], "未定義として報告する");
+    }
+
+    #[test]
+    fn variables_take_priority_and_expand_recursively() {
+        let players = default_players();
+        let variables = [
+            variable("PC1", "上書き"),
+            variable("場所", "{町}の図書館"),
+            variable("町", "アーカム"),
+            variable("循環", "{循環}"),
+        ];
+        let bindings = Bindings {
+            players: &players,
+            variables: &variables,
+        };
+        assert_eq!(resolve("{PC1}", bindings).text, "上書き", "変数が優先");
+        assert_eq!(
+            resolve("{ 場所 }", bindings).text,
+            "アーカムの図書館",
+            "値の中も展開する"
+        );
+        assert_eq!(
+            resolve("{循環}", bindings).text,
+            "{循環}",
+            "循環参照は深さの上限で止まる"
+        );
+    }
+
+    #[test]
+    fn braces_are_escaped_or_kept() {
+        let bindings = Bindings {
+            players: &[],
+            variables: &[],
+        };
+        assert_eq!(
+            resolve("{{x}}", bindings).text,
+            "{x}",
+            "二重の括弧は括弧1つ"
+        );
+        assert_eq!(
+            resolve("a}b{c", bindings).text,
+            "a}b{c",
+            "対応しない括弧はそのまま"
+        );
+        assert_eq!(
+            resolve("{a\nb}", bindings).text,
+            "{a\nb}",
+            "改行をまたがない"
+        );
+        assert!(
+            matches!(resolve("括弧なし", bindings).text, Cow::Borrowed(_)),
+            "置き換えがなければ複製しない"
+        );
+    }
+}
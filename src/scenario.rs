@@ -0,0 +1,587 @@
+use crate::app::Player;
+use crate::config::Config;
+use crate::migrate;
+use crate::placeholder::Variable;
+use crate::scene::{Id, Judge, Mode, Scene, TextSlot, find_mode};
+use crate::system::GameSystem;
+use std::path::Path;
+
+/// シナリオファイルの拡張子
+pub const SCENARIO_EXTENSION: &str = "json";
+
+/// 現在のシナリオファイル形式のバージョン
+///
+/// * 1: モード/判定/スロットを位置（インデックス）で参照する形式
+/// * 2: 各項目にIDを持たせ、IDで参照する形式
+pub const SCENARIO_FORMAT_VERSION: u32 = 2;
+
+/// シナリオ文書（ファイルとして保存/読み込みされる単位）
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct Scenario {
+    /*
+     * version: ファイル形式のバージョン
+     * system: 判定の段階のもとになったゲームシステム
+     * scenes: シーン一覧
+     * modes: モード（判定の種類）一覧
+     * player: プレイヤー（キャスト）一覧
+     * variables: コピー時に置き換える変数一覧
+     */
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub system: GameSystem,
+    #[serde(default)]
+    pub scenes: Vec<Scene>,
+    pub modes: Vec<Mode>,
+    #[serde(default)]
+    pub player: Vec<Player>,
+    #[serde(default)]
+    pub variables: Vec<Variable>,
+}
+
+/// シナリオファイルの読み書きで発生するエラー
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// シーンまたはモードが1つもない
+    Empty,
+}
+
+impl std::fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "ファイルの読み書きに失敗しました: {err}"),
+            Self::Json(err) => write!(f, "シナリオファイルの形式が不正です: {err}"),
+            Self::Empty => write!(f, "シーンまたはモードが1つもありません"),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl From<std::io::Error> for ScenarioError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ScenarioError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl Scenario {
+    /// 空のシナリオを作成（設定ファイルのモード/プレイヤー一覧 + 空のシーン1つ）
+    pub fn blank(system: GameSystem, config: &Config) -> Self {
+        let modes = config.modes(system);
+        let scenes = vec![Scene::new(1, &modes, config.new_scene_mode(&modes))];
+        Self {
+            version: SCENARIO_FORMAT_VERSION,
+            system,
+            scenes,
+            modes,
+            player: config.players(),
+            variables: Vec::new(),
+        }
+    }
+
+    /// JSONファイルからシナリオを読み込む
+    /// 返り値: (シナリオ, 古い形式からの変換で破棄したテキストの数)
+    ///
+    /// # Errors
+    /// ファイルが読めない、JSONとして不正、またはシーン/モードが空の場合
+    pub fn load(path: &Path) -> Result<(Self, usize), ScenarioError> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json)
+    }
+
+    /// JSON文字列からシナリオを読み込む（古い形式は現在の形式に変換する）
+    /// 返り値: (シナリオ, 古い形式からの変換で破棄したテキストの数)
+    ///
+    /// # Errors
+    /// JSONとして不正、またはシーン/モードが空の場合
+    pub fn from_json(json: &str) -> Result<(Self, usize), ScenarioError> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let version = value
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or(0);
+        let (mut scenario, dropped) = if version < 2 {
+            serde_json::from_value::<migrate::LegacyScenario>(value)?.migrate()
+        } else {
+            (serde_json::from_value(value)?, 0)
+        };
+        if scenario.scenes.is_empty() || scenario.modes.is_empty() {
+            return Err(ScenarioError::Empty);
+        }
+        scenario.normalize();
+        Ok((scenario, dropped))
+    }
+
+    /// シナリオをJSONファイルに書き出す
+    ///
+    /// # Errors
+    /// シリアライズまたはファイル書き込みに失敗した場合
+    pub fn save(&self, path: &Path) -> Result<(), ScenarioError> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// モードIDからモードを探す
+    pub fn mode(&self, id: Id) -> Option<&Mode> {
+        find_mode(&self.modes, id)
+    }
+
+    /// シーンIDの位置
+    pub fn scene_position(&self, id: Id) -> Option<usize> {
+        self.scenes.iter().position(|scene| scene.id == id)
+    }
+
+    /// シーンを複製してそれぞれ元のシーンの直後に挿入する
+    /// 返り値: 複製したシーンのID
+    pub fn duplicate_scenes(&mut self, ids: &[Id]) -> Vec<Id> {
+        let mut copies = Vec::new();
+        for id in ids {
+            if let Some(position) = self.scene_position(*id) {
+                if let Some(copy) = self.scenes.get(position).map(Scene::duplicate) {
+                    copies.push(copy.id);
+                    self.scenes.insert(position + 1, copy);
+                }
+            }
+        }
+        copies
+    }
+
+    /// シーンをまとめて削除（全てのシーンが消える場合は何もしない）
+    pub fn remove_scenes(&mut self, ids: &[Id]) {
+        if self.scenes.iter().all(|scene| ids.contains(&scene.id)) {
+            return;
+        }
+        self.scenes.retain(|scene| !ids.contains(&scene.id));
+    }
+
+    /// シーンをまとめて`to`の位置（移動前の並びで数える）へ移動する
+    ///
+    /// 移動するシーン同士の順序は保たれる。
+    pub fn move_scenes(&mut self, ids: &[Id], to: usize) {
+        let before = self
+            .scenes
+            .iter()
+            .take(to)
+            .filter(|scene| ids.contains(&scene.id))
+            .count();
+        let (moved, rest): (Vec<Scene>, Vec<Scene>) = std::mem::take(&mut self.scenes)
+            .into_iter()
+            .partition(|scene| ids.contains(&scene.id));
+        self.scenes = rest;
+        let at = to.saturating_sub(before).min(self.scenes.len());
+        let tail = self.scenes.split_off(at);
+        self.scenes.extend(moved);
+        self.scenes.extend(tail);
+    }
+
+    /// 存在しないモード/判定への参照を取り除き、選択中のモード/判定を補正する
+    pub fn normalize(&mut self) {
+        self.version = SCENARIO_FORMAT_VERSION;
+        let first_mode = self.modes.first().map(|mode| mode.id).unwrap_or_default();
+        for scene in &mut self.scenes {
+            scene
+                .contents
+                .retain(|mode_id, _| find_mode(&self.modes, *mode_id).is_some());
+            for mode in &self.modes {
+                let first_judge = mode
+                    .judges
+                    .first()
+                    .map(|judge| judge.id)
+                    .unwrap_or_default();
+                for slot in scene.slots_mut(mode.id) {
+                    slot.texts
+                        .retain(|judge_id, _| mode.judge(*judge_id).is_some());
+                    for judge in &mode.judges {
+                        slot.texts.entry(judge.id).or_default();
+                    }
+                    if mode.judge(slot.selected_judge).is_none() {
+                        slot.selected_judge = first_judge;
+                    }
+                }
+            }
+            if find_mode(&self.modes, scene.mode_id).is_none() {
+                scene.mode_id = first_mode;
+            }
+            scene.layout_cache = None;
+        }
+    }
+
+    /// モードを末尾に追加
+    pub fn add_mode(&mut self, name: String) -> Id {
+        let mode = Mode::new(name, vec![String::from("本文")], 1);
+        let id = mode.id;
+        self.modes.push(mode);
+        id
+    }
+
+    /// モードの位置を入れ替える（シーンの内容はIDで参照しているため変更不要）
+    pub fn swap_modes(&mut self, a: usize, b: usize) {
+        if a < self.modes.len() && b < self.modes.len() {
+            self.modes.swap(a, b);
+        }
+    }
+
+    /// モードとその全スロットを削除（最後の1つは削除しない）
+    pub fn remove_mode(&mut self, id: Id) {
+        if self.modes.len() <= 1 {
+            return;
+        }
+        self.modes.retain(|mode| mode.id != id);
+        self.normalize();
+    }
+
+    /// モードに判定を末尾に追加
+    pub fn add_judge(&mut self, mode_id: Id, name: String) {
+        let Some(mode) = self.modes.iter_mut().find(|mode| mode.id == mode_id) else {
+            return;
+        };
+        mode.judges.push(Judge::new(name));
+        self.normalize();
+    }
+
+    /// 判定の位置を入れ替える（テキストはIDで参照しているため変更不要）
+    pub fn swap_judges(&mut self, mode_id: Id, a: usize, b: usize) {
+        if let Some(mode) = self.modes.iter_mut().find(|mode| mode.id == mode_id) {
+            if a < mode.judges.len() && b < mode.judges.len() {
+                mode.judges.swap(a, b);
+            }
+        }
+    }
+
+    /// 判定と各スロットのそのテキストを削除（最後の1つは削除しない）
+    pub fn remove_judge(&mut self, mode_id: Id, judge_id: Id) {
+        let Some(mode) = self.modes.iter_mut().find(|mode| mode.id == mode_id) else {
+            return;
+        };
+        if mode.judges.len() <= 1 {
+            return;
+        }
+        mode.judges.retain(|judge| judge.id != judge_id);
+        self.normalize();
+    }
+}
+
+impl Default for Scenario {
+    /// サンプルのシーンを含むシナリオ
+    fn default() -> Self {
+        let modes = GameSystem::Generic.modes();
+        let scenes = (1..=3)
+            .map(|n| {
+                let mut scene = Scene::new(n, &modes, None);
+                scene.title = format!("シーン{n}");
+                let samples = [
+                    vec![format!("地の文{n}")],
+                    vec![
+                        format!("大成功{n}"),
+                        format!("成功{n}"),
+                        format!("失敗{n}"),
+                        format!("ファンブル{n}"),
+                    ],
+                    vec![
+                        String::from("大成功1"),
+                        String::from("成功1"),
+                        String::from("失敗1"),
+                        String::from("ファンブル1"),
+                    ],
+                ];
+                for (mode, texts) in modes.iter().zip(samples) {
+                    let mut slot = TextSlot::new_empty(&mode.judges);
+                    for (judge, text) in mode.judges.iter().zip(texts) {
+                        *slot.text_mut(judge.id) = text;
+                    }
+                    *scene.slots_mut(mode.id) = vec![slot];
+                }
+                scene
+            })
+            .collect();
+
+        Self {
+            version: SCENARIO_FORMAT_VERSION,
+            system: GameSystem::Generic,
+            scenes,
+            modes,
+            player: default_players(),
+            variables: Vec::new(),
+        }
+    }
+}
+
+/// 地の文の話者として使うプレイヤーの名前（`{PC1}`などの番号には含めない）
+pub const NARRATION_PLAYER: &str = "地の文";
+
+/// デフォルトのプレイヤー一覧
+pub(crate) fn default_players() -> Vec<Player> {
+    [NARRATION_PLAYER, "探偵", "助手", "医者", "怪盗"]
+        .into_iter()
+        .map(|name| Player {
+            id: Id::new(),
+            name: String::from(name),
+            icon_path: None,
+            character: String::new(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A〜Fの6シーンのシナリオ
+    fn six_scenes() -> Scenario {
+        let mut scenario = Scenario::default();
+        scenario.scenes = ["A", "B", "C", "D", "E", "F"]
+            .into_iter()
+            .enumerate()
+            .map(|(index, title)| {
+                let mut scene = Scene::new(index + 1, &scenario.modes, None);
+                scene.title = String::from(title);
+                scene
+            })
+            .collect();
+        scenario
+    }
+
+    fn ids(scenario: &Scenario, titles: &[&str]) -> Vec<Id> {
+        titles
+            .iter()
+            .filter_map(|title| scenario.scenes.iter().find(|scene| scene.title == *title))
+            .map(|scene| scene.id)
+            .collect()
+    }
+
+    fn titles(scenario: &Scenario) -> String {
+        scenario
+            .scenes
+            .iter()
+            .map(|scene| scene.title.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn move_scenes_before_selection() {
+        let mut scenario = six_scenes();
+        let selected = ids(&scenario, &["B", "D"]);
+        scenario.move_scenes(&selected, 0);
+        assert_eq!(
+            titles(&scenario),
+            "BDACEF",
+            "先頭へ、選択の順序のまま移動する"
+        );
+    }
+
+    #[test]
+    fn move_scenes_after_selection() {
+        let mut scenario = six_scenes();
+        let selected = ids(&scenario, &["B", "D"]);
+        scenario.move_scenes(&selected, 5);
+        assert_eq!(titles(&scenario), "ACEBDF", "Fの前へ移動する");
+
+        let mut scenario = six_scenes();
+        let selected = ids(&scenario, &["B", "D"]);
+        scenario.move_scenes(&selected, 6);
+        assert_eq!(titles(&scenario), "ACEFBD", "末尾へ移動する");
+    }
+
+    #[test]
+    fn move_scenes_into_selection() {
+        let mut scenario = six_scenes();
+        let selected = ids(&scenario, &["A", "E"]);
+        scenario.move_scenes(&selected, 3);
+        assert_eq!(
+            titles(&scenario),
+            "BCAEDF",
+            "選択したシーンの間の位置（Dの前）へ移動する"
+        );
+
+        let mut scenario = six_scenes();
+        let selected = ids(&scenario, &["B", "C"]);
+        scenario.move_scenes(&selected, 2);
+        assert_eq!(
+            titles(&scenario),
+            "ABCDEF",
+            "選択の中の位置なら並びは変わらない"
+        );
+    }
+
+    #[test]
+    fn remove_scenes_keeps_at_least_one() {
+        let mut scenario = six_scenes();
+        let all = ids(&scenario, &["A", "B", "C", "D", "E", "F"]);
+        scenario.remove_scenes(&all);
+        assert_eq!(scenario.scenes.len(), 6, "全てのシーンは削除しない");
+
+        let selected = ids(&scenario, &["A", "F"]);
+        scenario.remove_scenes(&selected);
+        assert_eq!(titles(&scenario), "BCDE", "選択したシーンを削除する");
+    }
+
+    #[test]
+    fn duplicate_scenes_insert_after_originals() {
+        let mut scenario = six_scenes();
+        let selected = ids(&scenario, &["B", "E"]);
+        scenario.duplicate_scenes(&selected);
+        assert_eq!(
+            titles(&scenario),
+            "ABBのコピーCDEEのコピーF",
+            "元のシーンの直後に複製する"
+        );
+        let copy = scenario.scenes.get(2).expect("複製がある");
+        assert!(!selected.contains(&copy.id), "複製には新しいIDを付ける");
+    }
+
+    /// モード名からモードを探す
+    fn mode<'a>(scenario: &'a Scenario, name: &str) -> &'a Mode {
+        scenario
+            .modes
+            .iter()
+            .find(|mode| mode.name == name)
+            .expect("サンプルのモードがある")
+    }
+
+    #[test]
+    fn add_judge_gives_every_slot_an_empty_text() {
+        let mut scenario = Scenario::default();
+        let explore = mode(&scenario, "探索").id;
+        scenario.add_judge(explore, String::from("特殊"));
+        let judge = mode(&scenario, "探索")
+            .judges
+            .last()
+            .map(|judge| judge.id)
+            .expect("追加した判定がある");
+        for scene in &scenario.scenes {
+            for slot in scene.slots(explore) {
+                assert_eq!(
+                    slot.texts.get(&judge).map(String::as_str),
+                    Some(""),
+                    "すべてのスロットに空のテキストを用意する"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn remove_judge_reselects_a_remaining_judge() {
+        let mut scenario = Scenario::default();
+        let player = mode(&scenario, "プレイヤー");
+        let (player, first, removed) = (
+            player.id,
+            player.judges.first().map(|judge| judge.id),
+            player.judges.get(1).map(|judge| judge.id),
+        );
+        let removed = removed.expect("2番目の判定がある");
+        for scene in &mut scenario.scenes {
+            for slot in scene.slots_mut(player) {
+                slot.selected_judge = removed;
+            }
+        }
+        scenario.remove_judge(player, removed);
+
+        assert_eq!(
+            mode(&scenario, "プレイヤー").judges.len(),
+            3,
+            "判定を1つ削除する"
+        );
+        for scene in &scenario.scenes {
+            for slot in scene.slots(player) {
+                assert!(!slot.texts.contains_key(&removed), "テキストも削除する");
+                assert_eq!(
+                    Some(slot.selected_judge),
+                    first,
+                    "選択中の判定を削除したら先頭の判定を選ぶ"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn remove_mode_drops_contents_and_repoints_scenes() {
+        let mut scenario = Scenario::default();
+        let explore = mode(&scenario, "探索").id;
+        let first = scenario.modes.first().map(|mode| mode.id);
+        for scene in &mut scenario.scenes {
+            scene.mode_id = explore;
+        }
+        scenario.remove_mode(explore);
+
+        assert!(scenario.mode(explore).is_none(), "モードを削除する");
+        for scene in &scenario.scenes {
+            assert!(
+                !scene.contents.contains_key(&explore),
+                "削除したモードのスロットも削除する"
+            );
+            assert_eq!(
+                Some(scene.mode_id),
+                first,
+                "表示中のモードを削除したら先頭のモードを表示する"
+            );
+        }
+
+        let mut single = Scenario::default();
+        single.modes.truncate(1);
+        let last = single
+            .modes
+            .first()
+            .map(|mode| mode.id)
+            .expect("モードがある");
+        single.remove_mode(last);
+        assert_eq!(single.modes.len(), 1, "最後のモードは削除しない");
+    }
+
+    #[test]
+    fn normalize_repairs_dangling_ids() {
+        let mut scenario = Scenario::default();
+        let player = mode(&scenario, "プレイヤー");
+        let (player, first_judge) = (
+            player.id,
+            player
+                .judges
+                .first()
+                .map(|judge| judge.id)
+                .expect("判定がある"),
+        );
+        let stray_mode = Id::new();
+        let stray_judge = Id::new();
+        let scene = scenario.scenes.first_mut().expect("シーンがある");
+        scene.mode_id = stray_mode;
+        scene.contents.insert(stray_mode, Vec::new());
+        let slot = scene.slots_mut(player).first_mut().expect("スロットがある");
+        slot.selected_judge = stray_judge;
+        slot.texts.insert(stray_judge, String::from("迷子"));
+        slot.texts.remove(&first_judge);
+
+        scenario.normalize();
+        let scene = scenario.scenes.first().expect("シーンがある");
+        assert_eq!(
+            Some(scene.mode_id),
+            scenario.modes.first().map(|mode| mode.id),
+            "存在しないモードは先頭のモードにする"
+        );
+        assert!(
+            !scene.contents.contains_key(&stray_mode),
+            "存在しないモードの内容は削除する"
+        );
+        let slot = scene.slots(player).first().expect("スロットがある");
+        assert_eq!(
+            slot.selected_judge, first_judge,
+            "存在しない判定の選択は先頭の判定にする"
+        );
+        assert!(
+            !slot.texts.contains_key(&stray_judge),
+            "存在しない判定のテキストは削除する"
+        );
+        assert_eq!(
+            slot.texts.get(&first_judge).map(String::as_str),
+            Some(""),
+            "足りない判定のテキストは空で補う"
+        );
+    }
+}
@@ -1,31 +1,130 @@
+use crate::app::Player;
+use std::collections::BTreeMap;
+use std::hash::{BuildHasher as _, Hasher as _};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// モード/判定/シーン/スロットを識別する永続ID
+///
+/// 並べ替えや削除で位置が変わっても同じ項目を指し続ける。
+/// `Id::default()`（0）はどの項目も指さない。
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(transparent)]
+pub struct Id(u64);
+
+impl Id {
+    /// 新しいIDを生成する（他のファイルのIDとも衝突しないようランダムに近い値にする）
+    pub fn new() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(1);
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Ok(elapsed) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+            hasher.write_u128(elapsed.as_nanos());
+        }
+        // 0は「なし」として予約
+        Self(hasher.finish().max(1))
+    }
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Clone)]
 pub struct TextSlot {
     /*
-     * texts: 各判定のテキスト (judge_index -> text)
-     * selected_judge_index: このスロットで選択中の判定インデックス
+     * id: スロットのID
+     * texts: 各判定のテキスト (judge_id -> text)
+     * selected_judge: このスロットで選択中の判定ID
      * label: スロットの名前 (例: "PC1", "探索地点A" など、任意)
-     * icon_path: アイコン画像のパス (将来用、任意)
+     * icon_path: アイコン画像のパス (任意)
+     * speaker: 話者のプレイヤーID (任意、設定されていればlabel/icon_pathより優先)
+     * check: 技能判定 (任意、ダイスの結果から判定を選ぶ)
+     * hidden: 結果を伏せてコピーする (Discordではスポイラー、ccfoliaではシークレットダイス)
      */
-    pub texts: Vec<String>,
-    pub selected_judge_index: usize,
+    pub id: Id,
+    pub texts: BTreeMap<Id, String>,
+    pub selected_judge: Id,
     pub label: Option<String>,
     pub icon_path: Option<std::path::PathBuf>,
+    #[serde(default)]
+    pub speaker: Option<Id>,
+    #[serde(default)]
+    pub check: Option<SkillCheck>,
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+/// スロットに設定する技能判定（技能名と目標値）
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq, Eq)]
+pub struct SkillCheck {
+    pub skill: String,
+    pub target: i64,
+    /// 振るダイスの数（エモクロアTRPGの判定ダイス数、ほかのシステムでは使わない）
+    #[serde(default = "default_check_dice")]
+    pub dice: u32,
+}
+
+fn default_check_dice() -> u32 {
+    1
+}
+
+impl Default for SkillCheck {
+    fn default() -> Self {
+        Self {
+            skill: String::new(),
+            target: 0,
+            dice: default_check_dice(),
+        }
+    }
+}
+
+impl SkillCheck {
+    /// ボタンに表示する名前（`目星(65)`）
+    pub fn label(&self) -> String {
+        if self.skill.is_empty() {
+            format!("({})", self.target)
+        } else {
+            format!("{}({})", self.skill, self.target)
+        }
+    }
+}
+
+/// スロットの話者（表示用の名前とアイコン）
+pub struct Speaker<'a> {
+    pub name: &'a str,
+    pub icon_path: Option<&'a std::path::Path>,
 }
 
 impl TextSlot {
-    /// 空のTextSlotを作成
+    /// 空の`TextSlot`を作成
     ///
     /// # Arguments
-    /// * `judge_count` - 判定の数（テキスト配列のサイズ）
+    /// * `judges` - 判定一覧（各判定のテキストを空文字列で用意する）
     ///
     /// # Returns
-    /// 各判定のテキストが空文字列で初期化されたTextSlot
-    pub fn new_empty(judge_count: usize) -> Self {
+    /// 各判定のテキストが空文字列で初期化され、先頭の判定を選択したTextSlot
+    pub fn new_empty(judges: &[Judge]) -> Self {
         Self {
-            texts: vec![String::new(); judge_count],
-            selected_judge_index: 0,
+            id: Id::new(),
+            texts: judges
+                .iter()
+                .map(|judge| (judge.id, String::new()))
+                .collect(),
+            selected_judge: judges.first().map(|judge| judge.id).unwrap_or_default(),
             label: None,
             icon_path: None,
+            speaker: None,
+            check: None,
+            hidden: false,
         }
     }
 
@@ -41,16 +140,33 @@ impl TextSlot {
         self
     }
 
-    /// アイコンパスを設定する（Builderパターン）
-    ///
-    /// # Arguments
-    /// * `icon_path` - 設定するアイコンのパス
+    /// 指定判定のテキスト（未設定なら空文字列）
+    pub fn text(&self, judge: Id) -> &str {
+        self.texts.get(&judge).map_or("", String::as_str)
+    }
+
+    /// 指定判定のテキスト（未設定なら空文字列を追加して返す）
+    pub fn text_mut(&mut self, judge: Id) -> &mut String {
+        self.texts.entry(judge).or_default()
+    }
+
+    /// 話者（プレイヤーが設定されていればプレイヤー、なければラベル）
     ///
-    /// # Returns
-    /// アイコンパスが設定されたTextSlot（所有権を移動）
-    pub fn with_icon(mut self, icon_path: std::path::PathBuf) -> Self {
-        self.icon_path = Some(icon_path);
-        self
+    /// 削除されたプレイヤーを指している場合はラベルにフォールバックする。
+    pub fn speaker<'a>(&'a self, players: &'a [Player]) -> Option<Speaker<'a>> {
+        let player = self
+            .speaker
+            .and_then(|id| players.iter().find(|player| player.id == id));
+        match player {
+            Some(player) => Some(Speaker {
+                name: &player.name,
+                icon_path: player.icon_path.as_deref(),
+            }),
+            None => self.label.as_deref().map(|label| Speaker {
+                name: label,
+                icon_path: self.icon_path.as_deref(),
+            }),
+        }
     }
 }
 
@@ -61,65 +177,307 @@ pub struct LayoutCache {
     pub judge_width: f32,
     /// アイコンエリアの幅
     pub icon_width: f32,
-    /// このキャッシュが有効なモードのインデックス
-    pub mode_index: usize,
+    /// このキャッシュが有効なモードのID
+    pub mode_id: Id,
 }
 
-#[derive(serde::Deserialize, serde::Serialize)]
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
 pub struct Scene {
     /*
+     * id: シーンのID
      * title:シーン名
-     * mode_index:選択中のモードインデックス
-     * contents: [mode_index][slot_index] -> TextSlot
+     * mode_id:選択中のモードID
+     * contents: mode_id -> [slot_index] -> TextSlot
      * layout_cache: レイアウト幅のキャッシュ（シリアライズ対象外）
      */
+    pub id: Id,
     pub title: String,
-    pub mode_index: usize,
-    pub contents: Vec<Vec<TextSlot>>,
+    pub mode_id: Id,
+    pub contents: BTreeMap<Id, Vec<TextSlot>>,
     #[serde(skip)]
     pub layout_cache: Option<LayoutCache>,
 }
 
-#[derive(serde::Deserialize, serde::Serialize)]
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq, Eq)]
+pub struct Judge {
+    /*
+     * id:判定のID
+     * name:判定の名前(成功/ファンブル/本文など)
+     */
+    pub id: Id,
+    pub name: String,
+}
+
+impl Judge {
+    pub fn new(name: String) -> Self {
+        Self {
+            id: Id::new(),
+            name,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, PartialEq, Eq)]
 pub struct Mode {
     /*
+     * id:モードのID
      * name:シーンタイプの名前(探索/地の文/etc)
      * judges:持っている判定種類(成功/ファンブルor地の文)
+     * copy_prefix/copy_suffix:コピー時に前後に付ける文字列({話者}/{判定}/{モード}と変数を置き換える)
      */
+    pub id: Id,
     pub name: String,
-    pub judges: Vec<String>,
+    pub judges: Vec<Judge>,
     pub default_text_num: usize,
+    #[serde(default)]
+    pub copy_prefix: String,
+    #[serde(default)]
+    pub copy_suffix: String,
+}
+
+impl Mode {
+    /// 判定名から新しいモードを作成
+    pub fn new(name: String, judges: Vec<String>, default_text_num: usize) -> Self {
+        Self {
+            id: Id::new(),
+            name,
+            judges: judges.into_iter().map(Judge::new).collect(),
+            default_text_num,
+            copy_prefix: String::new(),
+            copy_suffix: String::new(),
+        }
+    }
+
+    /// 判定IDから判定を探す
+    pub fn judge(&self, id: Id) -> Option<&Judge> {
+        self.judges.iter().find(|judge| judge.id == id)
+    }
 }
 
-// #[derive(serde::Deserialize, serde::Serialize)]
-// pub struct Judgement {
-//     /*
-//      * name:判定の名前
-//      * content:表示されるテキスト
-//      * */
-//     pub name: String,
-//     pub text: String,
-// }
+/// モードIDからモードを探す
+pub fn find_mode(modes: &[Mode], id: Id) -> Option<&Mode> {
+    modes.iter().find(|mode| mode.id == id)
+}
+
+/// モードIDの位置
+pub fn mode_position(modes: &[Mode], id: Id) -> Option<usize> {
+    modes.iter().position(|mode| mode.id == id)
+}
 
 impl Scene {
     /// modesから動的にcontentsを生成する
-    /// 各モードに対して、default_text_num個のTextSlotを作成
-    pub fn new(index: usize, modes: &[Mode]) -> Self {
+    /// 各モードに対して、`default_text_num`個の`TextSlot`を作成
+    /// `mode_id`は最初に表示するモード（Noneなら2番目のモード、なければ先頭のモード）
+    pub fn new(index: usize, modes: &[Mode], mode_id: Option<Id>) -> Self {
         let contents = modes
             .iter()
             .map(|mode| {
                 // このモードのdefault_text_num個のスロットを作成
-                (0..mode.default_text_num)
-                    .map(|_| TextSlot::new_empty(mode.judges.len()))
-                    .collect()
+                let slots = (0..mode.default_text_num)
+                    .map(|_| TextSlot::new_empty(&mode.judges))
+                    .collect();
+                (mode.id, slots)
             })
             .collect();
 
         Self {
-            title: format!("新規シーン{}", index),
-            mode_index: 1,
+            id: Id::new(),
+            title: format!("新規シーン{index}"),
+            mode_id: mode_id
+                .filter(|&id| find_mode(modes, id).is_some())
+                .or_else(|| modes.get(1).or(modes.first()).map(|mode| mode.id))
+                .unwrap_or_default(),
             contents,
             layout_cache: None,
         }
     }
+
+    /// 新しいIDを割り当てた複製
+    pub fn duplicate(&self) -> Self {
+        let mut copy = self.clone();
+        copy.id = Id::new();
+        copy.title = format!("{}のコピー", self.title);
+        for slot in copy.contents.values_mut().flatten() {
+            slot.id = Id::new();
+        }
+        copy.layout_cache = None;
+        copy
+    }
+
+    /// 指定モードのスロット一覧
+    pub fn slots(&self, mode: Id) -> &[TextSlot] {
+        self.contents.get(&mode).map_or(&[], Vec::as_slice)
+    }
+
+    /// 指定モードのスロット一覧（なければ空の一覧を追加して返す）
+    pub fn slots_mut(&mut self, mode: Id) -> &mut Vec<TextSlot> {
+        self.contents.entry(mode).or_default()
+    }
+
+    /// 選択中のモードのスロット一覧
+    pub fn current_slots(&self) -> &[TextSlot] {
+        self.slots(self.mode_id)
+    }
+
+    /// 選択中のモードのスロット一覧（変更用）
+    pub fn current_slots_mut(&mut self) -> &mut Vec<TextSlot> {
+        self.slots_mut(self.mode_id)
+    }
+
+    /// 表示するモードを切り替える（切り替え先の各スロットの判定は先頭に戻す）
+    pub fn set_mode(&mut self, mode: &Mode) {
+        self.mode_id = mode.id;
+        let first_judge = mode
+            .judges
+            .first()
+            .map(|judge| judge.id)
+            .unwrap_or_default();
+        for slot in self.slots_mut(mode.id).iter_mut() {
+            slot.selected_judge = first_judge;
+        }
+        self.layout_cache = None;
+    }
+
+    /// 選択中のモードの末尾に空のスロットを追加する
+    /// 返り値: 追加したスロットのID
+    pub fn push_slot(&mut self, mode: &Mode) -> Id {
+        let new_slot = TextSlot::new_empty(&mode.judges);
+        let id = new_slot.id;
+        self.slots_mut(mode.id).push(new_slot);
+        // スロット追加時はキャッシュを無効化（幅が変わる可能性がある）
+        self.layout_cache = None;
+        id
+    }
+
+    /// 選択中のモードのスロットを複製して直後に挿入する
+    pub fn duplicate_slot(&mut self, index: usize) {
+        let slots = self.current_slots_mut();
+        if let Some(slot) = slots.get(index) {
+            let mut copy = slot.clone();
+            copy.id = Id::new();
+            slots.insert(index + 1, copy);
+        }
+        self.layout_cache = None;
+    }
+
+    /// 選択中のモードのスロットを`from`から`to`の位置へ移動する
+    pub fn move_slot(&mut self, from: usize, to: usize) {
+        let slots = self.current_slots_mut();
+        if from < slots.len() && from != to {
+            let slot = slots.remove(from);
+            let to = to.min(slots.len());
+            slots.insert(to, slot);
+        }
+        self.layout_cache = None;
+    }
+
+    /// 選択中のモードからスロットを取り出す
+    pub fn take_slot(&mut self, index: usize) -> Option<TextSlot> {
+        let slots = self.current_slots_mut();
+        let slot = (index < slots.len()).then(|| slots.remove(index));
+        self.layout_cache = None;
+        slot
+    }
+
+    /// IDでスロットを探す（全モードから）
+    pub fn find_slot(&self, id: Id) -> Option<&TextSlot> {
+        self.contents.values().flatten().find(|slot| slot.id == id)
+    }
+
+    /// IDでスロットを探す（全モードから、変更用）
+    pub fn find_slot_mut(&mut self, id: Id) -> Option<&mut TextSlot> {
+        self.contents
+            .values_mut()
+            .flatten()
+            .find(|slot| slot.id == id)
+    }
+
+    /// IDでスロットを削除する（全モードから）
+    pub fn remove_slot(&mut self, id: Id) {
+        for slots in self.contents.values_mut() {
+            slots.retain(|slot| slot.id != id);
+        }
+        self.layout_cache = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A〜Cの3スロットのシーン
+    fn three_slots() -> (Scene, Id) {
+        let mode = Mode::new(String::from("探索"), vec![String::from("成功")], 3);
+        let judge = mode
+            .judges
+            .first()
+            .map(|judge| judge.id)
+            .expect("判定がある");
+        let mut scene = Scene::new(1, std::slice::from_ref(&mode), None);
+        for (slot, text) in scene.current_slots_mut().iter_mut().zip(["A", "B", "C"]) {
+            *slot.text_mut(judge) = String::from(text);
+        }
+        (scene, judge)
+    }
+
+    /// スロットのテキストを順に並べる
+    fn order(scene: &Scene, judge: Id) -> String {
+        scene
+            .current_slots()
+            .iter()
+            .map(|slot| slot.text(judge))
+            .collect()
+    }
+
+    #[test]
+    fn duplicate_slot_inserts_copy_after_original() {
+        let (mut scene, judge) = three_slots();
+        scene.duplicate_slot(1);
+        assert_eq!(order(&scene, judge), "ABBC", "直後に複製する");
+        let slots = scene.current_slots();
+        let (original, copy) = (slots.get(1), slots.get(2));
+        assert_ne!(
+            original.map(|slot| slot.id),
+            copy.map(|slot| slot.id),
+            "複製には新しいIDを付ける"
+        );
+
+        scene.duplicate_slot(4);
+        assert_eq!(order(&scene, judge), "ABBC", "範囲外の位置は何もしない");
+    }
+
+    #[test]
+    fn move_slot_to_each_position() {
+        let (mut scene, judge) = three_slots();
+        scene.move_slot(0, 2);
+        assert_eq!(order(&scene, judge), "BCA", "後ろへ移動する");
+
+        let (mut scene, judge) = three_slots();
+        scene.move_slot(2, 0);
+        assert_eq!(order(&scene, judge), "CAB", "前へ移動する");
+
+        let (mut scene, judge) = three_slots();
+        scene.move_slot(0, 3);
+        assert_eq!(order(&scene, judge), "BCA", "to == len は末尾へ移動する");
+
+        let (mut scene, judge) = three_slots();
+        scene.move_slot(1, 1);
+        assert_eq!(order(&scene, judge), "ABC", "from == to は何もしない");
+
+        scene.move_slot(3, 0);
+        assert_eq!(order(&scene, judge), "ABC", "範囲外のfromは何もしない");
+
+        scene.move_slot(0, 10);
+        assert_eq!(order(&scene, judge), "BCA", "範囲外のtoは末尾へ移動する");
+    }
+
+    #[test]
+    fn take_slot_removes_it() {
+        let (mut scene, judge) = three_slots();
+        let taken = scene.take_slot(1).expect("範囲内のスロットは取り出せる");
+        assert_eq!(taken.text(judge), "B", "指定した位置のスロット");
+        assert_eq!(order(&scene, judge), "AC", "取り出したスロットは残らない");
+        assert!(scene.take_slot(2).is_none(), "範囲外の位置はNone");
+        assert_eq!(order(&scene, judge), "AC", "範囲外なら何もしない");
+    }
 }
@@ -0,0 +1,447 @@
+use crate::scenario::Scenario;
+use crate::scene::Id;
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// 半角カタカナ（U+FF61〜U+FF9F）に対応する全角文字
+const HALFWIDTH_KATAKANA: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン゛゜";
+
+/// 検索のオプション（既定では大文字/小文字、全角/半角を区別しない）
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchOptions {
+    /// 大文字/小文字を区別する
+    pub case_sensitive: bool,
+    /// 全角/半角を区別する
+    pub width_sensitive: bool,
+    /// 検索語を正規表現として扱う
+    pub regex: bool,
+}
+
+/// 検索語に一致する範囲を探す
+///
+/// 通常の検索語もエスケープして正規表現として扱う。
+/// 全角/半角を区別しない場合は、検索語と対象の文字列の両方をそろえてから比較する。
+pub struct Matcher {
+    regex: regex::Regex,
+    options: MatchOptions,
+}
+
+/// 一致した1箇所
+pub struct Found {
+    /// 一致した範囲（バイト位置）
+    pub range: Range<usize>,
+    /// 正規表現のグループの範囲（0番は一致全体）
+    pub groups: Vec<Option<Range<usize>>>,
+}
+
+impl Matcher {
+    /// 空の検索語ならOk(None)
+    ///
+    /// # Errors
+    /// 正規表現として解釈できない場合
+    pub fn new(query: &str, options: MatchOptions) -> Result<Option<Self>, regex::Error> {
+        if query.is_empty() {
+            return Ok(None);
+        }
+        let query: String = if options.width_sensitive {
+            query.to_owned()
+        } else {
+            fold_text(query).0
+        };
+        let pattern = if options.regex {
+            query
+        } else {
+            regex::escape(&query)
+        };
+        let regex = regex::RegexBuilder::new(&pattern)
+            .case_insensitive(!options.case_sensitive)
+            .build()?;
+        Ok(Some(Self { regex, options }))
+    }
+
+    /// 一致する範囲（バイト位置、重ならない、空の一致は除く）
+    pub fn find_all(&self, haystack: &str) -> Vec<Found> {
+        let (folded, offsets) = if self.options.width_sensitive {
+            (Cow::Borrowed(haystack), None)
+        } else {
+            let (folded, offsets) = fold_text(haystack);
+            (Cow::Owned(folded), Some(offsets))
+        };
+        // そろえた文字列の位置 -> 元の文字列の位置
+        let original = |position: usize| match &offsets {
+            None => position,
+            Some(offsets) => offsets
+                .binary_search_by_key(&position, |(folded, _)| *folded)
+                .ok()
+                .and_then(|index| offsets.get(index))
+                .map_or(haystack.len(), |(_, original)| *original),
+        };
+
+        self.regex
+            .captures_iter(&folded)
+            .filter_map(|captures| {
+                let whole = captures.get(0)?;
+                if whole.is_empty() {
+                    return None;
+                }
+                let groups = captures
+                    .iter()
+                    .map(|group| group.map(|group| original(group.start())..original(group.end())))
+                    .collect();
+                Some(Found {
+                    range: original(whole.start())..original(whole.end()),
+                    groups,
+                })
+            })
+            .collect()
+    }
+
+    /// 置換後の文字列
+    ///
+    /// 正規表現の場合は`$1`、`${name}`などをグループの文字列に置き換える（`$$`で`$`）。
+    /// グループの文字列は元の文字列から取るので、全角/半角をそろえる前の文字のまま入る。
+    pub fn expand(&self, template: &str, text: &str, groups: &[Option<Range<usize>>]) -> String {
+        if !self.options.regex {
+            return template.to_owned();
+        }
+        let mut expanded = String::new();
+        let mut rest = template;
+        while let Some((before, after)) = rest.split_once('$') {
+            expanded.push_str(before);
+            if let Some(after) = after.strip_prefix('$') {
+                expanded.push('$');
+                rest = after;
+                continue;
+            }
+            let (name, after) = if let Some(braced) = after.strip_prefix('{') {
+                braced.split_once('}').unwrap_or(("", after))
+            } else {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                after.split_at(end)
+            };
+            if name.is_empty() {
+                // グループ名がなければ`$`をそのまま残す
+                expanded.push('$');
+            } else {
+                let index = name.parse::<usize>().ok().or_else(|| {
+                    self.regex
+                        .capture_names()
+                        .position(|group| group == Some(name))
+                });
+                if let Some(range) = index.and_then(|index| groups.get(index)).cloned().flatten() {
+                    expanded.push_str(text.get(range).unwrap_or_default());
+                }
+            }
+            rest = after;
+        }
+        expanded.push_str(rest);
+        expanded
+    }
+}
+
+/// 全角英数記号とスペースを半角に、半角カタカナを全角にそろえる（1文字を1文字に対応させる）
+pub fn fold_width(c: char) -> char {
+    match c {
+        '\u{3000}' => ' ',
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(u32::from(c) - 0xFEE0).unwrap_or(c),
+        '\u{FF61}'..='\u{FF9F}' => HALFWIDTH_KATAKANA
+            .chars()
+            .nth((u32::from(c) - 0xFF61) as usize)
+            .unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// 半角カタカナと、続く半角の濁点/半濁点を合わせた全角カタカナ（`ｶﾞ` → `ガ`）
+fn compose_voiced(base: char, mark: char) -> Option<char> {
+    if !('\u{FF61}'..='\u{FF9F}').contains(&base) {
+        return None;
+    }
+    let base = fold_width(base);
+    let shift = match mark {
+        '\u{FF9E}' if base == 'ウ' => return Some('ヴ'),
+        '\u{FF9E}' if "カキクケコサシスセソタチツテトハヒフヘホ".contains(base) => {
+            1
+        }
+        '\u{FF9F}' if "ハヒフヘホ".contains(base) => 2,
+        _ => return None,
+    };
+    char::from_u32(u32::from(base) + shift)
+}
+
+/// 全角/半角をそろえた文字列と、(そろえた後の位置, 元の位置)の対応（末尾を含む）
+///
+/// 半角カタカナと濁点/半濁点の2文字は1文字の全角カタカナにするため、
+/// そろえた後の1文字が元の2文字に対応することがある。
+fn fold_text(text: &str) -> (String, Vec<(usize, usize)>) {
+    let mut folded = String::with_capacity(text.len());
+    let mut offsets = Vec::with_capacity(text.len() + 1);
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        offsets.push((folded.len(), index));
+        let composed = chars.peek().and_then(|(_, mark)| compose_voiced(c, *mark));
+        if let Some(composed) = composed {
+            chars.next();
+            folded.push(composed);
+        } else {
+            folded.push(fold_width(c));
+        }
+    }
+    offsets.push((folded.len(), text.len()));
+    (folded, offsets)
+}
+
+/// 一致した場所
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Location {
+    SceneTitle,
+    SlotLabel {
+        mode_id: Id,
+        slot_id: Id,
+    },
+    Text {
+        mode_id: Id,
+        slot_id: Id,
+        judge_id: Id,
+    },
+}
+
+/// 検索結果の1件
+#[derive(Clone)]
+pub struct Hit {
+    pub scene_id: Id,
+    pub location: Location,
+    /// 一致した範囲（対象の文字列内のバイト位置）
+    pub range: Range<usize>,
+    /// 正規表現のグループの範囲（置換に使う）
+    pub groups: Vec<Option<Range<usize>>>,
+}
+
+/// シーン名、スロットのラベル、全判定のテキストを検索する
+///
+/// 結果はシーン、モード、スロット、判定の並び順に並ぶ。
+pub fn search(scenario: &Scenario, matcher: &Matcher) -> Vec<Hit> {
+    let mut hits = Vec::new();
+    for scene in &scenario.scenes {
+        let mut push = |location, text: &str| {
+            hits.extend(matcher.find_all(text).into_iter().map(|found| Hit {
+                scene_id: scene.id,
+                location,
+                range: found.range,
+                groups: found.groups,
+            }));
+        };
+        push(Location::SceneTitle, &scene.title);
+        for mode in &scenario.modes {
+            for slot in scene.slots(mode.id) {
+                if let Some(label) = &slot.label {
+                    push(
+                        Location::SlotLabel {
+                            mode_id: mode.id,
+                            slot_id: slot.id,
+                        },
+                        label,
+                    );
+                }
+                for judge in &mode.judges {
+                    push(
+                        Location::Text {
+                            mode_id: mode.id,
+                            slot_id: slot.id,
+                            judge_id: judge.id,
+                        },
+                        slot.text(judge.id),
+                    );
+                }
+            }
+        }
+    }
+    hits
+}
+
+/// 一致した場所の文字列
+pub fn field_text(scenario: &Scenario, scene_id: Id, location: Location) -> Option<&str> {
+    let scene = scenario.scenes.iter().find(|scene| scene.id == scene_id)?;
+    match location {
+        Location::SceneTitle => Some(&scene.title),
+        Location::SlotLabel { slot_id, .. } => scene.find_slot(slot_id)?.label.as_deref(),
+        Location::Text {
+            slot_id, judge_id, ..
+        } => Some(scene.find_slot(slot_id)?.text(judge_id)),
+    }
+}
+
+/// 置換する1箇所
+pub struct Replacement {
+    pub hit: Hit,
+    /// 置換後の文字列
+    pub text: String,
+}
+
+/// 置換をまとめて適用する
+///
+/// 同じ場所の置換は後ろから適用するので、範囲は置換前の文字列のものでよい
+/// （`search`の並び順のまま渡す）。
+/// 返り値: 置換した件数
+pub fn replace_all(scenario: &mut Scenario, replacements: &[Replacement]) -> usize {
+    let mut count = 0;
+    for replacement in replacements.iter().rev() {
+        let hit = &replacement.hit;
+        let Some(scene) = scenario
+            .scenes
+            .iter_mut()
+            .find(|scene| scene.id == hit.scene_id)
+        else {
+            continue;
+        };
+        let field = match hit.location {
+            Location::SceneTitle => Some(&mut scene.title),
+            Location::SlotLabel { slot_id, .. } => scene
+                .find_slot_mut(slot_id)
+                .and_then(|slot| slot.label.as_mut()),
+            Location::Text {
+                slot_id, judge_id, ..
+            } => scene
+                .find_slot_mut(slot_id)
+                .and_then(|slot| slot.texts.get_mut(&judge_id)),
+        };
+        let Some(field) = field else {
+            continue;
+        };
+        // 一致した後に内容が変わっていれば範囲が文字の境界にならないことがある
+        if field.get(hit.range.clone()).is_some() {
+            field.replace_range(hit.range.clone(), &replacement.text);
+            count += 1;
+        }
+        scene.layout_cache = None;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(query: &str, options: MatchOptions) -> Matcher {
+        Matcher::new(query, options)
+            .expect("正しい検索語")
+            .expect("空でない検索語")
+    }
+
+    fn ranges(matcher: &Matcher, haystack: &str) -> Vec<Range<usize>> {
+        matcher
+            .find_all(haystack)
+            .into_iter()
+            .map(|found| found.range)
+            .collect()
+    }
+
+    #[test]
+    fn folded_ranges_point_into_original_text() {
+        let matcher = matcher("abc", MatchOptions::default());
+        let haystack = "前ＡＢＣ後abc";
+        let found = ranges(&matcher, haystack);
+        assert_eq!(found.len(), 2, "全角と半角の両方に一致する");
+        let texts: Vec<&str> = found
+            .iter()
+            .filter_map(|range| haystack.get(range.clone()))
+            .collect();
+        assert_eq!(texts, ["ＡＢＣ", "abc"], "元の文字列の範囲を返す");
+    }
+
+    #[test]
+    fn halfwidth_voiced_kana_matches_fullwidth() {
+        let matcher_full = matcher("ガイド", MatchOptions::default());
+        let haystack = "ｶﾞｲﾄﾞとガイド";
+        let texts: Vec<&str> = ranges(&matcher_full, haystack)
+            .into_iter()
+            .filter_map(|range| haystack.get(range))
+            .collect();
+        assert_eq!(
+            texts,
+            ["ｶﾞｲﾄﾞ", "ガイド"],
+            "濁点を分けた半角カタカナにも一致する"
+        );
+
+        let matcher_half = matcher("ﾊﾟﾝ", MatchOptions::default());
+        let found = ranges(&matcher_half, "パン");
+        assert_eq!(
+            found.first().and_then(|range| "パン".get(range.clone())),
+            Some("パン"),
+            "半角の検索語も全角に一致する"
+        );
+        assert!(
+            ranges(&matcher_half, "ハン").is_empty(),
+            "半濁点のない文字には一致しない"
+        );
+    }
+
+    #[test]
+    fn width_sensitive_does_not_fold() {
+        let options = MatchOptions {
+            width_sensitive: true,
+            ..MatchOptions::default()
+        };
+        assert!(
+            ranges(&matcher("abc", options), "ＡＢＣ").is_empty(),
+            "全角/半角を区別する"
+        );
+    }
+
+    #[test]
+    fn expands_groups_from_original_text() {
+        let options = MatchOptions {
+            regex: true,
+            ..MatchOptions::default()
+        };
+        let matcher = matcher(r"(?<skill>\w+)\((\d+)\)", options);
+        let text = "目星(６５)";
+        let found = matcher.find_all(text);
+        let groups = &found.first().expect("一致する").groups;
+        assert_eq!(
+            matcher.expand("${skill}=$2 $$1 $", text, groups),
+            "目星=６５ $1 $",
+            "名前付き/番号のグループ、$$と単独の$"
+        );
+        assert_eq!(matcher.expand("$9", text, groups), "", "ないグループは空");
+
+        let plain = self::matcher("目星", MatchOptions::default());
+        assert_eq!(
+            plain.expand("$1", text, groups),
+            "$1",
+            "正規表現でなければそのまま"
+        );
+    }
+
+    #[test]
+    fn replace_all_applies_from_the_end() {
+        let mut scenario = Scenario::default();
+        let scene = scenario.scenes.first_mut().expect("サンプルのシーンがある");
+        scene.title = String::from("aXbXc");
+        let scene_id = scene.id;
+
+        let matcher = matcher("X", MatchOptions::default());
+        let replacements: Vec<Replacement> = search(&scenario, &matcher)
+            .into_iter()
+            .filter(|hit| hit.scene_id == scene_id && hit.location == Location::SceneTitle)
+            .map(|hit| Replacement {
+                hit,
+                text: String::from("長い置換"),
+            })
+            .collect();
+        assert_eq!(replacements.len(), 2, "タイトルに2箇所一致する");
+
+        assert_eq!(
+            replace_all(&mut scenario, &replacements),
+            2,
+            "2箇所置換する"
+        );
+        assert_eq!(
+            field_text(&scenario, scene_id, Location::SceneTitle),
+            Some("a長い置換b長い置換c"),
+            "前の置換で後ろの範囲がずれない"
+        );
+    }
+}
@@ -0,0 +1,524 @@
+use crate::scenario::Scenario;
+use crate::scene::{Id, Judge, Mode};
+
+/// ゲームシステム（判定の段階のプリセット）
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GameSystem {
+    /// 大成功/成功/失敗/ファンブルの汎用4段階
+    #[default]
+    Generic,
+    Coc6,
+    Coc7,
+    SwordWorld25,
+    Emoklore,
+}
+
+/// 判定結果の大まかな分類（システム間の対応付けに使う）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Critical,
+    Special,
+    Success,
+    Failure,
+    Fumble,
+}
+
+impl Outcome {
+    /// 対応する判定がない場合に次に近い分類
+    pub fn fallbacks(self) -> &'static [Self] {
+        match self {
+            Self::Critical => &[Self::Special, Self::Success],
+            Self::Special => &[Self::Success, Self::Critical],
+            Self::Success => &[Self::Special, Self::Critical],
+            Self::Failure => &[Self::Fumble],
+            Self::Fumble => &[Self::Failure],
+        }
+    }
+}
+
+impl GameSystem {
+    pub const ALL: [Self; 5] = [
+        Self::Generic,
+        Self::Coc6,
+        Self::Coc7,
+        Self::SwordWorld25,
+        Self::Emoklore,
+    ];
+
+    /// 表示名
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Generic => "汎用",
+            Self::Coc6 => "クトゥルフ神話TRPG（6版）",
+            Self::Coc7 => "新クトゥルフ神話TRPG（7版）",
+            Self::SwordWorld25 => "ソード・ワールド2.5",
+            Self::Emoklore => "エモクロアTRPG",
+        }
+    }
+
+    /// 判定の段階（良い結果から順）
+    pub fn judges(self) -> &'static [(&'static str, Outcome)] {
+        match self {
+            Self::Generic => &[
+                ("大成功", Outcome::Critical),
+                ("成功", Outcome::Success),
+                ("失敗", Outcome::Failure),
+                ("ファンブル", Outcome::Fumble),
+            ],
+            Self::Coc6 => &[
+                ("決定的成功", Outcome::Critical),
+                ("スペシャル", Outcome::Special),
+                ("成功", Outcome::Success),
+                ("失敗", Outcome::Failure),
+                ("致命的失敗", Outcome::Fumble),
+            ],
+            Self::Coc7 => &[
+                ("クリティカル", Outcome::Critical),
+                ("イクストリーム", Outcome::Special),
+                ("ハード", Outcome::Special),
+                ("レギュラー", Outcome::Success),
+                ("失敗", Outcome::Failure),
+                ("ファンブル", Outcome::Fumble),
+            ],
+            Self::SwordWorld25 => &[
+                ("自動成功", Outcome::Critical),
+                ("成功", Outcome::Success),
+                ("失敗", Outcome::Failure),
+                ("自動失敗", Outcome::Fumble),
+            ],
+            Self::Emoklore => &[
+                ("ミラクル", Outcome::Critical),
+                ("トリプル", Outcome::Special),
+                ("ダブル", Outcome::Special),
+                ("シングル", Outcome::Success),
+                ("失敗", Outcome::Failure),
+                ("ファンブル", Outcome::Fumble),
+            ],
+        }
+    }
+
+    /// 判定名の一覧
+    pub fn judge_names(self) -> Vec<String> {
+        self.judges()
+            .iter()
+            .map(|(name, _)| String::from(*name))
+            .collect()
+    }
+
+    /// このシステム用のモード一覧（地の文/プレイヤー/探索）
+    pub fn modes(self) -> Vec<Mode> {
+        vec![
+            Mode::new(String::from("地の文"), vec![String::from("本文")], 1),
+            Mode::new(String::from("プレイヤー"), self.judge_names(), 4),
+            Mode::new(String::from("探索"), self.judge_names(), 4),
+        ]
+    }
+}
+
+/// 判定名から分類を推定する（どのシステムにもない名前はNone）
+pub fn classify(judge: &str) -> Option<Outcome> {
+    let judge = judge.trim();
+    if judge == "大失敗" {
+        return Some(Outcome::Fumble);
+    }
+    GameSystem::ALL
+        .iter()
+        .flat_map(|system| system.judges())
+        .find(|(name, _)| *name == judge)
+        .map(|(_, outcome)| *outcome)
+}
+
+/// 1つのモードの判定の付け替え計画
+pub struct ModeRemap {
+    pub mode_id: Id,
+    /// 付け替え後の判定一覧
+    pub new_judges: Vec<Judge>,
+    /// 旧判定ID -> 新判定ID（Noneは破棄）
+    pub mapping: Vec<(Id, Option<Id>)>,
+    /// 複数の旧判定のテキストが1つに統合されるスロット数
+    pub merged_slots: usize,
+    /// 破棄される空でないテキストの数
+    pub dropped_texts: usize,
+}
+
+impl ModeRemap {
+    /// 旧判定IDの付け替え先
+    pub fn target(&self, old: Id) -> Option<Id> {
+        self.mapping
+            .iter()
+            .find(|(id, _)| *id == old)
+            .and_then(|(_, new)| *new)
+    }
+
+    /// 付け替え先の判定名
+    pub fn target_name(&self, old: Id) -> Option<&str> {
+        let new = self.target(old)?;
+        self.new_judges
+            .iter()
+            .find(|judge| judge.id == new)
+            .map(|judge| judge.name.as_str())
+    }
+}
+
+/// ゲームシステム切り替えの計画（適用前のプレビュー用）
+pub struct SystemSwitchPlan {
+    pub system: GameSystem,
+    pub modes: Vec<ModeRemap>,
+}
+
+impl SystemSwitchPlan {
+    /// `scenario`を`system`に切り替える計画を作る
+    ///
+    /// 判定が2つ以上あるモードだけが対象で、地の文のような判定1つのモードは変更しない。
+    /// 旧判定は同名の判定、同じ分類の判定、近い分類の判定の順に対応付ける。
+    pub fn new(scenario: &Scenario, system: GameSystem) -> Self {
+        let modes = scenario
+            .modes
+            .iter()
+            .filter(|mode| mode.judges.len() > 1)
+            .map(|mode| {
+                let new_judges: Vec<Judge> =
+                    system.judge_names().into_iter().map(Judge::new).collect();
+                let mapping: Vec<(Id, Option<Id>)> = mode
+                    .judges
+                    .iter()
+                    .map(|judge| {
+                        let new = map_judge(&judge.name, system)
+                            .and_then(|index| new_judges.get(index))
+                            .map(|judge| judge.id);
+                        (judge.id, new)
+                    })
+                    .collect();
+
+                let mut merged_slots = 0;
+                let mut dropped_texts = 0;
+                for slot in scenario
+                    .scenes
+                    .iter()
+                    .flat_map(|scene| scene.slots(mode.id))
+                {
+                    let mut targets = Vec::new();
+                    for (old, text) in &slot.texts {
+                        if text.trim().is_empty() {
+                            continue;
+                        }
+                        match mapping
+                            .iter()
+                            .find(|(id, _)| id == old)
+                            .and_then(|(_, new)| *new)
+                        {
+                            Some(new) => targets.push(new),
+                            None => dropped_texts += 1,
+                        }
+                    }
+                    let filled = targets.len();
+                    targets.sort();
+                    targets.dedup();
+                    if targets.len() < filled {
+                        merged_slots += 1;
+                    }
+                }
+
+                ModeRemap {
+                    mode_id: mode.id,
+                    new_judges,
+                    mapping,
+                    merged_slots,
+                    dropped_texts,
+                }
+            })
+            .collect();
+
+        Self { system, modes }
+    }
+
+    /// 計画をシナリオに適用する
+    pub fn apply(&self, scenario: &mut Scenario) {
+        for remap in &self.modes {
+            let Some(mode) = scenario
+                .modes
+                .iter_mut()
+                .find(|mode| mode.id == remap.mode_id)
+            else {
+                continue;
+            };
+            mode.judges.clone_from(&remap.new_judges);
+            let first_judge = remap
+                .new_judges
+                .first()
+                .map(|judge| judge.id)
+                .unwrap_or_default();
+
+            for slot in scenario
+                .scenes
+                .iter_mut()
+                .flat_map(|scene| scene.slots_mut(remap.mode_id).iter_mut())
+            {
+                let old_texts = std::mem::take(&mut slot.texts);
+                // 旧判定の並び順で統合する
+                let mut ordered: Vec<(Id, String)> = remap
+                    .mapping
+                    .iter()
+                    .filter_map(|(old, _)| old_texts.get(old).map(|text| (*old, text.clone())))
+                    .collect();
+                ordered.retain(|(_, text)| !text.trim().is_empty());
+
+                for judge in &remap.new_judges {
+                    slot.texts.insert(judge.id, String::new());
+                }
+                for (old, text) in ordered {
+                    let Some(target) = remap.target(old).and_then(|new| slot.texts.get_mut(&new))
+                    else {
+                        continue;
+                    };
+                    // 統合されるテキストは空行で区切る
+                    if !target.is_empty() {
+                        target.push_str("\n\n");
+                    }
+                    target.push_str(&text);
+                }
+                slot.selected_judge = remap.target(slot.selected_judge).unwrap_or(first_judge);
+            }
+        }
+
+        for scene in &mut scenario.scenes {
+            scene.layout_cache = None;
+        }
+        scenario.system = self.system;
+    }
+}
+
+/// 旧判定名に対応する新システムの判定インデックス
+fn map_judge(judge: &str, system: GameSystem) -> Option<usize> {
+    let judges = system.judges();
+    if let Some(index) = judges.iter().position(|(name, _)| *name == judge) {
+        return Some(index);
+    }
+    let outcome = classify(judge)?;
+    std::iter::once(outcome)
+        .chain(outcome.fallbacks().iter().copied())
+        .find_map(|outcome| judges.iter().position(|(_, o)| *o == outcome))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// モード名からモードIDを探す
+    fn mode_id(scenario: &Scenario, name: &str) -> Id {
+        scenario
+            .modes
+            .iter()
+            .find(|mode| mode.name == name)
+            .map(|mode| mode.id)
+            .expect("サンプルのモードがある")
+    }
+
+    /// 指定シーンの指定モードの最初のスロットの（判定名, テキスト）一覧
+    fn texts(scenario: &Scenario, scene: usize, mode: &str) -> Vec<(String, String)> {
+        let mode = scenario
+            .mode(mode_id(scenario, mode))
+            .expect("サンプルのモードがある");
+        let slot = scenario
+            .scenes
+            .get(scene)
+            .and_then(|scene| scene.slots(mode.id).first())
+            .expect("サンプルのスロットがある");
+        mode.judges
+            .iter()
+            .map(|judge| (judge.name.clone(), String::from(slot.text(judge.id))))
+            .collect()
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected
+            .iter()
+            .map(|(judge, text)| (String::from(*judge), String::from(*text)))
+            .collect()
+    }
+
+    fn remap<'a>(plan: &'a SystemSwitchPlan, scenario: &Scenario, mode: &str) -> &'a ModeRemap {
+        let id = mode_id(scenario, mode);
+        plan.modes
+            .iter()
+            .find(|remap| remap.mode_id == id)
+            .expect("判定が複数あるモードは計画に含まれる")
+    }
+
+    #[test]
+    fn classifies_judge_names() {
+        assert_eq!(
+            classify("大失敗"),
+            Some(Outcome::Fumble),
+            "大失敗はファンブル扱い"
+        );
+        assert_eq!(
+            classify(" 成功 "),
+            Some(Outcome::Success),
+            "前後の空白は無視する"
+        );
+        assert_eq!(
+            classify("スペシャル"),
+            Some(Outcome::Special),
+            "6版のスペシャル"
+        );
+        assert_eq!(classify("謎"), None, "どのシステムにもない名前");
+    }
+
+    #[test]
+    fn maps_judges_by_name_then_outcome() {
+        assert_eq!(
+            map_judge("成功", GameSystem::Coc6),
+            Some(2),
+            "同名の判定を優先する"
+        );
+        assert_eq!(
+            map_judge("大成功", GameSystem::Coc6),
+            Some(0),
+            "同じ分類の判定（決定的成功）"
+        );
+        assert_eq!(
+            map_judge("スペシャル", GameSystem::Generic),
+            Some(1),
+            "スペシャルがなければ成功"
+        );
+        assert_eq!(
+            map_judge("大失敗", GameSystem::SwordWorld25),
+            Some(3),
+            "大失敗は自動失敗"
+        );
+        assert_eq!(
+            map_judge("謎", GameSystem::Generic),
+            None,
+            "分類できない判定は破棄"
+        );
+    }
+
+    #[test]
+    fn generic_to_coc6_keeps_every_text() {
+        let mut scenario = Scenario::default();
+        let plan = SystemSwitchPlan::new(&scenario, GameSystem::Coc6);
+        assert_eq!(plan.modes.len(), 2, "判定が1つの地の文は対象外");
+        for mode in ["プレイヤー", "探索"] {
+            let remap = remap(&plan, &scenario, mode);
+            assert_eq!(remap.merged_slots, 0, "{mode}: 統合されるスロットはない");
+            assert_eq!(remap.dropped_texts, 0, "{mode}: 破棄されるテキストはない");
+        }
+
+        plan.apply(&mut scenario);
+        assert_eq!(scenario.system, GameSystem::Coc6, "システムを切り替える");
+        assert_eq!(
+            texts(&scenario, 0, "プレイヤー"),
+            pairs(&[
+                ("決定的成功", "大成功1"),
+                ("スペシャル", ""),
+                ("成功", "成功1"),
+                ("失敗", "失敗1"),
+                ("致命的失敗", "ファンブル1"),
+            ]),
+            "同じ分類の判定に付け替える"
+        );
+        assert_eq!(
+            texts(&scenario, 0, "地の文"),
+            pairs(&[("本文", "地の文1")]),
+            "地の文はそのまま"
+        );
+    }
+
+    #[test]
+    fn coc6_to_generic_merges_special_into_success() {
+        let mut scenario = Scenario::default();
+        SystemSwitchPlan::new(&scenario, GameSystem::Coc6).apply(&mut scenario);
+        let player = mode_id(&scenario, "プレイヤー");
+        let special = scenario
+            .mode(player)
+            .and_then(|mode| mode.judges.iter().find(|judge| judge.name == "スペシャル"))
+            .map(|judge| judge.id)
+            .expect("6版の判定にスペシャルがある");
+        let slot = scenario
+            .scenes
+            .first_mut()
+            .and_then(|scene| scene.slots_mut(player).first_mut())
+            .expect("サンプルのスロットがある");
+        *slot.text_mut(special) = String::from("スペシャル1");
+        slot.selected_judge = special;
+
+        let plan = SystemSwitchPlan::new(&scenario, GameSystem::Generic);
+        let remap_player = remap(&plan, &scenario, "プレイヤー");
+        assert_eq!(
+            remap_player.merged_slots, 1,
+            "スペシャルと成功のテキストがあるスロットは1つ"
+        );
+        assert_eq!(remap_player.dropped_texts, 0, "破棄されるテキストはない");
+        assert_eq!(
+            remap_player.target_name(special),
+            Some("成功"),
+            "スペシャルは成功へ"
+        );
+        assert_eq!(
+            remap(&plan, &scenario, "探索").merged_slots,
+            0,
+            "探索は統合されない"
+        );
+
+        plan.apply(&mut scenario);
+        assert_eq!(
+            texts(&scenario, 0, "プレイヤー"),
+            pairs(&[
+                ("大成功", "大成功1"),
+                ("成功", "スペシャル1\n\n成功1"),
+                ("失敗", "失敗1"),
+                ("ファンブル", "ファンブル1"),
+            ]),
+            "統合するテキストは元の判定の順に空行で区切る"
+        );
+        assert_eq!(
+            texts(&scenario, 1, "プレイヤー"),
+            pairs(&[
+                ("大成功", "大成功2"),
+                ("成功", "成功2"),
+                ("失敗", "失敗2"),
+                ("ファンブル", "ファンブル2"),
+            ]),
+            "ほかのシーンは元の判定に戻る"
+        );
+        let slot = scenario
+            .scenes
+            .first()
+            .and_then(|scene| scene.slots(player).first())
+            .expect("サンプルのスロットがある");
+        let selected = scenario
+            .mode(player)
+            .and_then(|mode| mode.judge(slot.selected_judge))
+            .map(|judge| judge.name.as_str());
+        assert_eq!(selected, Some("成功"), "選択中の判定も付け替え先になる");
+    }
+
+    #[test]
+    fn unknown_judges_are_dropped() {
+        let mut scenario = Scenario::default();
+        let player = mode_id(&scenario, "プレイヤー");
+        let mode = scenario
+            .modes
+            .iter_mut()
+            .find(|mode| mode.id == player)
+            .expect("サンプルのモードがある");
+        let judge = mode.judges.last_mut().expect("判定がある");
+        judge.name = String::from("謎");
+
+        let plan = SystemSwitchPlan::new(&scenario, GameSystem::Coc6);
+        assert_eq!(
+            remap(&plan, &scenario, "プレイヤー").dropped_texts,
+            3,
+            "3シーンの「謎」のテキストを破棄する"
+        );
+        plan.apply(&mut scenario);
+        assert_eq!(
+            texts(&scenario, 0, "プレイヤー")
+                .last()
+                .map(|(_, text)| text.as_str()),
+            Some(""),
+            "致命的失敗には何も入らない"
+        );
+    }
+}
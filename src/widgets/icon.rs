@@ -0,0 +1,218 @@
+use egui::{Response, Ui};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::hash::{DefaultHasher, Hash as _, Hasher as _};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// 読み込むアイコン画像の最大辺（大きい画像は縮小してからテクスチャにする）
+const ICON_TEXTURE_SIZE: u32 = 128;
+
+/// 画像ファイルの更新日時を確認し直す間隔（秒）
+const RECHECK_INTERVAL: f64 = 2.0;
+
+/// 画像ファイルから読み込んだアイコンのテクスチャキャッシュ
+///
+/// 画像ファイルが更新/作成されたら読み直す（読み込みに失敗した画像も更新されれば読み直す）。
+#[derive(Default)]
+pub struct IconCache {
+    textures: HashMap<PathBuf, CachedIcon>,
+}
+
+/// 読み込んだアイコン1つ分
+struct CachedIcon {
+    /// テクスチャ（読み込みに失敗した場合はNone、毎フレーム読み直さないよう記録する）
+    texture: Option<egui::TextureHandle>,
+    /// 読み込んだときの画像ファイルの更新日時（ファイルがなければNone）
+    modified: Option<SystemTime>,
+    /// 最後に更新日時を確認した時刻（`egui::InputState::time`）
+    checked: f64,
+}
+
+impl CachedIcon {
+    fn load(ctx: &egui::Context, path: &Path, now: f64) -> Self {
+        Self {
+            modified: modified(path),
+            texture: load_texture(ctx, path),
+            checked: now,
+        }
+    }
+}
+
+/// ファイルの更新日時（ファイルがなければNone）
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+impl IconCache {
+    /// パスの画像のテクスチャ（初回と画像ファイルが変わったときだけディスクから読み込む）
+    pub fn texture(&mut self, ctx: &egui::Context, path: &Path) -> Option<&egui::TextureHandle> {
+        let now = ctx.input(|i| i.time);
+        let icon = match self.textures.entry(path.to_path_buf()) {
+            Entry::Vacant(entry) => entry.insert(CachedIcon::load(ctx, path, now)),
+            Entry::Occupied(entry) => {
+                let icon = entry.into_mut();
+                if now - icon.checked >= RECHECK_INTERVAL {
+                    icon.checked = now;
+                    if modified(path) != icon.modified {
+                        *icon = CachedIcon::load(ctx, path, now);
+                    }
+                }
+                icon
+            }
+        };
+        icon.texture.as_ref()
+    }
+}
+
+fn load_texture(ctx: &egui::Context, path: &Path) -> Option<egui::TextureHandle> {
+    let image = match image::open(path) {
+        Ok(image) => image,
+        Err(err) => {
+            log::warn!("アイコンを読み込めませんでした: {}: {err}", path.display());
+            return None;
+        }
+    };
+    let image = image
+        .thumbnail(ICON_TEXTURE_SIZE, ICON_TEXTURE_SIZE)
+        .to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    let color_image =
+        egui::ColorImage::from_rgba_unmultiplied(size, image.as_flat_samples().as_slice());
+    Some(ctx.load_texture(
+        path.to_string_lossy(),
+        color_image,
+        egui::TextureOptions::LINEAR,
+    ))
+}
+
+/// アイコンを表示（画像がない/読めない場合は名前から生成したプレースホルダー）
+pub fn show_icon(
+    ui: &mut Ui,
+    cache: &mut IconCache,
+    path: Option<&Path>,
+    name: &str,
+    size: f32,
+) -> Response {
+    let texture = path.and_then(|path| cache.texture(ui.ctx(), path));
+    match texture {
+        Some(texture) => ui.add(
+            egui::Image::from_texture(texture)
+                .fit_to_exact_size(egui::vec2(size, size))
+                .corner_radius(4.0)
+                .sense(egui::Sense::click()),
+        ),
+        None => show_placeholder(ui, name, size),
+    }
+}
+
+/// 名前の頭文字と名前から決めた色のプレースホルダー
+fn show_placeholder(ui: &mut Ui, name: &str, size: f32) -> Response {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::click());
+    let painter = ui.painter();
+    match name.chars().next() {
+        Some(initial) => {
+            painter.rect_filled(rect, 4.0, placeholder_color(name));
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                initial,
+                egui::FontId::proportional(size * 0.5),
+                egui::Color32::WHITE,
+            );
+        }
+        None => {
+            // 話者なし
+            painter.rect_stroke(
+                rect,
+                4.0,
+                ui.visuals().widgets.noninteractive.bg_stroke,
+                egui::StrokeKind::Inside,
+            );
+        }
+    }
+    response
+}
+
+/// 名前ごとに一定の色
+fn placeholder_color(name: &str) -> egui::Color32 {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    let hue = (hasher.finish() % 360) as f32 / 360.0;
+    egui::ecolor::Hsva::new(hue, 0.45, 0.55, 1.0).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `time`秒の時点の1フレームでテクスチャの大きさを調べる
+    fn texture_size(
+        ctx: &egui::Context,
+        cache: &mut IconCache,
+        path: &Path,
+        time: f64,
+    ) -> Option<[usize; 2]> {
+        let raw = egui::RawInput {
+            time: Some(time),
+            ..egui::RawInput::default()
+        };
+        let mut size = None;
+        let _output: egui::FullOutput = ctx.run(raw, |ctx| {
+            size = cache.texture(ctx, path).map(egui::TextureHandle::size);
+        });
+        size
+    }
+
+    /// 縦横の比が違えば縮小後のテクスチャの大きさも違う
+    fn write_image(path: &Path, width: u32, height: u32, modified: SystemTime) {
+        image::RgbaImage::new(width, height)
+            .save(path)
+            .expect("画像を書き込める");
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(modified))
+            .expect("更新日時を設定できる");
+    }
+
+    #[test]
+    fn reloads_when_file_appears_or_changes() {
+        let dir = std::env::temp_dir().join(format!("icon-cache-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("一時ディレクトリを作れる");
+        let path = dir.join("icon.png");
+        if path.exists() {
+            std::fs::remove_file(&path).expect("前回の画像を消せる");
+        }
+        let ctx = egui::Context::default();
+        let mut cache = IconCache::default();
+
+        assert_eq!(
+            texture_size(&ctx, &mut cache, &path, 0.0),
+            None,
+            "ファイルがない"
+        );
+        let start = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        write_image(&path, 2, 1, start);
+        assert_eq!(
+            texture_size(&ctx, &mut cache, &path, 1.0),
+            None,
+            "確認の間隔が空くまでは失敗を覚えておく"
+        );
+        assert_eq!(
+            texture_size(&ctx, &mut cache, &path, 3.0),
+            Some([128, 64]),
+            "作成されたファイルを読み込む"
+        );
+
+        write_image(&path, 1, 2, start + std::time::Duration::from_secs(10));
+        assert_eq!(
+            texture_size(&ctx, &mut cache, &path, 6.0),
+            Some([64, 128]),
+            "更新されたファイルを読み直す"
+        );
+        std::fs::remove_dir_all(&dir).expect("一時ディレクトリを消せる");
+    }
+}
@@ -1 +1,2 @@
 pub mod combobox;
+pub mod icon;